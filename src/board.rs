@@ -1,16 +1,26 @@
+use crate::gallery::GalleryConfig;
+use crate::python::{self, Namespace, SandboxConfig};
+use crate::worker::WorkerPool;
+use crate::error::ScryError;
+use crate::render::{self, Quality, RenderConfig, RenderOutput};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use chrono::{DateTime, Utc};
-use pyo3::Py;
-use pyo3::types::PyDict;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::{RwLock, broadcast};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedMutexGuard, RwLock, Semaphore, broadcast};
+use tokio_util::sync::CancellationToken;
 
 #[derive(Clone, Debug)]
 #[allow(dead_code)] // fields stored for future history/undo support
 pub struct Snapshot {
     pub svg: String,
     pub png: Vec<u8>,
+    /// Pixel size of `png`.
+    pub width: u32,
+    pub height: u32,
     pub timestamp: DateTime<Utc>,
 }
 
@@ -20,33 +30,164 @@ pub struct Board {
     pub height: u32,
     pub svg: String,
     pub png: Vec<u8>,
-    pub namespace: Py<PyDict>,
+    /// Downscaled copy of `png` for the gallery grid and list output.
+    pub thumb_png: Vec<u8>,
+    pub namespace: Namespace,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub history: Vec<Snapshot>,
+    /// `random` seed used for the current render, if the caller supplied one.
+    pub seed: Option<u64>,
+    /// Free-form labels for filtering, see [`normalize_tags`].
+    pub tags: Vec<String>,
+    /// Reference board that automatic cleanup must never remove.
+    pub pinned: bool,
+    /// Message from the most recent failed run, cleared by the next success.
+    pub last_error: Option<String>,
+    /// What the board is for, see [`normalize_description`].
+    pub description: Option<String>,
+    /// Secret for the board's `/b/{token}` share link, see [`AppState::issue_share_token`].
+    pub share_token: String,
+}
+
+/// Snapshots kept per board; the oldest is dropped first.
+pub const MAX_HISTORY: usize = 50;
+
+impl Board {
+    /// Push the current render onto `history`, dropping the oldest snapshot
+    /// past [`MAX_HISTORY`]. Does nothing for a board that hasn't rendered.
+    pub fn push_history(&mut self) {
+        if self.svg.is_empty() {
+            return;
+        }
+        if self.history.len() >= MAX_HISTORY {
+            self.history.remove(0);
+        }
+        let (width, height) = render::png_dimensions(&self.png).unwrap_or((self.width, self.height));
+        self.history.push(Snapshot {
+            svg: self.svg.clone(),
+            png: self.png.clone(),
+            width,
+            height,
+            timestamp: self.updated_at,
+        });
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct BoardEvent {
+    /// Sequence number, increasing across all boards, assigned on publish.
+    pub id: u64,
     pub board_name: String,
     pub event_type: BoardEventType,
+    /// Pixel size of the board's image after a render, so clients can lay out
+    /// before fetching it. None for events that don't render.
+    pub size: Option<(u32, u32)>,
+    /// The rendered size differs from the image the board showed before
+    /// (always true for a board's first image).
+    pub dimensions_changed: bool,
 }
 
 #[derive(Clone, Debug)]
 pub enum BoardEventType {
     Created,
     Updated,
+    Deleted,
+}
+
+/// Held while a board is being executed or stored, see [`AppState::lock_board`].
+///
+/// Dropping the last holder removes the board's lock entry, so deleted boards
+/// (and names that never became boards) don't leave one behind.
+pub struct BoardGuard<'a> {
+    state: &'a AppState,
+    name: String,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl Drop for BoardGuard<'_> {
+    fn drop(&mut self) {
+        let mut locks = self.state.board_locks.lock().unwrap();
+        // Waiters clone the Arc under this map lock, so a count of two (the
+        // map's and ours) means nobody else is holding or waiting for it
+        if locks.get(&self.name).is_some_and(|lock| Arc::strong_count(lock) == 2) {
+            locks.remove(&self.name);
+        }
+    }
 }
 
 pub struct AppState {
     pub boards: RwLock<HashMap<String, Board>>,
-    pub event_tx: broadcast::Sender<BoardEvent>,
+    /// Per-board execution locks, see [`AppState::lock_board`].
+    board_locks: std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    /// Live events; send through [`AppState::publish`] so ids stay ordered.
+    event_tx: broadcast::Sender<BoardEvent>,
+    /// Recent events kept for reconnecting clients, see [`AppState::subscribe_since`].
+    event_log: std::sync::Mutex<EventLog>,
+    /// Share token → board name, for `/b/{token}` links.
+    share_tokens: std::sync::Mutex<HashMap<String, String>>,
     pub gallery_addr: Option<(String, u16)>,
     pub output_dir: Option<PathBuf>,
+    /// Source of `whiteboard_template` code, see [`crate::templates`].
+    pub template_dir: Option<PathBuf>,
+    pub sandbox: SandboxConfig,
+    /// Worker processes holding board namespaces, see [`AppState::new_namespace`].
+    pub workers: Option<WorkerPool>,
+    pub gallery: GalleryConfig,
+    pub render: RenderConfig,
+    /// Permits for [`AppState::render_png`], one per allowed concurrent render.
+    render_slots: Semaphore,
+    /// Global `whiteboard` execution budget, see [`AppState::try_acquire_exec`].
+    exec_limiter: Option<std::sync::Mutex<TokenBucket>>,
+    /// Cancelled once on Ctrl-C/SIGTERM or when the MCP session ends.
+    /// Long-lived work (gallery, SSE streams) should stop when it fires.
+    pub shutdown: CancellationToken,
+    /// When the server started, for uptime reporting.
+    pub started: Instant,
 }
 
 pub type SharedState = Arc<AppState>;
 
+/// Events retained for replay to reconnecting gallery clients.
+const EVENT_REPLAY_LEN: usize = 64;
+
+#[derive(Default)]
+struct EventLog {
+    last_id: u64,
+    recent: std::collections::VecDeque<BoardEvent>,
+}
+
+/// Token bucket allowing `rate` operations per second on average, with bursts
+/// of up to `rate`.
+pub struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate: u32, now: Instant) -> Self {
+        TokenBucket {
+            rate: rate as f64,
+            tokens: rate as f64,
+            last: now,
+        }
+    }
+
+    /// Take one token, or report how long until one is available.
+    pub fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.rate))
+        }
+    }
+}
+
 /// Maximum board name length in bytes.
 const MAX_NAME_LEN: usize = 128;
 
@@ -68,6 +209,45 @@ pub fn validate_board_name(name: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Maximum number of tags on one board.
+const MAX_TAGS: usize = 32;
+
+/// Validate tags (same rules as board names) and drop duplicates, keeping
+/// first-seen order.
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    if tags.len() > MAX_TAGS {
+        return Err(format!("Too many tags ({}, max {MAX_TAGS})", tags.len()));
+    }
+    let mut out: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        validate_board_name(tag).map_err(|e| format!("Invalid tag {tag:?}: {}", e.replace("Board name", "Tag")))?;
+        if !out.contains(tag) {
+            out.push(tag.clone());
+        }
+    }
+    Ok(out)
+}
+
+/// Longest board description, in characters.
+const MAX_DESCRIPTION_CHARS: usize = 1000;
+
+/// Trim a description and check its length; empty clears it (`None`).
+/// Newlines and tabs are kept, other control characters are rejected.
+pub fn normalize_description(description: &str) -> Result<Option<String>, String> {
+    let description = description.trim();
+    if description.is_empty() {
+        return Ok(None);
+    }
+    let chars = description.chars().count();
+    if chars > MAX_DESCRIPTION_CHARS {
+        return Err(format!("Description too long ({chars} characters, max {MAX_DESCRIPTION_CHARS})"));
+    }
+    if description.chars().any(|c| c.is_control() && c != '\n' && c != '\t') {
+        return Err("Description must not contain control characters".into());
+    }
+    Ok(Some(description.to_string()))
+}
+
 /// Escape a string for safe embedding in HTML text content and attributes.
 pub fn html_escape(s: &str) -> String {
     s.replace('&', "&amp;")
@@ -104,25 +284,340 @@ pub fn sanitize_filename(name: &str) -> String {
         .collect()
 }
 
+/// Filename stem for a board's output files.
+///
+/// Names that survive [`sanitize_filename`] unchanged are used as-is. Anything
+/// else gets a short hash of the original name appended, so names differing
+/// only in replaced characters ("café", "caf?", "caf_") never share files.
+pub fn board_file_stem(name: &str) -> String {
+    let safe = sanitize_filename(name);
+    if safe == name {
+        return safe;
+    }
+    // FNV-1a: stable across builds, unlike std's DefaultHasher
+    let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{safe}-{:08x}", hash as u32)
+}
+
+/// Largest archive `whiteboard_export_all` will return.
+pub const MAX_EXPORT_BYTES: usize = 64 * 1024 * 1024;
+
+/// One board's files for [`export_zip`].
+pub struct ExportBoard {
+    pub name: String,
+    pub png: Vec<u8>,
+    pub svg: String,
+    pub vars: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Bundle boards into a zip: `{stem}.png`, `{stem}.svg` and, when present,
+/// `{stem}.vars.json` per board. Boards with no render yet contribute only
+/// their variables.
+///
+/// Stems come from [`board_file_stem`]; should two still coincide, later ones
+/// get a `-2`, `-3`, ... suffix. PNGs are stored as-is since they're already
+/// compressed; text files are deflated.
+pub fn export_zip(boards: &[ExportBoard]) -> Result<Vec<u8>, ScryError> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    let zip_err = |e: zip::result::ZipError| ScryError::Internal(format!("Zip export failed: {e}"));
+    let io_err = |e: std::io::Error| ScryError::Internal(format!("Zip export failed: {e}"));
+    let stored = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let mut used = std::collections::HashSet::new();
+    for board in boards {
+        let base = board_file_stem(&board.name);
+        let mut stem = base.clone();
+        let mut n = 1;
+        while !used.insert(stem.clone()) {
+            n += 1;
+            stem = format!("{base}-{n}");
+        }
+
+        if !board.png.is_empty() {
+            zip.start_file(format!("{stem}.png"), stored).map_err(zip_err)?;
+            zip.write_all(&board.png).map_err(io_err)?;
+        }
+        if !board.svg.is_empty() {
+            zip.start_file(format!("{stem}.svg"), deflated).map_err(zip_err)?;
+            zip.write_all(board.svg.as_bytes()).map_err(io_err)?;
+        }
+        if let Some(ref vars) = board.vars {
+            let json = serde_json::to_vec_pretty(vars)
+                .map_err(|e| ScryError::Internal(format!("Zip export failed: {e}")))?;
+            zip.start_file(format!("{stem}.vars.json"), deflated).map_err(zip_err)?;
+            zip.write_all(&json).map_err(io_err)?;
+        }
+    }
+    Ok(zip.finish().map_err(zip_err)?.into_inner())
+}
+
+/// Read back an [`export_zip`] archive, one entry per file stem, sorted by
+/// name. The stem becomes the board name. PNGs are ignored: the SVG is the
+/// board's source and gets rendered again. Other files are skipped, and
+/// decompressed content is capped at [`MAX_EXPORT_BYTES`] in total.
+pub fn import_zip(bytes: &[u8]) -> Result<Vec<ExportBoard>, String> {
+    use std::io::Read;
+
+    let invalid = |msg: String| format!("Zip import failed: {msg}");
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).map_err(|e| invalid(e.to_string()))?;
+    let mut boards = std::collections::BTreeMap::new();
+    let mut budget = MAX_EXPORT_BYTES as u64;
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).map_err(|e| invalid(e.to_string()))?;
+        let path = file.name().to_string();
+        let (stem, kind) = if let Some(stem) = path.strip_suffix(".vars.json") {
+            (stem, "vars")
+        } else if let Some(stem) = path.strip_suffix(".svg") {
+            (stem, "svg")
+        } else {
+            continue;
+        };
+        if stem.contains('/') || validate_board_name(stem).is_err() {
+            continue;
+        }
+        let mut data = Vec::new();
+        file.take(budget + 1).read_to_end(&mut data).map_err(|e| invalid(format!("{path}: {e}")))?;
+        if data.len() as u64 > budget {
+            return Err(invalid(format!("archive expands past {MAX_EXPORT_BYTES} bytes")));
+        }
+        budget -= data.len() as u64;
+
+        let board = boards.entry(stem.to_string()).or_insert_with(|| ExportBoard {
+            name: stem.to_string(),
+            png: Vec::new(),
+            svg: String::new(),
+            vars: None,
+        });
+        if kind == "svg" {
+            board.svg = String::from_utf8(data).map_err(|_| invalid(format!("{path} is not UTF-8")))?;
+        } else {
+            board.vars = Some(serde_json::from_slice(&data).map_err(|e| invalid(format!("{path}: {e}")))?);
+        }
+    }
+    Ok(boards.into_values().collect())
+}
+
 impl AppState {
-    pub fn new(gallery_addr: Option<(String, u16)>, output_dir: Option<PathBuf>) -> SharedState {
+    pub fn new(
+        gallery_addr: Option<(String, u16)>,
+        output_dir: Option<PathBuf>,
+        template_dir: Option<PathBuf>,
+        sandbox: SandboxConfig,
+        gallery: GalleryConfig,
+        render: RenderConfig,
+    ) -> SharedState {
         let (event_tx, _) = broadcast::channel(64);
+        let sandbox_rate = sandbox.max_exec_per_sec;
+        let render_slots = Semaphore::new(render.max_concurrent_renders.max(1));
         Arc::new(AppState {
             boards: RwLock::new(HashMap::new()),
+            board_locks: std::sync::Mutex::new(HashMap::new()),
             event_tx,
+            event_log: std::sync::Mutex::new(EventLog::default()),
+            share_tokens: std::sync::Mutex::new(HashMap::new()),
             gallery_addr,
             output_dir,
+            template_dir,
+            workers: WorkerPool::new(&sandbox),
+            sandbox,
+            gallery,
+            render,
+            render_slots,
+            exec_limiter: sandbox_rate
+                .map(|rate| std::sync::Mutex::new(TokenBucket::new(rate, Instant::now()))),
+            shutdown: CancellationToken::new(),
+            started: Instant::now(),
         })
     }
 
-    pub fn board_url(&self, name: &str) -> Option<String> {
+    /// Serialize execute-and-store for a single board.
+    ///
+    /// Hold the guard from before the namespace is read until the result is
+    /// stored. tokio's `Mutex` is FIFO, so calls to the same board apply in
+    /// arrival order with no lost namespace updates or doubled history pushes;
+    /// calls to different boards never contend.
+    pub async fn lock_board(&self, name: &str) -> BoardGuard<'_> {
+        let lock = self
+            .board_locks
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .clone();
+        BoardGuard {
+            state: self,
+            name: name.to_string(),
+            _guard: lock.lock_owned().await,
+        }
+    }
+
+    /// Number the event, remember it for replay, and broadcast it.
+    pub fn publish(&self, board_name: &str, event_type: BoardEventType) {
+        self.publish_event(board_name, event_type, None, false);
+    }
+
+    /// [`AppState::publish`] for a new render, with its pixel size if known.
+    pub fn publish_render(
+        &self,
+        board_name: &str,
+        event_type: BoardEventType,
+        size: Option<(u32, u32)>,
+        dimensions_changed: bool,
+    ) {
+        self.publish_event(board_name, event_type, size, dimensions_changed);
+    }
+
+    fn publish_event(
+        &self,
+        board_name: &str,
+        event_type: BoardEventType,
+        size: Option<(u32, u32)>,
+        dimensions_changed: bool,
+    ) {
+        // Send under the log lock so ids reach subscribers in order
+        let mut log = self.event_log.lock().unwrap();
+        log.last_id += 1;
+        let event = BoardEvent {
+            id: log.last_id,
+            board_name: board_name.to_string(),
+            event_type,
+            size,
+            dimensions_changed,
+        };
+        if log.recent.len() == EVENT_REPLAY_LEN {
+            log.recent.pop_front();
+        }
+        log.recent.push_back(event.clone());
+        let _ = self.event_tx.send(event);
+    }
+
+    /// Subscribe to live events, plus the retained events newer than `since`
+    /// (a previously seen [`BoardEvent::id`]) for a client catching up after a
+    /// reconnect. Nothing is missed or duplicated between the two. Events
+    /// older than the replay window are gone.
+    pub fn subscribe_since(
+        &self,
+        since: Option<u64>,
+    ) -> (Vec<BoardEvent>, broadcast::Receiver<BoardEvent>) {
+        let log = self.event_log.lock().unwrap();
+        let missed = match since {
+            Some(since) => log.recent.iter().filter(|e| e.id > since).cloned().collect(),
+            None => Vec::new(),
+        };
+        (missed, self.event_tx.subscribe())
+    }
+
+    /// Spend one execution from the process-wide budget. On `Err`, the caller
+    /// should reject the call rather than queue it; the duration says when a
+    /// retry can succeed.
+    pub fn try_acquire_exec(&self) -> Result<(), Duration> {
+        match &self.exec_limiter {
+            Some(bucket) => bucket.lock().unwrap().try_take(Instant::now()),
+            None => Ok(()),
+        }
+    }
+
+    /// Rasterize on the blocking pool, waiting for a free render slot first so
+    /// a burst of boards can't tie up every core (or the MCP transport).
+    pub async fn render_png(&self, svg: String, quality: Quality) -> Result<RenderOutput, ScryError> {
+        let config = self.render.clone();
+        self.run_render(move || render::svg_to_png_with_quality(&svg, quality, &config))
+            .await
+    }
+
+    /// Create a board namespace, on a worker process when there are any,
+    /// restoring `vars` into it.
+    pub async fn new_namespace(
+        &self,
+        width: u32,
+        height: u32,
+        vars: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> Result<Namespace, ScryError> {
+        if let Some(ref workers) = self.workers {
+            return workers.create(width, height, vars).await;
+        }
+        let config = self.sandbox.clone();
+        let namespace = match vars {
+            Some(vars) => python::restored_namespace_async(width, height, config, vars).await?,
+            None => python::create_namespace_async(width, height, config).await?,
+        };
+        Ok(Namespace::Local(namespace))
+    }
+
+    /// Run any rasterization job under the same render-slot limit.
+    pub async fn run_render<T: Send + 'static>(
+        &self,
+        job: impl FnOnce() -> Result<T, ScryError> + Send + 'static,
+    ) -> Result<T, ScryError> {
+        let _permit = self
+            .render_slots
+            .acquire()
+            .await
+            .map_err(|e| ScryError::Internal(format!("Render slots closed: {e}")))?;
+        tokio::task::spawn_blocking(job)
+            .await
+            .map_err(|e| ScryError::Internal(format!("Task join error: {e}")))?
+    }
+
+    /// Where clients reach the gallery: `--public-url` if given, else the bind
+    /// address. None when the gallery isn't running.
+    fn gallery_base(&self) -> Option<String> {
         let (ref addr, port) = *self.gallery_addr.as_ref()?;
-        Some(format!(
-            "http://{}:{}/gallery/board/{}",
-            addr,
-            port,
-            url_encode(name)
-        ))
+        Some(match self.gallery.public_url {
+            Some(ref url) => url.clone(),
+            None => format!("http://{addr}:{port}"),
+        })
+    }
+
+    /// Whether board links only open on this machine: no `--public-url`, and
+    /// the gallery is bound to a loopback or wildcard address.
+    pub fn links_are_local(&self) -> bool {
+        if self.gallery.public_url.is_some() {
+            return false;
+        }
+        self.gallery_addr.as_ref().is_some_and(|(addr, _)| {
+            addr == "localhost"
+                || addr
+                    .parse::<std::net::IpAddr>()
+                    .is_ok_and(|ip| ip.is_loopback() || ip.is_unspecified())
+        })
+    }
+
+    pub fn board_url(&self, name: &str) -> Option<String> {
+        Some(format!("{}/gallery/board/{}", self.gallery_base()?, url_encode(name)))
+    }
+
+    /// Mint an unguessable share token for a new board and register it.
+    pub fn issue_share_token(&self, name: &str) -> String {
+        let mut bytes = [0u8; 16];
+        getrandom::fill(&mut bytes).expect("OS random source unavailable");
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+        self.share_tokens
+            .lock()
+            .unwrap()
+            .insert(token.clone(), name.to_string());
+        token
+    }
+
+    /// Board name a share token was issued for, if it is still live.
+    pub fn board_for_token(&self, token: &str) -> Option<String> {
+        self.share_tokens.lock().unwrap().get(token).cloned()
+    }
+
+    /// Invalidate a removed board's share link.
+    pub fn revoke_share_token(&self, token: &str) {
+        self.share_tokens.lock().unwrap().remove(token);
+    }
+
+    /// Public URL of a board's share link, when the gallery is running.
+    pub fn share_url(&self, token: &str) -> Option<String> {
+        Some(format!("{}/b/{token}", self.gallery_base()?))
     }
 }
 
@@ -140,6 +635,22 @@ mod tests {
         assert!(validate_board_name(&"x".repeat(200)).is_err());
     }
 
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+        assert!(bucket.try_take(start).is_ok());
+        assert!(bucket.try_take(start).is_ok());
+        let wait = bucket.try_take(start).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+        assert!(bucket.try_take(start + Duration::from_millis(500)).is_ok());
+        // Idle time refills only up to the burst size
+        let later = start + Duration::from_secs(60);
+        assert!(bucket.try_take(later).is_ok());
+        assert!(bucket.try_take(later).is_ok());
+        assert!(bucket.try_take(later).is_err());
+    }
+
     #[test]
     fn test_html_escape() {
         assert_eq!(html_escape("<script>"), "&lt;script&gt;");
@@ -154,6 +665,165 @@ mod tests {
         assert_eq!(url_encode("a/b"), "a%2Fb");
     }
 
+    #[test]
+    fn test_normalize_tags() {
+        let tags = vec!["diagram".to_string(), "draft".to_string(), "diagram".to_string()];
+        assert_eq!(normalize_tags(&tags).unwrap(), vec!["diagram", "draft"]);
+        assert!(normalize_tags(&[]).unwrap().is_empty());
+
+        let err = normalize_tags(&["a/b".to_string()]).unwrap_err();
+        assert!(err.starts_with("Invalid tag \"a/b\": Tag cannot contain"), "{err}");
+        assert!(normalize_tags(&[String::new()]).is_err());
+        assert!(normalize_tags(&vec!["t".to_string(); MAX_TAGS + 1]).is_err());
+    }
+
+    #[test]
+    fn test_event_replay() {
+        let state = AppState::new(
+            None,
+            None,
+            None,
+            SandboxConfig::default(),
+            GalleryConfig::default(),
+            RenderConfig::default(),
+        );
+        for i in 0..EVENT_REPLAY_LEN + 3 {
+            state.publish(&format!("b{i}"), BoardEventType::Updated);
+        }
+
+        let (missed, mut rx) = state.subscribe_since(Some(EVENT_REPLAY_LEN as u64));
+        let ids: Vec<u64> = missed.iter().map(|e| e.id).collect();
+        assert_eq!(ids, (EVENT_REPLAY_LEN as u64 + 1..=EVENT_REPLAY_LEN as u64 + 3).collect::<Vec<_>>());
+        // Anything older than the window can't be replayed
+        let (missed, _) = state.subscribe_since(Some(0));
+        assert_eq!(missed.len(), EVENT_REPLAY_LEN);
+        assert_eq!(missed[0].id, 4);
+        assert!(state.subscribe_since(None).0.is_empty());
+
+        state.publish("live", BoardEventType::Updated);
+        let live = rx.try_recv().unwrap();
+        assert_eq!((live.id, live.board_name.as_str()), (EVENT_REPLAY_LEN as u64 + 4, "live"));
+    }
+
+    #[test]
+    fn test_push_history() {
+        let namespace = Namespace::Local(pyo3::Python::attach(|py| pyo3::types::PyDict::new(py).unbind()));
+        let now = Utc::now();
+        let mut board = Board {
+            name: "b".into(),
+            width: 10,
+            height: 10,
+            svg: String::new(),
+            png: Vec::new(),
+            thumb_png: Vec::new(),
+            namespace,
+            created_at: now,
+            updated_at: now,
+            history: Vec::new(),
+            seed: None,
+            tags: Vec::new(),
+            pinned: false,
+            last_error: None,
+            description: None,
+            share_token: String::new(),
+        };
+        board.push_history();
+        assert!(board.history.is_empty(), "nothing rendered yet");
+        for i in 0..MAX_HISTORY + 5 {
+            board.svg = format!("<svg id='{i}'/>");
+            board.push_history();
+        }
+        assert_eq!(board.history.len(), MAX_HISTORY);
+        assert_eq!(board.history[0].svg, "<svg id='5'/>", "oldest dropped first");
+        assert_eq!((board.history[0].width, board.history[0].height), (10, 10));
+    }
+
+    #[test]
+    fn test_share_tokens() {
+        let state = AppState::new(
+            Some(("127.0.0.1".into(), 3000)),
+            None,
+            None,
+            SandboxConfig::default(),
+            GalleryConfig::default(),
+            RenderConfig::default(),
+        );
+        let a = state.issue_share_token("a");
+        let b = state.issue_share_token("b");
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 22, "128 bits, unpadded base64");
+        assert!(a.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_'));
+        assert_eq!(state.board_for_token(&a).as_deref(), Some("a"));
+        assert_eq!(state.share_url(&b), Some(format!("http://127.0.0.1:3000/b/{b}")));
+        state.revoke_share_token(&a);
+        assert_eq!(state.board_for_token(&a), None);
+        assert_eq!(state.board_for_token("guess"), None);
+    }
+
+    #[tokio::test]
+    async fn test_lock_board_serializes_same_board_only() {
+        let state = AppState::new(
+            None,
+            None,
+            None,
+            SandboxConfig::default(),
+            GalleryConfig::default(),
+            RenderConfig::default(),
+        );
+        let guard = state.lock_board("a").await;
+        // A different board is independent
+        let other = state.lock_board("b").await;
+        // The same board waits until the first guard drops
+        let same = tokio::time::timeout(std::time::Duration::from_millis(50), state.lock_board("a"));
+        assert!(same.await.is_err(), "second lock on the same board should block");
+        drop(guard);
+        let again = state.lock_board("a").await;
+
+        // Idle locks are dropped, not kept for every name ever used
+        drop(again);
+        drop(other);
+        assert!(state.board_locks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_board_links() {
+        let state = |addr: &str, public_url: Option<&str>| {
+            AppState::new(
+                Some((addr.to_string(), 3333)),
+                None,
+                None,
+                SandboxConfig::default(),
+                GalleryConfig {
+                    public_url: public_url.map(|u| crate::gallery::parse_public_url(u).unwrap()),
+                    ..Default::default()
+                },
+                RenderConfig::default(),
+            )
+        };
+        let local = state("127.0.0.1", None);
+        assert_eq!(local.board_url("a b").unwrap(), "http://127.0.0.1:3333/gallery/board/a%20b");
+        assert!(local.links_are_local());
+        assert!(state("0.0.0.0", None).links_are_local());
+        assert!(!state("192.168.1.20", None).links_are_local());
+
+        let public = state("0.0.0.0", Some("https://scry.example.com/"));
+        assert!(!public.links_are_local());
+        assert_eq!(public.board_url("a").unwrap(), "https://scry.example.com/gallery/board/a");
+        assert_eq!(public.share_url("tok").unwrap(), "https://scry.example.com/b/tok");
+
+        assert!(crate::gallery::parse_public_url("scry.example.com").is_err());
+        assert!(crate::gallery::parse_public_url("ftp://host").is_err());
+    }
+
+    #[test]
+    fn test_normalize_description() {
+        assert_eq!(normalize_description("  Sales by region\n2026 ").unwrap().as_deref(), Some("Sales by region\n2026"));
+        assert_eq!(normalize_description("   ").unwrap(), None);
+        assert!(normalize_description(&"é".repeat(MAX_DESCRIPTION_CHARS)).is_ok());
+        assert!(normalize_description(&"x".repeat(MAX_DESCRIPTION_CHARS + 1)).is_err());
+        assert!(normalize_description("bell\u{7}").is_err());
+    }
+
     #[test]
     fn test_sanitize_filename() {
         assert_eq!(sanitize_filename("hello"), "hello");
@@ -162,4 +832,85 @@ mod tests {
         assert_eq!(sanitize_filename("café"), "caf_");
         assert_eq!(sanitize_filename("a@b#c!d"), "a_b_c_d");
     }
+
+    #[test]
+    fn test_board_file_stem_avoids_collisions() {
+        assert_eq!(board_file_stem("plain-name_1.0"), "plain-name_1.0");
+        let names = ["café", "cafe", "caf_", "caf?", "cafe\u{301}"];
+        let stems: Vec<String> = names.iter().map(|n| board_file_stem(n)).collect();
+        for (i, a) in stems.iter().enumerate() {
+            for b in &stems[i + 1..] {
+                assert_ne!(a, b, "{names:?} -> {stems:?}");
+            }
+        }
+        assert!(stems[0].starts_with("caf_-"));
+        assert_eq!(board_file_stem("café"), stems[0], "stable across calls");
+    }
+
+    #[test]
+    fn test_export_zip() {
+        let board = |name: &str, png: &[u8], svg: &str| ExportBoard {
+            name: name.into(),
+            png: png.to_vec(),
+            svg: svg.into(),
+            vars: None,
+        };
+        let mut with_vars = board("café", b"png", "<svg/>");
+        with_vars.vars = Some(serde_json::json!({"n": 3}).as_object().unwrap().clone());
+        let boards = [
+            board("a", b"png-a", "<svg>a</svg>"),
+            with_vars,
+            board("a", b"dup", "<svg>dup</svg>"),
+            board("empty", b"", ""),
+        ];
+        let bytes = export_zip(&boards).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        let cafe = board_file_stem("café");
+        let mut expected = vec![
+            "a.png".to_string(),
+            "a.svg".into(),
+            "a-2.png".into(),
+            "a-2.svg".into(),
+            format!("{cafe}.png"),
+            format!("{cafe}.svg"),
+            format!("{cafe}.vars.json"),
+        ];
+        expected.sort();
+        assert_eq!(names, expected);
+
+        let mut dup = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("a-2.svg").unwrap(), &mut dup).unwrap();
+        assert_eq!(dup, "<svg>dup</svg>");
+    }
+
+    #[test]
+    fn test_import_zip_round_trip() {
+        let mut with_vars = ExportBoard {
+            name: "b".into(),
+            png: b"png".to_vec(),
+            svg: "<svg/>".into(),
+            vars: None,
+        };
+        with_vars.vars = Some(serde_json::json!({"n": [1, 2]}).as_object().unwrap().clone());
+        let vars_only = ExportBoard {
+            name: "a".into(),
+            png: Vec::new(),
+            svg: String::new(),
+            vars: Some(serde_json::Map::new()),
+        };
+        let bytes = export_zip(&[with_vars, vars_only]).unwrap();
+
+        let boards = import_zip(&bytes).unwrap();
+        let names: Vec<&str> = boards.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+        assert!(boards[0].svg.is_empty());
+        assert_eq!(boards[1].svg, "<svg/>");
+        assert!(boards[1].png.is_empty(), "PNGs are re-rendered, not trusted");
+        assert_eq!(boards[1].vars.as_ref().unwrap()["n"], serde_json::json!([1, 2]));
+
+        assert!(matches!(import_zip(b"not a zip"), Err(e) if e.starts_with("Zip import failed")));
+    }
 }
@@ -0,0 +1,192 @@
+//! Color and easing helpers exposed to scripts.
+//!
+//! This is the single implementation behind the sandbox's color API; the
+//! Python bindings in `python.rs` are thin wrappers, so any other scripting
+//! surface registering the same functions gets identical results.
+
+use palette::{Clamp, FromColor, Mix, Oklaba, Oklcha, ShiftHue, Srgba, WithAlpha};
+
+/// Parse `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa` or a CSS color name.
+pub fn parse(color: &str) -> Result<Srgba, String> {
+    let s = color.trim();
+    let Some(hex) = s.strip_prefix('#') else {
+        return palette::named::from_str(&s.to_ascii_lowercase())
+            .map(|c| c.into_format::<f32>().with_alpha(1.0))
+            .ok_or_else(|| format!("Unknown color {color:?}"));
+    };
+    let invalid = || format!("Invalid hex color {color:?}");
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let nibble = |i: usize| u8::from_str_radix(&hex[i..=i], 16).map(|v| v * 17);
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    let channels = match hex.len() {
+        3 => [nibble(0), nibble(1), nibble(2), Ok(255)],
+        4 => [nibble(0), nibble(1), nibble(2), nibble(3)],
+        6 => [byte(0), byte(2), byte(4), Ok(255)],
+        8 => [byte(0), byte(2), byte(4), byte(6)],
+        _ => return Err(invalid()),
+    };
+    let [r, g, b, a] = channels.map(|c| c.unwrap_or(0));
+    Ok(Srgba::new(r, g, b, a).into_format())
+}
+
+/// `#rrggbb`, or `#rrggbbaa` when the color isn't fully opaque.
+pub fn to_hex(color: Srgba) -> String {
+    let c: Srgba<u8> = color.clamp().into_format();
+    if c.alpha == 255 {
+        format!("#{:02x}{:02x}{:02x}", c.red, c.green, c.blue)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", c.red, c.green, c.blue, c.alpha)
+    }
+}
+
+/// Hex for an Oklch color: lightness 0..1, chroma 0..~0.4, hue in degrees.
+/// Colors outside the sRGB gamut are clipped.
+pub fn oklch(l: f32, c: f32, h: f32, alpha: f32) -> String {
+    to_hex(Srgba::from_color(Oklcha::new(l, c, h, alpha)))
+}
+
+/// Hex for an Oklab color: lightness 0..1, `a` and `b` roughly -0.4..0.4.
+/// Colors outside the sRGB gamut are clipped.
+pub fn oklab(l: f32, a: f32, b: f32, alpha: f32) -> String {
+    to_hex(Srgba::from_color(Oklaba::new(l, a, b, alpha)))
+}
+
+/// Interpolate from `a` (t = 0) to `b` (t = 1) in Oklab, which keeps the
+/// midpoints perceptually even instead of going muddy like sRGB mixing.
+pub fn color_mix(a: &str, b: &str, t: f32) -> Result<String, String> {
+    let a = Oklaba::from_color(parse(a)?);
+    let b = Oklaba::from_color(parse(b)?);
+    Ok(to_hex(Srgba::from_color(a.mix(b, t.clamp(0.0, 1.0)))))
+}
+
+/// Rotate a color's Oklch hue by `degrees`, keeping lightness and chroma.
+pub fn hue_shift(color: &str, degrees: f32) -> Result<String, String> {
+    let lch = Oklcha::from_color(parse(color)?);
+    Ok(to_hex(Srgba::from_color(lch.shift_hue(degrees))))
+}
+
+/// Accessibility color remap for rendered SVGs, applied in Oklab.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Remap {
+    /// Red-blind: red-green differences move onto the blue-yellow axis, and
+    /// reds are lifted since protanopes see them darkened.
+    Protanopia,
+    /// Green-blind: red-green differences move onto the blue-yellow axis.
+    Deuteranopia,
+    /// Lightness pushed away from mid-gray and chroma boosted.
+    HighContrast,
+}
+
+impl Remap {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "protanopia" => Ok(Self::Protanopia),
+            "deuteranopia" => Ok(Self::Deuteranopia),
+            "high-contrast" => Ok(Self::HighContrast),
+            other => Err(format!(
+                "Unknown remap_palette {other:?} (expected \"protanopia\", \"deuteranopia\" or \"high-contrast\")"
+            )),
+        }
+    }
+
+    /// Remap one color, keeping its alpha. Out-of-gamut results are clipped.
+    pub fn apply(self, color: Srgba) -> Srgba {
+        let lab = Oklaba::from_color(color);
+        let (l, a, b) = match self {
+            Self::Protanopia => (lab.l + 0.2 * lab.a, lab.a, lab.b + 0.8 * lab.a),
+            Self::Deuteranopia => (lab.l, lab.a, lab.b + 0.8 * lab.a),
+            Self::HighContrast => ((0.5 + (lab.l - 0.5) * 1.6).clamp(0.0, 1.0), lab.a * 1.25, lab.b * 1.25),
+        };
+        Srgba::from_color(Oklaba::new(l, a, b, lab.alpha)).clamp()
+    }
+
+    /// [`apply`](Self::apply) to a hex or named color, as hex.
+    pub fn apply_str(self, color: &str) -> Result<String, String> {
+        Ok(to_hex(self.apply(parse(color)?)))
+    }
+}
+
+/// Easing curve applied to `t` (clamped to 0..1): `linear`, or cubic
+/// `in`, `out` and `in_out`.
+pub fn ease(kind: &str, t: f64) -> Result<f64, String> {
+    let t = t.clamp(0.0, 1.0);
+    match kind {
+        "linear" => Ok(t),
+        "in" => Ok(t * t * t),
+        "out" => Ok(1.0 - (1.0 - t).powi(3)),
+        "in_out" if t < 0.5 => Ok(4.0 * t * t * t),
+        "in_out" => Ok(1.0 - (-2.0 * t + 2.0).powi(3) / 2.0),
+        other => Err(format!(
+            "Unknown easing {other:?} (expected \"linear\", \"in\", \"out\" or \"in_out\")"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_hex() {
+        assert_eq!(to_hex(parse("#f00").unwrap()), "#ff0000");
+        assert_eq!(to_hex(parse("#f00a").unwrap()), "#ff0000aa");
+        assert_eq!(to_hex(parse("#336699").unwrap()), "#336699");
+        assert_eq!(to_hex(parse("#33669980").unwrap()), "#33669980");
+        assert_eq!(to_hex(parse("Teal").unwrap()), "#008080");
+        for bad in ["#ff", "#ggg", "#12345", "notacolor", ""] {
+            assert!(parse(bad).is_err(), "{bad:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_oklch_and_mix() {
+        // Reference values for pure red in Oklch
+        assert_eq!(oklch(0.6279554, 0.2576833, 29.233885, 1.0), "#ff0000");
+        assert_eq!(oklch(1.0, 0.0, 0.0, 0.5), "#ffffff80");
+        assert_eq!(oklab(0.6279554, 0.22486306, 0.1258463, 1.0), "#ff0000");
+        assert_eq!(oklab(0.0, 0.0, 0.0, 0.25), "#00000040");
+
+        assert_eq!(color_mix("red", "blue", 0.0).unwrap(), "#ff0000");
+        assert_eq!(color_mix("red", "blue", 1.0).unwrap(), "#0000ff");
+        assert_eq!(color_mix("#000", "#fff", 0.5).unwrap(), "#636363", "Oklab midpoint, not #808080");
+        assert!(color_mix("red", "nope", 0.5).is_err());
+
+        assert_eq!(hue_shift("#336699", 360.0).unwrap(), "#336699");
+        assert_ne!(hue_shift("#336699", 180.0).unwrap(), "#336699");
+    }
+
+    #[test]
+    fn test_remap() {
+        assert_eq!(Remap::parse("high-contrast").unwrap(), Remap::HighContrast);
+        assert!(Remap::parse("tritanopia").is_err());
+
+        // Neutral grays have no red-green component to move
+        assert_eq!(Remap::Deuteranopia.apply_str("#808080").unwrap(), "#808080");
+        assert_eq!(Remap::Protanopia.apply_str("#80808040").unwrap(), "#80808040");
+
+        // Red and green end up further apart on the blue-yellow axis
+        let b = |c: &str| Oklaba::from_color(parse(c).unwrap()).b;
+        for remap in [Remap::Protanopia, Remap::Deuteranopia] {
+            let (red, green) = (remap.apply_str("#d62728").unwrap(), remap.apply_str("#2ca02c").unwrap());
+            assert!(b(&red) - b(&green) > b("#d62728") - b("#2ca02c"), "{remap:?}: {red} {green}");
+        }
+
+        let l = |c: &str| Oklaba::from_color(parse(c).unwrap()).l;
+        assert!(l(&Remap::HighContrast.apply_str("#444").unwrap()) < l("#444"));
+        assert!(l(&Remap::HighContrast.apply_str("#bbb").unwrap()) > l("#bbb"));
+    }
+
+    #[test]
+    fn test_ease() {
+        for kind in ["linear", "in", "out", "in_out"] {
+            assert_eq!(ease(kind, 0.0).unwrap(), 0.0);
+            assert_eq!(ease(kind, 1.0).unwrap(), 1.0);
+        }
+        assert_eq!(ease("in_out", 0.5).unwrap(), 0.5);
+        assert!(ease("in", 0.5).unwrap() < 0.5 && ease("out", 0.5).unwrap() > 0.5);
+        assert_eq!(ease("linear", 2.0).unwrap(), 1.0);
+        assert!(ease("bounce", 0.5).is_err());
+    }
+}
@@ -1,18 +1,103 @@
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum ScryError {
+    /// User code failed to compile (SyntaxError, IndentationError, null bytes).
+    Syntax(String),
+    /// User code raised while running.
     Python(String),
     SvgParse(String),
+    /// SVG parsed but its size is zero or over the render limit.
+    Dimensions(String),
     Render(String),
+    /// Host-side failure unrelated to user input (task join, interpreter setup).
+    Internal(String),
+    /// A tool argument is malformed or out of range.
+    InvalidParams(String),
+    /// The board, render, snapshot or template asked for doesn't exist.
+    NotFound(String),
+    /// Input or output over a size cap.
+    TooLarge(String),
+    /// `--max-exec-per-sec` refused the call; retrying later can succeed.
+    RateLimited(String),
+    /// The server runs with `--disable-execution`.
+    ExecutionDisabled(String),
+    /// The code ran without calling svg() under `require_svg`; the message
+    /// carries its stdout.
+    NoSvg(String),
+}
+
+/// Stable, machine-readable classification of a [`ScryError`].
+///
+/// The `code()` strings are part of the tool response contract; don't rename them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    SyntaxError,
+    Runtime,
+    SvgParse,
+    RenderDimensions,
+    RenderEncode,
+    Internal,
+    InvalidParams,
+    NotFound,
+    TooLarge,
+    RateLimited,
+    ExecutionDisabled,
+    NoSvg,
+}
+
+impl ErrorKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::SyntaxError => "syntax_error",
+            ErrorKind::Runtime => "runtime_error",
+            ErrorKind::SvgParse => "svg_parse_error",
+            ErrorKind::RenderDimensions => "render_dimensions",
+            ErrorKind::RenderEncode => "render_encode",
+            ErrorKind::Internal => "internal_error",
+            ErrorKind::InvalidParams => "invalid_params",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::TooLarge => "too_large",
+            ErrorKind::RateLimited => "rate_limited",
+            ErrorKind::ExecutionDisabled => "execution_disabled",
+            ErrorKind::NoSvg => "no_svg",
+        }
+    }
+}
+
+impl ScryError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ScryError::Syntax(_) => ErrorKind::SyntaxError,
+            ScryError::Python(_) => ErrorKind::Runtime,
+            ScryError::SvgParse(_) => ErrorKind::SvgParse,
+            ScryError::Dimensions(_) => ErrorKind::RenderDimensions,
+            ScryError::Render(_) => ErrorKind::RenderEncode,
+            ScryError::Internal(_) => ErrorKind::Internal,
+            ScryError::InvalidParams(_) => ErrorKind::InvalidParams,
+            ScryError::NotFound(_) => ErrorKind::NotFound,
+            ScryError::TooLarge(_) => ErrorKind::TooLarge,
+            ScryError::RateLimited(_) => ErrorKind::RateLimited,
+            ScryError::ExecutionDisabled(_) => ErrorKind::ExecutionDisabled,
+            ScryError::NoSvg(_) => ErrorKind::NoSvg,
+        }
+    }
 }
 
 impl fmt::Display for ScryError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ScryError::Python(msg) => write!(f, "Python error: {msg}"),
+            ScryError::Syntax(msg) | ScryError::Python(msg) => write!(f, "Python error: {msg}"),
             ScryError::SvgParse(msg) => write!(f, "SVG parse error: {msg}"),
-            ScryError::Render(msg) => write!(f, "Render error: {msg}"),
+            ScryError::Dimensions(msg) | ScryError::Render(msg) => write!(f, "Render error: {msg}"),
+            ScryError::Internal(msg) => write!(f, "Internal error: {msg}"),
+            // Tool-level refusals already read as complete sentences
+            ScryError::InvalidParams(msg)
+            | ScryError::NotFound(msg)
+            | ScryError::TooLarge(msg)
+            | ScryError::RateLimited(msg)
+            | ScryError::ExecutionDisabled(msg)
+            | ScryError::NoSvg(msg) => f.write_str(msg),
         }
     }
 }
@@ -1,32 +1,163 @@
-use crate::board::{SharedState, html_escape, url_encode};
-use axum::extract::{Path, State};
+use crate::board::{BoardEvent, SharedState, board_file_stem, html_escape, url_encode};
+use crate::render;
+use axum::body::{Body, Bytes};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{DefaultBodyLimit, Path, Query, Request, State};
+use axum::middleware::{self, Next};
 use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::http::{HeaderMap, HeaderValue, Method, header};
 use axum::routing::get;
 use axum::Router;
 use base64::Engine;
+use chrono::{DateTime, Utc};
 use base64::engine::general_purpose::STANDARD as BASE64;
 use futures_core::Stream;
 use std::convert::Infallible;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::StreamExt;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
+
+/// Gallery HTTP settings, fixed at startup.
+#[derive(Clone, Debug, Default)]
+pub struct GalleryConfig {
+    /// Origins allowed to make cross-origin GET requests. `*` allows any
+    /// origin; empty (the default) sends no CORS headers.
+    pub cors_origins: Vec<HeaderValue>,
+    /// Refuse every state-changing request from the web UI.
+    pub readonly: bool,
+    /// Base URL other devices reach the gallery at (`--public-url`), used for
+    /// board links and QR codes instead of the bind address.
+    pub public_url: Option<String>,
+}
+
+impl GalleryConfig {
+    /// Whether the gallery may mutate server state. Write handlers should
+    /// check this; [`readonly_guard`] enforces it for unsafe methods anyway.
+    pub fn writable(&self) -> bool {
+        !self.readonly
+    }
+}
+
+/// Parse a `--public-url` value: an http(s) URL, without a trailing slash.
+pub fn parse_public_url(s: &str) -> Result<String, String> {
+    let url = s.trim().trim_end_matches('/');
+    match url.split_once("://") {
+        Some(("http" | "https", host)) if !host.is_empty() => Ok(url.to_string()),
+        _ => Err("expected an http:// or https:// URL".into()),
+    }
+}
+
+/// Build the CORS layer for the configured origins, if any.
+fn cors_layer(origins: &[HeaderValue]) -> Option<CorsLayer> {
+    if origins.is_empty() {
+        return None;
+    }
+    let allow_origin = if origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(origins.iter().cloned())
+    };
+    Some(
+        CorsLayer::new()
+            .allow_methods([Method::GET, Method::HEAD])
+            .allow_origin(allow_origin),
+    )
+}
+
+/// Reject non-safe methods (anything but GET/HEAD/OPTIONS/TRACE) when the
+/// gallery is read-only, so write routes added later can't slip past the flag.
+async fn readonly_guard(State(state): State<SharedState>, req: Request, next: Next) -> Response {
+    if !state.gallery.writable() && !req.method().is_safe() {
+        return (axum::http::StatusCode::FORBIDDEN, "Gallery is read-only").into_response();
+    }
+    next.run(req).await
+}
+
+/// Largest request body the gallery accepts. Every route is GET today, so
+/// this only needs to cover small form posts added later.
+const MAX_REQUEST_BODY_BYTES: usize = 64 * 1024;
+
+/// Longest path + query string accepted before answering 414.
+const MAX_URI_BYTES: usize = 8 * 1024;
+
+/// Refuse oversized URIs before any handler parses the query string.
+async fn uri_limit(req: Request, next: Next) -> Response {
+    let len = req.uri().path_and_query().map_or(0, |pq| pq.as_str().len());
+    if len > MAX_URI_BYTES {
+        return (axum::http::StatusCode::URI_TOO_LONG, "Request URI too long").into_response();
+    }
+    next.run(req).await
+}
 
 pub fn router(state: SharedState) -> Router {
-    Router::new()
+    let cors = cors_layer(&state.gallery.cors_origins);
+    let guard = middleware::from_fn_with_state(state.clone(), readonly_guard);
+    let router = Router::new()
         .route("/", get(|| async { Redirect::permanent("/gallery/") }))
         .route("/gallery/", get(gallery_index))
         .route("/gallery/board/{name}", get(board_detail))
         .route("/gallery/board/{name}/png", get(board_png))
+        .route("/gallery/board/{name}/thumb", get(board_thumb))
         .route("/gallery/board/{name}/svg", get(board_svg))
+        .route("/gallery/board/{name}/download", get(board_download))
+        .route("/gallery/board/{name}/qr", get(board_qr))
+        .route("/b/{token}", get(shared_board))
+        .route("/b/{token}/png", get(shared_png))
+        .route("/b/{token}/svg", get(shared_svg))
         .route("/gallery/events", get(sse_handler))
-        .with_state(state)
+        .route("/gallery/ws", get(ws_handler))
+        .route("/healthz", get(healthz))
+        .layer(guard)
+        // Oversized bodies get 413: up front from Content-Length, or while
+        // streaming; DefaultBodyLimit covers axum's own body extractors
+        .layer(DefaultBodyLimit::max(MAX_REQUEST_BODY_BYTES))
+        .layer(RequestBodyLimitLayer::new(MAX_REQUEST_BODY_BYTES))
+        .layer(middleware::from_fn(uri_limit))
+        // gzip/deflate when the client accepts it. The default predicate skips
+        // raster images (already compressed), SSE, and tiny bodies, but keeps SVG.
+        .layer(CompressionLayer::new())
+        .with_state(state);
+    match cors {
+        Some(layer) => router.layer(layer),
+        None => router,
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct IndexQuery {
+    /// Only show boards carrying this tag
+    tag: Option<String>,
+    /// `light` or `dark`; overrides the theme saved in the browser
+    theme: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ThemeQuery {
+    theme: Option<String>,
 }
 
-async fn gallery_index(State(state): State<SharedState>) -> Html<String> {
+/// `data-theme` value for a `?theme=` param; anything unrecognized is dark.
+fn theme_attr(theme: Option<&str>) -> &'static str {
+    match theme {
+        Some("light") => "light",
+        _ => "dark",
+    }
+}
+
+async fn gallery_index(
+    State(state): State<SharedState>,
+    Query(query): Query<IndexQuery>,
+) -> Html<String> {
     let boards = state.boards.read().await;
     let mut cards = String::new();
 
-    let mut board_list: Vec<_> = boards.values().collect();
+    let mut board_list: Vec<_> = boards
+        .values()
+        .filter(|b| query.tag.as_ref().is_none_or(|t| b.tags.contains(t)))
+        .collect();
     board_list.sort_by_key(|b| std::cmp::Reverse(b.updated_at));
 
     for board in board_list {
@@ -35,50 +166,92 @@ async fn gallery_index(State(state): State<SharedState>) -> Html<String> {
         let has_image = !board.png.is_empty();
         let img_tag = if has_image {
             format!(
-                r#"<img src="/gallery/board/{}/png" alt="{}" loading="lazy">"#,
-                name_url, name_html
+                r#"<img src="/gallery/board/{}/thumb?v={}" alt="{}" loading="lazy">"#,
+                name_url,
+                board.updated_at.timestamp_micros(),
+                name_html
             )
         } else {
             "<div class=\"placeholder\">No render yet</div>".to_string()
         };
 
+        // Tag links sit inside the clickable card, so keep them from also
+        // opening the board
+        let tag_links: String = board
+            .tags
+            .iter()
+            .map(|t| {
+                format!(
+                    r#"<a class="tag" href="/gallery/?tag={}" onclick="event.stopPropagation()">{}</a>"#,
+                    url_encode(t),
+                    html_escape(t)
+                )
+            })
+            .collect();
+        let desc = board
+            .description
+            .as_ref()
+            .map(|d| format!(r#"<p class="desc">{}</p>"#, html_escape(d)))
+            .unwrap_or_default();
+
         cards.push_str(&format!(
             r#"<div class="card" onclick="location.href='/gallery/board/{name_url}'">
                 <div class="card-img">{img_tag}</div>
                 <div class="card-info">
-                    <h2>{name_html}</h2>
-                    <span class="dim">{w}x{h} &middot; {updated}</span>
+                    <h2>{pin}{name_html}</h2>
+                    {desc}<span class="dim">{w}x{h} &middot; {updated}</span>
+                    <div class="tags">{tag_links}</div>
                 </div>
             </div>"#,
             name_url = name_url,
             img_tag = img_tag,
             name_html = name_html,
+            tag_links = tag_links,
+            desc = desc,
+            pin = if board.pinned { r#"<span class="pin" title="Pinned">&#128204;</span> "# } else { "" },
             w = board.width,
             h = board.height,
             updated = board.updated_at.format("%H:%M:%S"),
         ));
     }
 
+    let filter_note = match &query.tag {
+        Some(tag) => {
+            if cards.is_empty() {
+                cards = format!("<p class=\"empty\">No boards tagged {}.</p>", html_escape(tag));
+            }
+            format!(
+                r#"<span class="dim">Tagged <b>{}</b> &middot; <a class="back" href="/gallery/">show all</a></span>"#,
+                html_escape(tag)
+            )
+        }
+        None => String::new(),
+    };
     if cards.is_empty() {
         cards = "<p class=\"empty\">No boards yet. Use the whiteboard tool to create one.</p>".to_string();
     }
 
     Html(format!(
         r#"<!DOCTYPE html>
-<html lang="en">
+<html lang="en" data-theme="{theme}">
 <head>
 <meta charset="utf-8">
 <meta name="viewport" content="width=device-width, initial-scale=1">
 <title>Scry Gallery</title>
 <style>{CSS}</style>
+<script>{THEME_JS}</script>
 </head>
 <body>
-<header><h1>Scry Gallery</h1></header>
+<header>{THEME_TOGGLE}<h1>Scry Gallery</h1>{filter_note}</header>
 <main class="grid">{cards}</main>
-<script>{SSE_JS}</script>
+<script>{LIVE_JS}
+{SSE_JS}</script>
 </body>
 </html>"#,
         CSS = CSS,
+        theme = theme_attr(query.theme.as_deref()),
+        LIVE_JS = LIVE_JS,
+        filter_note = filter_note,
         cards = cards,
         SSE_JS = SSE_RELOAD_JS,
     ))
@@ -87,13 +260,16 @@ async fn gallery_index(State(state): State<SharedState>) -> Html<String> {
 async fn board_detail(
     State(state): State<SharedState>,
     Path(name): Path<String>,
+    Query(query): Query<ThemeQuery>,
 ) -> Response {
     let boards = state.boards.read().await;
     let name_html = html_escape(&name);
     let name_url = url_encode(&name);
+    let theme = theme_attr(query.theme.as_deref());
     let Some(board) = boards.get(&name) else {
         return Html(format!(
-            r#"<!DOCTYPE html><html><head><style>{CSS}</style></head>
+            r#"<!DOCTYPE html><html data-theme="{theme}"><head><style>{CSS}</style>
+            <script>{THEME_JS}</script></head>
             <body><h1>Board not found: {name_html}</h1>
             <a href="/gallery/">Back to gallery</a></body></html>"#,
             CSS = CSS,
@@ -109,33 +285,47 @@ async fn board_detail(
                 <img src="data:image/png;base64,{b64}" alt="{name_html}">
             </div>
             <div class="links">
-                <a href="/gallery/board/{name_url}/png">Raw PNG</a>
-                <a href="/gallery/board/{name_url}/svg">Raw SVG</a>
+                <a href="/gallery/board/{name_url}/png?v={version}">Raw PNG</a>
+                <a href="/gallery/board/{name_url}/svg?v={version}">Raw SVG</a>
+                <a href="/gallery/board/{name_url}/download">Download HTML</a>
             </div>"#,
             b64 = b64,
             name_html = name_html,
             name_url = name_url,
+            version = board.updated_at.timestamp_micros(),
         )
     } else {
         "<p>No render yet.</p>".to_string()
     };
 
     let svg_escaped = html_escape(&board.svg);
+    let seed_note = board
+        .seed
+        .map(|seed| format!(" &middot; seed {seed}"))
+        .unwrap_or_default();
+    let desc = board
+        .description
+        .as_ref()
+        .map(|d| format!(r#"<p class="desc">{}</p>"#, html_escape(d)))
+        .unwrap_or_default();
 
     Html(format!(
         r#"<!DOCTYPE html>
-<html lang="en">
+<html lang="en" data-theme="{theme}">
 <head>
 <meta charset="utf-8">
 <meta name="viewport" content="width=device-width, initial-scale=1">
 <title>Scry — {name_html}</title>
 <style>{CSS}</style>
+<script>{THEME_JS}</script>
 </head>
 <body>
 <header>
+    {THEME_TOGGLE}
     <a href="/gallery/" class="back">&larr; Gallery</a>
     <h1>{name_html}</h1>
-    <span class="dim">{w}x{h} &middot; Updated {updated} &middot; {history_len} snapshots</span>
+    <span class="dim">{w}x{h} &middot; Updated {updated} &middot; {history_len} snapshots{seed_note}</span>
+    {desc}
 </header>
 <main>
     {img_section}
@@ -153,6 +343,8 @@ async fn board_detail(
         h = board.height,
         updated = board.updated_at.format("%Y-%m-%d %H:%M:%S UTC"),
         history_len = board.history.len(),
+        seed_note = seed_note,
+        desc = desc,
         img_section = img_section,
         svg_escaped = svg_escaped,
         SSE_JS = sse_board_js(&board.name),
@@ -160,77 +352,528 @@ async fn board_detail(
     .into_response()
 }
 
+/// A standalone HTML page for one board, served as an attachment. The SVG is
+/// embedded as a data URI rather than inline markup, so any script in it stays
+/// inert when the file is opened locally.
+async fn board_download(State(state): State<SharedState>, Path(name): Path<String>) -> Response {
+    let boards = state.boards.read().await;
+    let Some(board) = boards.get(&name).filter(|b| !b.svg.is_empty()) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Board not found or no SVG").into_response();
+    };
+
+    let name_html = html_escape(&board.name);
+    let mut meta = format!(
+        "{}x{} &middot; created {} &middot; updated {}",
+        board.width,
+        board.height,
+        board.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        board.updated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+    );
+    if let Some(seed) = board.seed {
+        meta.push_str(&format!(" &middot; seed {seed}"));
+    }
+    if !board.tags.is_empty() {
+        meta.push_str(&format!(" &middot; tags: {}", html_escape(&board.tags.join(", "))));
+    }
+    let page = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{name_html}</title>
+<style>{CSS}</style>
+</head>
+<body>
+<header>
+    <h1>{name_html}</h1>
+    <span class="dim">{meta}</span>
+</header>
+<main>
+    <div class="board-img">
+        <img src="data:image/svg+xml;base64,{svg_b64}" alt="{name_html}">
+    </div>
+    <details>
+        <summary>SVG Source</summary>
+        <pre><code>{svg_escaped}</code></pre>
+    </details>
+</main>
+</body>
+</html>"#,
+        CSS = CSS,
+        svg_b64 = BASE64.encode(&board.svg),
+        svg_escaped = html_escape(&board.svg),
+    );
+    let disposition = format!(
+        "attachment; filename=\"{}.html\"",
+        board_file_stem(&board.name)
+    );
+    (
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        page,
+    )
+        .into_response()
+}
+
+/// `?v=` cache-buster the gallery appends to image URLs; any value marks the
+/// URL as versioned, so it can be cached indefinitely.
+#[derive(serde::Deserialize)]
+struct VersionQuery {
+    v: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ImageQuery {
+    /// Output format: "png" (default) or "webp"
+    fmt: Option<String>,
+    v: Option<String>,
+}
+
+/// True if the request's `If-None-Match` / `If-Modified-Since` show the client
+/// already has this version. `If-None-Match` wins when both are sent.
+fn is_not_modified(headers: &HeaderMap, etag: &str, updated: DateTime<Utc>) -> bool {
+    if let Some(inm) = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        return inm
+            .split(',')
+            .map(|t| t.trim().trim_start_matches("W/"))
+            .any(|t| t == etag || t == "*");
+    }
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+        .is_some_and(|since| updated.timestamp() <= since.timestamp())
+}
+
+/// Answer with `304 Not Modified` when the client is current, otherwise build
+/// the body by awaiting `make`. Either way, attach validators derived from the board's
+/// `updated_at` (bumped on every change) and a cache policy: versioned URLs are
+/// immutable, bare ones must revalidate.
+async fn cached<F: std::future::Future<Output = Response>>(
+    headers: &HeaderMap,
+    updated: DateTime<Utc>,
+    variant: &str,
+    versioned: bool,
+    make: impl FnOnce() -> F,
+) -> Response {
+    let etag = format!("\"{}-{variant}\"", updated.timestamp_micros());
+    let mut response = if is_not_modified(headers, &etag, updated) {
+        axum::http::StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        make().await
+    };
+    if !(response.status().is_success() || response.status() == axum::http::StatusCode::NOT_MODIFIED) {
+        return response;
+    }
+    let cache_control = if versioned {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    let last_modified = updated.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    let h = response.headers_mut();
+    for (name, value) in [
+        (header::ETAG, etag),
+        (header::LAST_MODIFIED, last_modified),
+        (header::CACHE_CONTROL, cache_control.to_string()),
+    ] {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            h.insert(name, value);
+        }
+    }
+    response
+}
+
+/// Chunk size for [`streamed`] bodies.
+const STREAM_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Respond with `bytes` sent in chunks rather than as one buffer. Callers
+/// copy the bytes out and drop the board lock before building this.
+fn streamed(bytes: Vec<u8>, content_type: &'static str) -> Response {
+    let len = bytes.len();
+    let bytes = Bytes::from(bytes);
+    let chunks = (0..len).step_by(STREAM_CHUNK_BYTES).map(move |start| {
+        Ok::<_, Infallible>(bytes.slice(start..(start + STREAM_CHUNK_BYTES).min(len)))
+    });
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_LENGTH, len.to_string()),
+        ],
+        Body::from_stream(futures_util::stream::iter(chunks)),
+    )
+        .into_response()
+}
+
 async fn board_png(
     State(state): State<SharedState>,
     Path(name): Path<String>,
+    Query(query): Query<ImageQuery>,
+    headers: HeaderMap,
 ) -> Response {
-    let boards = state.boards.read().await;
-    match boards.get(&name) {
-        Some(board) if !board.png.is_empty() => {
-            (
-                [(axum::http::header::CONTENT_TYPE, "image/png")],
-                board.png.clone(),
-            )
-                .into_response()
+    let (png, updated) = {
+        let boards = state.boards.read().await;
+        match boards.get(&name) {
+            Some(board) if !board.png.is_empty() => (board.png.clone(), board.updated_at),
+            _ => {
+                return (axum::http::StatusCode::NOT_FOUND, "Board not found or no render")
+                    .into_response();
+            }
+        }
+    }; // read lock released before any transcoding
+
+    let versioned = query.v.is_some();
+    match query.fmt.as_deref() {
+        None | Some("png") => {
+            cached(&headers, updated, "png", versioned, || async { streamed(png, "image/png") }).await
         }
-        _ => (axum::http::StatusCode::NOT_FOUND, "Board not found or no render").into_response(),
+        Some("webp") => {
+            cached(&headers, updated, "webp", versioned, || async {
+                match state.run_render(move || render::png_to_webp(&png)).await {
+                    Ok(webp) => streamed(webp, "image/webp"),
+                    Err(e) => {
+                        (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+                    }
+                }
+            })
+            .await
+        }
+        Some(other) => (
+            axum::http::StatusCode::BAD_REQUEST,
+            format!("Unsupported format: {other} (expected png or webp)"),
+        )
+            .into_response(),
+    }
+}
+
+/// One board's page, reached through its share token rather than its name.
+///
+/// Standalone on purpose: no link back to the index, no live-update script
+/// (events carry board names), and image links scoped to the token, so the
+/// link reveals nothing beyond the board itself.
+async fn shared_board(
+    State(state): State<SharedState>,
+    Path(token): Path<String>,
+    Query(query): Query<ThemeQuery>,
+) -> Response {
+    let Some(name) = state.board_for_token(&token) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Unknown share link").into_response();
+    };
+    let boards = state.boards.read().await;
+    let Some(board) = boards.get(&name) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Unknown share link").into_response();
+    };
+    let theme = theme_attr(query.theme.as_deref());
+    let token_url = url_encode(&token);
+
+    let img_section = if !board.png.is_empty() {
+        format!(
+            r#"<div class="board-img">
+                <img src="/b/{token_url}/png?v={version}" alt="Shared board">
+            </div>
+            <div class="links">
+                <a href="/b/{token_url}/png?v={version}">Raw PNG</a>
+                <a href="/b/{token_url}/svg?v={version}">Raw SVG</a>
+            </div>"#,
+            version = board.updated_at.timestamp_micros(),
+        )
+    } else {
+        "<p>No render yet.</p>".to_string()
+    };
+    let desc = board
+        .description
+        .as_ref()
+        .map(|d| format!(r#"<p class="desc">{}</p>"#, html_escape(d)))
+        .unwrap_or_default();
+
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en" data-theme="{theme}">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+<title>Scry — shared board</title>
+<style>{CSS}</style>
+<script>{THEME_JS}</script>
+</head>
+<body>
+<header>
+    {THEME_TOGGLE}
+    <h1>Shared board</h1>
+    <span class="dim">{w}x{h} &middot; Updated {updated}</span>
+    {desc}
+</header>
+<main>
+    {img_section}
+    <details>
+        <summary>SVG Source</summary>
+        <pre><code>{svg_escaped}</code></pre>
+    </details>
+</main>
+</body>
+</html>"#,
+        w = board.width,
+        h = board.height,
+        updated = board.updated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        svg_escaped = html_escape(&board.svg),
+    ))
+    .into_response()
+}
+
+/// [`board_png`] for a share token.
+async fn shared_png(
+    State(state): State<SharedState>,
+    Path(token): Path<String>,
+    query: Query<ImageQuery>,
+    headers: HeaderMap,
+) -> Response {
+    match state.board_for_token(&token) {
+        Some(name) => board_png(State(state), Path(name), query, headers).await,
+        None => (axum::http::StatusCode::NOT_FOUND, "Unknown share link").into_response(),
+    }
+}
+
+/// [`board_svg`] for a share token.
+async fn shared_svg(
+    State(state): State<SharedState>,
+    Path(token): Path<String>,
+    query: Query<VersionQuery>,
+    headers: HeaderMap,
+) -> Response {
+    match state.board_for_token(&token) {
+        Some(name) => board_svg(State(state), Path(name), query, headers).await,
+        None => (axum::http::StatusCode::NOT_FOUND, "Unknown share link").into_response(),
     }
 }
 
+/// Liveness probe: always 200 while the HTTP side is serving.
+async fn healthz(State(state): State<SharedState>) -> Response {
+    let boards = state.boards.read().await.len();
+    axum::Json(serde_json::json!({
+        "status": "ok",
+        "boards": boards,
+        "uptime_s": state.started.elapsed().as_secs(),
+    }))
+    .into_response()
+}
+
+/// QR code for the board's public URL, for opening it on a phone.
+async fn board_qr(State(state): State<SharedState>, Path(name): Path<String>) -> Response {
+    if !state.boards.read().await.contains_key(&name) {
+        return (axum::http::StatusCode::NOT_FOUND, "Board not found").into_response();
+    }
+    let Some(url) = state.board_url(&name) else {
+        return (axum::http::StatusCode::NOT_FOUND, "No gallery address configured").into_response();
+    };
+    if state.links_are_local() {
+        return (
+            axum::http::StatusCode::NOT_FOUND,
+            "The gallery address only works on this machine; start scry-mcp with --public-url for a QR code a phone can open",
+        )
+            .into_response();
+    }
+    match state.run_render(move || render::qr_png(&url)).await {
+        Ok(png) => ([(header::CONTENT_TYPE, "image/png")], png).into_response(),
+        Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Serve the cached thumbnail, falling back to the full PNG if none was made.
+async fn board_thumb(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Query(query): Query<VersionQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let (png, updated) = {
+        let boards = state.boards.read().await;
+        let Some(board) = boards.get(&name).filter(|b| !b.png.is_empty()) else {
+            return (axum::http::StatusCode::NOT_FOUND, "Board not found or no render").into_response();
+        };
+        let png = if board.thumb_png.is_empty() {
+            &board.png
+        } else {
+            &board.thumb_png
+        };
+        (png.clone(), board.updated_at)
+    };
+    cached(&headers, updated, "thumb", query.v.is_some(), || async { streamed(png, "image/png") }).await
+}
+
 async fn board_svg(
     State(state): State<SharedState>,
     Path(name): Path<String>,
+    Query(query): Query<VersionQuery>,
+    headers: HeaderMap,
 ) -> Response {
     let boards = state.boards.read().await;
     match boards.get(&name) {
         Some(board) if !board.svg.is_empty() => {
-            (
-                [(axum::http::header::CONTENT_TYPE, "image/svg+xml")],
-                board.svg.clone(),
-            )
-                .into_response()
+            cached(&headers, board.updated_at, "svg", query.v.is_some(), || async {
+                svg_response(board.svg.clone())
+            })
+            .await
         }
         _ => (axum::http::StatusCode::NOT_FOUND, "Board not found or no SVG").into_response(),
     }
 }
 
+/// Board SVG opened directly in a browser. The content is sanitized when
+/// stored; the CSP keeps anything that slips through from running.
+fn svg_response(svg: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CONTENT_SECURITY_POLICY, "script-src 'none'"),
+        ],
+        svg,
+    )
+        .into_response()
+}
+
+fn event_json(event: &BoardEvent) -> String {
+    let mut data = serde_json::json!({
+        "id": event.id,
+        "board": event.board_name,
+        "type": format!("{:?}", event.event_type),
+        "dimensions_changed": event.dimensions_changed,
+    });
+    if let Some((width, height)) = event.size {
+        data["width"] = width.into();
+        data["height"] = height.into();
+    }
+    data.to_string()
+}
+
+/// Live board events as `(id, JSON)` pairs, shared by the SSE and WebSocket
+/// endpoints, starting with any retained events newer than `since`. Ends on
+/// shutdown so graceful shutdown isn't held open by browsers.
+fn board_event_stream(
+    state: &SharedState,
+    since: Option<u64>,
+) -> impl Stream<Item = (u64, String)> + use<> {
+    let (missed, rx) = state.subscribe_since(since);
+    let shutdown = state.shutdown.clone().cancelled_owned();
+    let replay = tokio_stream::iter(missed).map(|event| (event.id, event_json(&event)));
+    let live = BroadcastStream::new(rx).filter_map(|result| match result {
+        Ok(event) => Some((event.id, event_json(&event))),
+        Err(_) => None, // lagged, skip
+    });
+    futures_util::StreamExt::take_until(replay.chain(live), shutdown)
+}
+
+#[derive(serde::Deserialize)]
+struct SinceQuery {
+    /// Replay retained events with a greater id (the `Last-Event-ID` header
+    /// wins when both are present)
+    since: Option<u64>,
+}
+
 async fn sse_handler(
     State(state): State<SharedState>,
+    Query(query): Query<SinceQuery>,
+    headers: HeaderMap,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    let rx = state.event_tx.subscribe();
-    let stream = BroadcastStream::new(rx).filter_map(|result| {
-        match result {
-            Ok(event) => {
-                let data = serde_json::json!({
-                    "board": event.board_name,
-                    "type": format!("{:?}", event.event_type),
-                });
-                Some(Ok(Event::default().data(data.to_string())))
+    // Browsers resend the last id they saw when EventSource reconnects
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok());
+    let stream = board_event_stream(&state, last_event_id.or(query.since))
+        .map(|(id, data)| Ok(Event::default().id(id.to_string()).data(data)));
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// WebSocket alternative to `/gallery/events` for proxies that buffer SSE.
+/// Pushes the same JSON messages (`?since=` replays the same way); anything
+/// the client sends is ignored.
+async fn ws_handler(
+    State(state): State<SharedState>,
+    Query(query): Query<SinceQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let events = board_event_stream(&state, query.since).map(|(_, data)| data);
+    ws.on_upgrade(move |socket| ws_push(socket, events))
+}
+
+async fn ws_push(mut socket: WebSocket, events: impl Stream<Item = String>) {
+    let mut events = std::pin::pin!(events);
+    loop {
+        tokio::select! {
+            event = events.next() => {
+                let Some(data) = event else { break }; // shutdown
+                if socket.send(Message::Text(data.into())).await.is_err() {
+                    return;
+                }
             }
-            Err(_) => None, // lagged, skip
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
         }
-    });
-    Sse::new(stream).keep_alive(KeepAlive::default())
+    }
+    let _ = socket.send(Message::Close(None)).await;
 }
 
 fn sse_board_js(board_name: &str) -> String {
     // JSON-encode the board name to safely embed in a JS string literal
     let js_safe = serde_json::to_string(board_name).unwrap_or_else(|_| "\"\"".into());
     format!(
-        r#"const es = new EventSource('/gallery/events');
+        r#"{LIVE_JS}
 const _boardName = {js_safe};
-es.onmessage = function(e) {{
-    const data = JSON.parse(e.data);
+scryLive(function(data) {{
     if (data.board === _boardName) {{
         location.reload();
     }}
-}};"#,
+}});"#,
+        LIVE_JS = LIVE_JS,
         js_safe = js_safe,
     )
 }
 
-const SSE_RELOAD_JS: &str = r#"const es = new EventSource('/gallery/events');
-es.onmessage = function(e) { location.reload(); };"#;
+/// Subscribe to board events, preferring the WebSocket endpoint and falling
+/// back to SSE if it fails to connect or drops.
+const LIVE_JS: &str = r#"function scryLive(onEvent) {
+    let fellBack = false;
+    function sse() {
+        if (fellBack) return;
+        fellBack = true;
+        const es = new EventSource('/gallery/events');
+        es.onmessage = function(e) { onEvent(JSON.parse(e.data)); };
+    }
+    if (!('WebSocket' in window)) return sse();
+    const proto = location.protocol === 'https:' ? 'wss:' : 'ws:';
+    const ws = new WebSocket(proto + '//' + location.host + '/gallery/ws');
+    ws.onmessage = function(e) { onEvent(JSON.parse(e.data)); };
+    ws.onerror = sse;
+    ws.onclose = sse;
+}"#;
+
+const SSE_RELOAD_JS: &str = r#"scryLive(function() { location.reload(); });"#;
+
+/// Runs in `<head>` so the saved theme applies before first paint. A
+/// `?theme=` param wins and becomes the saved choice.
+const THEME_JS: &str = r#"(function() {
+    const fromUrl = new URLSearchParams(location.search).get('theme');
+    let theme = fromUrl;
+    try {
+        if (fromUrl === 'light' || fromUrl === 'dark') localStorage.setItem('scry-theme', fromUrl);
+        else theme = localStorage.getItem('scry-theme');
+    } catch (e) {}
+    if (theme === 'light' || theme === 'dark') document.documentElement.dataset.theme = theme;
+})();
+function scryToggleTheme() {
+    const root = document.documentElement;
+    const next = root.dataset.theme === 'light' ? 'dark' : 'light';
+    root.dataset.theme = next;
+    try { localStorage.setItem('scry-theme', next); } catch (e) {}
+}"#;
+
+const THEME_TOGGLE: &str = r#"<button class="theme-toggle" onclick="scryToggleTheme()" title="Toggle light/dark theme">&#9680;</button>"#;
 
 const CSS: &str = r#"
 :root {
@@ -240,6 +883,16 @@ const CSS: &str = r#"
     --text: #e0e0e0;
     --dim: #888;
     --accent: #e94560;
+    --well: #111;
+}
+:root[data-theme="light"] {
+    --bg: #f5f5f7;
+    --surface: #ffffff;
+    --border: #d0d4dc;
+    --text: #1a1a2e;
+    --dim: #666;
+    --accent: #c2185b;
+    --well: #e8e8ec;
 }
 * { margin: 0; padding: 0; box-sizing: border-box; }
 body {
@@ -253,6 +906,17 @@ header {
     border-bottom: 1px solid var(--border);
 }
 header h1 { font-size: 1.4rem; margin-bottom: 0.3rem; }
+.theme-toggle {
+    float: right;
+    background: var(--surface);
+    color: var(--text);
+    border: 1px solid var(--border);
+    border-radius: 4px;
+    padding: 0.2rem 0.5rem;
+    font-size: 1rem;
+    cursor: pointer;
+}
+.theme-toggle:hover { border-color: var(--accent); }
 .back {
     color: var(--accent);
     text-decoration: none;
@@ -260,6 +924,7 @@ header h1 { font-size: 1.4rem; margin-bottom: 0.3rem; }
 }
 .back:hover { text-decoration: underline; }
 .dim { color: var(--dim); font-size: 0.85rem; }
+.desc { color: var(--dim); font-size: 0.9rem; white-space: pre-line; margin: 0.2rem 0; }
 main { padding: 2rem; }
 .grid {
     display: grid;
@@ -280,7 +945,7 @@ main { padding: 2rem; }
     display: flex;
     align-items: center;
     justify-content: center;
-    background: #111;
+    background: var(--well);
     overflow: hidden;
 }
 .card-img img {
@@ -290,6 +955,17 @@ main { padding: 2rem; }
 }
 .card-info { padding: 0.8rem 1rem; }
 .card-info h2 { font-size: 1rem; margin-bottom: 0.2rem; }
+.pin { font-size: 0.9rem; }
+.tags { margin-top: 0.4rem; display: flex; flex-wrap: wrap; gap: 0.3rem; }
+.tag {
+    font-size: 0.75rem;
+    color: var(--text);
+    text-decoration: none;
+    border: 1px solid var(--border);
+    border-radius: 4px;
+    padding: 0.05rem 0.4rem;
+}
+.tag:hover { border-color: var(--accent); }
 .placeholder {
     color: var(--dim);
     font-size: 0.9rem;
@@ -303,7 +979,7 @@ main { padding: 2rem; }
 .board-img {
     text-align: center;
     margin: 1rem 0;
-    background: #111;
+    background: var(--well);
     padding: 1rem;
     border-radius: 8px;
 }
@@ -339,7 +1015,7 @@ pre {
     font-size: 0.8rem;
     line-height: 1.4;
     padding: 1rem;
-    background: #111;
+    background: var(--well);
     border-radius: 4px;
 }
 "#;
@@ -1,11 +1,19 @@
 mod board;
+mod colors;
 mod error;
 mod gallery;
+mod presets;
 mod python;
 mod render;
 mod server;
+mod svg_util;
+mod templates;
+mod worker;
 
 use crate::board::AppState;
+use crate::gallery::GalleryConfig;
+use crate::python::SandboxConfig;
+use crate::render::RenderConfig;
 use crate::server::ScryServer;
 use clap::Parser;
 use rmcp::ServiceExt;
@@ -22,25 +30,175 @@ struct Cli {
     /// Gallery web server port. Omit to run headless (no HTTP listener).
     #[arg(long)]
     port: Option<u16>,
+    /// URL other devices reach the gallery at, e.g. http://192.168.1.20:3333.
+    /// Board links, share links and QR codes use it instead of --address
+    #[arg(long, value_name = "URL", value_parser = gallery::parse_public_url)]
+    public_url: Option<String>,
     /// Directory to write PNG/SVG output files. Created if it doesn't exist.
     #[arg(long)]
     output_dir: Option<PathBuf>,
+    /// Directory of `.py` code templates for the whiteboard_template tool
+    #[arg(long)]
+    template_dir: Option<PathBuf>,
+    /// Python recursion limit for sandboxed code (at most 20000, which the
+    /// Python threads' stack can hold)
+    #[arg(long, default_value_t = python::DEFAULT_RECURSION_LIMIT,
+          value_parser = clap::value_parser!(u32).range(50..=python::MAX_RECURSION_LIMIT as i64))]
+    recursion_limit: u32,
+    /// Pre-import a module into every namespace and remove it from the blocklist (repeatable)
+    #[arg(long = "allow-module", value_name = "MODULE")]
+    allow_modules: Vec<String>,
+    /// Block an additional module via sys.modules (repeatable; wins over --allow-module)
+    #[arg(long = "block-module", value_name = "MODULE")]
+    block_modules: Vec<String>,
+    /// Pre-import numpy as `np` (numpy exposes file I/O, so this loosens the sandbox)
+    #[arg(long)]
+    allow_numpy: bool,
+    /// Pre-import an installed third-party package, bound by its top-level name (repeatable).
+    /// Packages can expose whatever they import, so only allow ones you trust.
+    #[arg(long = "allow-package", value_name = "PACKAGE")]
+    allow_packages: Vec<String>,
+    /// Keep at most this many bytes of a run's printed output; the rest is
+    /// dropped with a truncation marker
+    #[arg(long, value_name = "BYTES", default_value_t = python::DEFAULT_MAX_STDOUT_BYTES as u64,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    max_stdout_bytes: u64,
+    /// Reject code whose syntax tree has more than N nodes before running it
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_ast_nodes: Option<u64>,
+    /// Refuse to restore variables (whiteboard_import) whose JSON holds an
+    /// array longer than this
+    #[arg(long, value_name = "N", default_value_t = python::DEFAULT_MAX_ARRAY_SIZE as u64,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    max_array_size: u64,
+    /// Refuse to restore variables whose JSON holds an object with more entries than this
+    #[arg(long, value_name = "N", default_value_t = python::DEFAULT_MAX_MAP_SIZE as u64,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    max_map_size: u64,
+    /// Run board code in N worker processes instead of the server's own
+    /// interpreter, so different boards execute in parallel
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..=64))]
+    python_workers: Option<u64>,
+    /// Limit whiteboard executions across all boards to this many per second;
+    /// calls over the limit fail fast instead of queueing
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_exec_per_sec: Option<u32>,
+    /// Refuse all code execution (whiteboard, whiteboard_batch,
+    /// whiteboard_template); read tools and the gallery keep working
+    #[arg(long)]
+    disable_execution: bool,
+    /// Allow cross-origin GETs to the gallery from this origin, or `*` for any (repeatable)
+    #[arg(long = "gallery-cors-origin", value_name = "ORIGIN")]
+    gallery_cors_origins: Vec<String>,
+    /// Downscale any stored PNG larger than this many bytes until it fits
+    #[arg(long, value_name = "BYTES", value_parser = clap::value_parser!(u64).range(1024..))]
+    max_png_bytes: Option<u64>,
+    /// WIDTH for new boards created without an explicit width
+    #[arg(long, value_name = "PX", default_value_t = render::DEFAULT_WIDTH,
+          value_parser = clap::value_parser!(u32).range(1..=render::MAX_DIMENSION as i64))]
+    default_width: u32,
+    /// HEIGHT for new boards created without an explicit height
+    #[arg(long, value_name = "PX", default_value_t = render::DEFAULT_HEIGHT,
+          value_parser = clap::value_parser!(u32).range(1..=render::MAX_DIMENSION as i64))]
+    default_height: u32,
+    /// Maximum SVG rasterizations running at once [default: number of CPUs]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_concurrent_renders: Option<u64>,
+    /// Font family for SVG text that doesn't set font-family
+    /// [default: the embedded font with the embedded-font feature, else Times New Roman]
+    #[arg(long, value_name = "FAMILY")]
+    default_font_family: Option<String>,
+    /// Stamp this label on every rendered PNG, e.g. `bottom-right:© Ana`.
+    /// The optional corner prefix is top-left, top-right, bottom-left or bottom-right
+    #[arg(long, value_name = "[CORNER:]TEXT", value_parser = render::Watermark::parse)]
+    watermark: Option<render::Watermark>,
+    /// Log verbosity for scry-mcp's own messages (RUST_LOG overrides this when set)
+    #[arg(long, value_name = "LEVEL", default_value = "info",
+          value_parser = ["trace", "debug", "info", "warn", "error"])]
+    log_level: String,
+    /// Only log errors; shorthand for --log-level error
+    #[arg(long, short, conflicts_with = "log_level")]
+    quiet: bool,
+    /// Serve the gallery strictly read-only: any non-GET request is refused
+    #[arg(long)]
+    readonly_gallery: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Run a Python (or plain SVG) file through the pipeline once and write the
+    /// PNG, without starting the MCP server. Sandbox flags still apply.
+    Render(RenderArgs),
+    /// Serve board namespaces for a parent server (see --python-workers)
+    #[command(name = worker::WORKER_SUBCOMMAND, hide = true)]
+    PythonWorker,
+}
+
+#[derive(clap::Args)]
+struct RenderArgs {
+    /// Python file calling svg(), or an .svg file to render as-is
+    #[arg(long, value_name = "FILE")]
+    code: PathBuf,
+    /// Where to write the PNG
+    #[arg(long, value_name = "FILE")]
+    out: PathBuf,
+    /// Canvas width exposed to the code as WIDTH [default: --default-width]
+    #[arg(long, value_name = "PX",
+          value_parser = clap::value_parser!(u32).range(1..=render::MAX_DIMENSION as i64))]
+    width: Option<u32>,
+    /// Canvas height exposed to the code as HEIGHT [default: --default-height]
+    #[arg(long, value_name = "PX",
+          value_parser = clap::value_parser!(u32).range(1..=render::MAX_DIMENSION as i64))]
+    height: Option<u32>,
+    /// Seed `random` before running, for reproducible output
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+fn main() -> anyhow::Result<()> {
+    // Python runs on the blocking pool; give those threads room for deep
+    // C-level recursion so the recursion limit trips before the native stack.
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_stack_size(python::PYTHON_STACK_SIZE)
+        .build()?;
+    let result = runtime.block_on(run());
+    // The stdio transport may still be parked in a blocking stdin read after a
+    // signal-initiated shutdown; don't wait for it.
+    runtime.shutdown_background();
+    result
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn run() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     // Tracing MUST go to stderr — stdout is MCP JSON-RPC transport
     tracing_subscriber::fmt()
-        .with_env_filter(
-            EnvFilter::from_default_env()
-                .add_directive("scry_mcp=info".parse()?)
-        )
+        // RUST_LOG wins when set (e.g. scry_mcp=debug to log executed code)
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+            let level = if cli.quiet { "error" } else { cli.log_level.as_str() };
+            EnvFilter::new(format!("scry_mcp={level}"))
+        }))
         .with_writer(std::io::stderr)
         .with_ansi(false)
         .init();
 
+    if let Some(Command::PythonWorker) = cli.command {
+        return tokio::task::spawn_blocking(worker::serve).await?;
+    }
+
+    if let Some(Command::Render(args)) = &cli.command {
+        // Print just the message (a Python traceback reads better without an
+        // anyhow backtrace under it)
+        if let Err(e) = render_once(args, build_sandbox(&cli), &build_render(&cli)).await {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Validate and create output directory if requested
     if let Some(ref dir) = cli.output_dir {
         std::fs::create_dir_all(dir).map_err(|e| {
@@ -49,6 +207,14 @@ async fn main() -> anyhow::Result<()> {
         tracing::info!("File output enabled: {}", dir.display());
     }
 
+    if let Some(ref dir) = cli.template_dir {
+        if !dir.is_dir() {
+            anyhow::bail!("--template-dir {} is not a directory", dir.display());
+        }
+        let names = templates::list(dir);
+        tracing::info!("Templates from {}: {}", dir.display(), names.join(", "));
+    }
+
     let gallery_addr = cli.port.map(|p| (cli.address.clone(), p));
 
     match &gallery_addr {
@@ -56,7 +222,69 @@ async fn main() -> anyhow::Result<()> {
         None => tracing::info!("Scry MCP starting — headless (no gallery)"),
     }
 
-    let state = AppState::new(gallery_addr.clone(), cli.output_dir);
+    let sandbox = build_sandbox(&cli);
+    tracing::info!("Sandbox modules available: {}", sandbox.safe_modules.join(", "));
+    tracing::info!("Sandbox modules blocked: {}", sandbox.blocked_modules.join(", "));
+    if !sandbox.packages.is_empty() {
+        tracing::info!("Sandbox packages available: {}", sandbox.packages.join(", "));
+    }
+    if let Some(rate) = sandbox.max_exec_per_sec {
+        tracing::info!("Whiteboard executions limited to {rate}/s");
+    }
+    if sandbox.disable_execution {
+        tracing::info!("Code execution disabled; boards are read-only");
+    }
+    if sandbox.allow_numpy {
+        tracing::warn!("numpy enabled in sandbox — np.load/np.fromfile/tofile can touch the filesystem");
+    }
+    let cors_origins = cli
+        .gallery_cors_origins
+        .iter()
+        .map(|o| {
+            axum::http::HeaderValue::from_str(o)
+                .map_err(|e| anyhow::anyhow!("Invalid --gallery-cors-origin {o:?}: {e}"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if !cors_origins.is_empty() {
+        tracing::info!("Gallery CORS enabled for: {}", cli.gallery_cors_origins.join(", "));
+    }
+    if cli.readonly_gallery {
+        tracing::info!("Gallery is read-only");
+    }
+    if let Some(ref url) = cli.public_url {
+        tracing::info!("Gallery links use {url}");
+    }
+    let gallery = GalleryConfig {
+        cors_origins,
+        readonly: cli.readonly_gallery,
+        public_url: cli.public_url.clone(),
+    };
+
+    let render = build_render(&cli);
+    tracing::info!("Concurrent renders: {}", render.max_concurrent_renders);
+    if let Some(ref family) = render.default_font_family {
+        tracing::info!("Default font family: {family}");
+    }
+    if let Some(ref watermark) = render.watermark {
+        tracing::info!("Watermarking renders with {:?} ({:?})", watermark.text, watermark.corner);
+    }
+    if let Some(max) = render.max_png_bytes {
+        tracing::info!("PNG size budget: {max} bytes");
+    }
+
+    let state = AppState::new(
+        gallery_addr.clone(),
+        cli.output_dir,
+        cli.template_dir,
+        sandbox,
+        gallery,
+        render,
+    );
+
+    if let Some(ref workers) = state.workers {
+        workers.start().await?;
+        tracing::info!("Python workers: {}", workers.len());
+    }
 
     // Spawn web gallery only if --port was provided
     let gallery_handle = if let Some((ref addr, port)) = gallery_addr {
@@ -65,8 +293,12 @@ async fn main() -> anyhow::Result<()> {
         let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
         tracing::info!("Gallery listening on {bind_addr}");
 
+        let shutdown = state.shutdown.clone();
         Some(tokio::spawn(async move {
-            if let Err(e) = axum::serve(listener, gallery_router).await {
+            if let Err(e) = axum::serve(listener, gallery_router)
+                .with_graceful_shutdown(shutdown.cancelled_owned())
+                .await
+            {
                 tracing::error!("Gallery server error: {e}");
             }
         }))
@@ -74,20 +306,137 @@ async fn main() -> anyhow::Result<()> {
         None
     };
 
-    // Serve MCP on stdio
-    let server = ScryServer::new(state);
-    let service = server.serve(stdio()).await.inspect_err(|e| {
-        tracing::error!("MCP serve error: {e:?}");
-    })?;
+    // Ctrl-C / SIGTERM trigger the same shutdown path as the client hanging up
+    let shutdown = state.shutdown.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        tracing::info!("Received shutdown signal");
+        shutdown.cancel();
+    });
+
+    // Serve MCP on stdio. The child token stops accepting MCP calls once
+    // shutdown starts; the select covers a signal arriving mid-handshake.
+    let server = ScryServer::new(state.clone());
+    let service = tokio::select! {
+        biased;
+        _ = state.shutdown.cancelled() => None,
+        result = server.serve_with_ct(stdio(), state.shutdown.child_token()) => {
+            Some(result.inspect_err(|e| {
+                tracing::error!("MCP serve error: {e:?}");
+            })?)
+        }
+    };
 
     // Wait for MCP session to end
-    service.waiting().await?;
-    tracing::info!("MCP session ended, shutting down");
+    if let Some(service) = service {
+        let reason = service.waiting().await?;
+        tracing::info!("MCP session ended ({reason:?}), shutting down");
+    }
+    state.shutdown.cancel();
 
-    // Shutdown gallery
-    if let Some(handle) = gallery_handle {
-        handle.abort();
+    // Let the gallery finish in-flight requests, but don't hang on a stuck client
+    if let Some(mut handle) = gallery_handle {
+        if tokio::time::timeout(GALLERY_DRAIN_TIMEOUT, &mut handle).await.is_err() {
+            tracing::warn!("Gallery did not drain within {GALLERY_DRAIN_TIMEOUT:?}, aborting");
+            handle.abort();
+        }
     }
 
+    let board_count = state.boards.read().await.len();
+    tracing::info!("Shutdown complete — {board_count} boards in memory");
+
+    Ok(())
+}
+
+fn build_sandbox(cli: &Cli) -> SandboxConfig {
+    let sandbox = SandboxConfig {
+        recursion_limit: cli.recursion_limit,
+        allow_numpy: cli.allow_numpy,
+        max_exec_per_sec: cli.max_exec_per_sec,
+        disable_execution: cli.disable_execution,
+        max_stdout_bytes: cli.max_stdout_bytes as usize,
+        max_ast_nodes: cli.max_ast_nodes.map(|n| n as usize),
+        max_array_size: cli.max_array_size as usize,
+        max_map_size: cli.max_map_size as usize,
+        python_workers: cli.python_workers.unwrap_or(0) as usize,
+        ..Default::default()
+    }
+    .with_module_overrides(&cli.allow_modules, &cli.block_modules);
+    pyo3::Python::attach(|py| sandbox.with_packages(py, &cli.allow_packages))
+}
+
+fn build_render(cli: &Cli) -> RenderConfig {
+    RenderConfig {
+        max_png_bytes: cli.max_png_bytes.map(|b| b as usize),
+        max_concurrent_renders: cli
+            .max_concurrent_renders
+            .map_or_else(render::default_render_concurrency, |n| n as usize),
+        default_width: cli.default_width,
+        default_height: cli.default_height,
+        watermark: cli.watermark.clone(),
+        default_font_family: cli.default_font_family.clone(),
+    }
+}
+
+/// `scry-mcp render`: execute one file and write its PNG.
+async fn render_once(args: &RenderArgs, sandbox: SandboxConfig, render: &RenderConfig) -> anyhow::Result<()> {
+    let source = std::fs::read_to_string(&args.code)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", args.code.display()))?;
+
+    let svg = if args.code.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg")) {
+        source
+    } else {
+        let width = args.width.unwrap_or(render.default_width);
+        let height = args.height.unwrap_or(render.default_height);
+        let namespace =
+            python::Namespace::Local(python::create_namespace_async(width, height, sandbox.clone()).await?);
+        let (result, _) = python::run_python(namespace, source, width, height, args.seed, None, &sandbox).await?;
+        // The code's own output is the only thing on stdout in this mode
+        print!("{}", result.stdout);
+        result
+            .svg_content
+            .ok_or_else(|| anyhow::anyhow!("{} ran but svg() was not called", args.code.display()))?
+    };
+
+    let out = render::svg_to_png_with_quality(&svg, render::Quality::default(), render)?;
+    for warning in &out.warnings {
+        eprintln!("warning: {warning}");
+    }
+    std::fs::write(&args.out, &out.png)
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {e}", args.out.display()))?;
+    eprintln!("Wrote {} ({}x{}, {} bytes)", args.out.display(), out.width, out.height, out.png.len());
     Ok(())
 }
+
+/// How long the gallery gets to finish in-flight requests on shutdown.
+const GALLERY_DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
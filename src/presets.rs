@@ -0,0 +1,109 @@
+//! Named style presets for the `whiteboard` tool's `style` parameter.
+//!
+//! A preset is a small table of colors and defaults injected into the
+//! namespace as `STYLE_*` variables before the code runs, so a script can
+//! write `fill=STYLE_BG` instead of repeating hex codes on every call.
+
+/// Colors and defaults exposed to code as the [`VARIABLES`].
+#[derive(Debug, PartialEq)]
+pub struct Preset {
+    pub name: &'static str,
+    /// `STYLE_BG`: canvas background.
+    pub bg: &'static str,
+    /// `STYLE_FG`: text and primary strokes.
+    pub fg: &'static str,
+    /// `STYLE_ACCENT`: highlights.
+    pub accent: &'static str,
+    /// `STYLE_GRID`: gridlines, axes and other quiet structure.
+    pub grid: &'static str,
+    /// `STYLE_FONT`: font-family value.
+    pub font: &'static str,
+    /// `STYLE_STROKE_WIDTH`: default line width in pixels.
+    pub stroke_width: f64,
+    /// `STYLE_PALETTE`: categorical colors for series.
+    pub palette: &'static [&'static str],
+}
+
+/// Namespace names a preset sets.
+pub const VARIABLES: &[&str] = &[
+    "STYLE_BG",
+    "STYLE_FG",
+    "STYLE_ACCENT",
+    "STYLE_GRID",
+    "STYLE_FONT",
+    "STYLE_STROKE_WIDTH",
+    "STYLE_PALETTE",
+];
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "dark",
+        bg: "#1a1a2e",
+        fg: "#e0e0e0",
+        accent: "#e94560",
+        grid: "#2e2e4f",
+        font: "sans-serif",
+        stroke_width: 2.0,
+        palette: &["#e94560", "#4cc9f0", "#f9c74f", "#90be6d", "#b388eb", "#f3722c"],
+    },
+    Preset {
+        name: "light",
+        bg: "#ffffff",
+        fg: "#222222",
+        accent: "#1f77b4",
+        grid: "#e5e5e5",
+        font: "sans-serif",
+        stroke_width: 2.0,
+        palette: &["#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b"],
+    },
+    Preset {
+        name: "blueprint",
+        bg: "#0b3d91",
+        fg: "#e8f1ff",
+        accent: "#ffd166",
+        grid: "#2f5fb3",
+        font: "monospace",
+        stroke_width: 1.5,
+        palette: &["#e8f1ff", "#ffd166", "#9ad1ff", "#ff8fab", "#b8f2e6"],
+    },
+    Preset {
+        name: "minimal",
+        bg: "#fafafa",
+        fg: "#333333",
+        accent: "#000000",
+        grid: "#dddddd",
+        font: "sans-serif",
+        stroke_width: 1.0,
+        palette: &["#333333", "#777777", "#aaaaaa", "#000000"],
+    },
+];
+
+/// Look up a preset by name.
+pub fn get(name: &str) -> Result<&'static Preset, String> {
+    PRESETS.iter().find(|p| p.name == name).ok_or_else(|| {
+        let known: Vec<&str> = PRESETS.iter().map(|p| p.name).collect();
+        format!("Unknown style {name:?} (expected one of: {})", known.join(", "))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        assert_eq!(get("blueprint").unwrap().name, "blueprint");
+        let err = get("neon").unwrap_err();
+        assert!(err.contains("Unknown style \"neon\""), "{err}");
+        assert!(err.contains("dark, light, blueprint, minimal"), "{err}");
+    }
+
+    #[test]
+    fn test_preset_colors_parse() {
+        for preset in PRESETS {
+            for color in [preset.bg, preset.fg, preset.accent, preset.grid].iter().chain(preset.palette) {
+                assert!(crate::colors::parse(color).is_ok(), "{}: {color}", preset.name);
+            }
+        }
+    }
+}
@@ -1,24 +1,371 @@
+use crate::board::html_escape;
+use crate::colors;
 use crate::error::ScryError;
+use crate::presets::{self, Preset};
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyModule};
-use std::ffi::CString;
+use pyo3::types::{PyDict, PyModule, PyString, PyTuple};
+use crate::worker::RemoteNamespace;
 use std::sync::{Arc, Mutex};
 
+/// The `svg()` builtin. Every call is kept, in order.
 #[pyclass]
 struct SvgCallback {
-    inner: Arc<Mutex<Option<String>>>,
+    inner: Arc<Mutex<Vec<String>>>,
 }
 
 #[pymethods]
 impl SvgCallback {
     fn __call__(&self, content: String) -> PyResult<()> {
-        *self.inner.lock().unwrap() = Some(content);
+        self.inner.lock().unwrap().push(content);
         Ok(())
     }
 }
 
+/// Default cap on stdout captured from one run.
+pub const DEFAULT_MAX_STDOUT_BYTES: usize = 64 * 1024;
+
+/// Default caps on a single JSON array / object converted into Python values.
+pub const DEFAULT_MAX_ARRAY_SIZE: usize = 100_000;
+pub const DEFAULT_MAX_MAP_SIZE: usize = 10_000;
+
+/// Reject JSON holding an array or object larger than the configured caps,
+/// at any depth, before any Python objects are built from it.
+pub fn check_json_size(value: &serde_json::Value, config: &SandboxConfig) -> Result<(), String> {
+    let (max_array, max_map) = (config.max_array_size, config.max_map_size);
+    let mut pending = vec![value];
+    while let Some(value) = pending.pop() {
+        match value {
+            serde_json::Value::Array(items) if items.len() > max_array => {
+                return Err(format!("JSON array of {} items exceeds the limit of {max_array}", items.len()));
+            }
+            serde_json::Value::Object(map) if map.len() > max_map => {
+                return Err(format!("JSON object of {} entries exceeds the limit of {max_map}", map.len()));
+            }
+            serde_json::Value::Array(items) => pending.extend(items),
+            serde_json::Value::Object(map) => pending.extend(map.values()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Stand-in for `sys.stdout` during a run. Keeps the first `limit` bytes and
+/// only counts the rest, so a print loop can't grow memory or the response.
+#[pyclass]
+#[derive(Default)]
+struct StdoutCapture {
+    buf: String,
+    limit: usize,
+    dropped: usize,
+}
+
+#[pymethods]
+impl StdoutCapture {
+    fn write(&mut self, s: &str) -> usize {
+        let room = self.limit.saturating_sub(self.buf.len());
+        if s.len() <= room {
+            self.buf.push_str(s);
+        } else {
+            let mut cut = room;
+            while !s.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            self.buf.push_str(&s[..cut]);
+            self.dropped += s.len() - cut;
+        }
+        s.chars().count()
+    }
+
+    fn flush(&self) {}
+}
+
+impl StdoutCapture {
+    fn into_output(self) -> String {
+        if self.dropped == 0 {
+            return self.buf;
+        }
+        format!("{}\n...[truncated {} bytes]\n", self.buf, self.dropped)
+    }
+}
+
+struct Layer {
+    name: String,
+    fragment: String,
+    z: i64,
+}
+
+/// The `layer()` builtin: named SVG fragments composited into one document
+/// when a run ends without calling `svg()`. Lives in the namespace, so layers
+/// accumulate across calls to the same board.
+#[pyclass]
+struct LayerStack {
+    layers: Vec<Layer>,
+}
+
+#[pymethods]
+impl LayerStack {
+    /// Set (or replace) a layer. Lower `z` draws first; ties keep insertion order.
+    #[pyo3(signature = (name, fragment, z=None))]
+    fn __call__(&mut self, name: String, fragment: String, z: Option<i64>) {
+        match self.layers.iter_mut().find(|l| l.name == name) {
+            Some(layer) => {
+                layer.fragment = fragment;
+                if let Some(z) = z {
+                    layer.z = z;
+                }
+            }
+            None => self.layers.push(Layer {
+                name,
+                fragment,
+                z: z.unwrap_or(0),
+            }),
+        }
+    }
+
+    /// Remove a layer by name. Returns whether it existed.
+    fn remove(&mut self, name: &str) -> bool {
+        let before = self.layers.len();
+        self.layers.retain(|l| l.name != name);
+        self.layers.len() != before
+    }
+
+    fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Layer names in draw order.
+    fn names(&self) -> Vec<String> {
+        self.ordered().map(|l| l.name.clone()).collect()
+    }
+}
+
+impl LayerStack {
+    fn ordered(&self) -> impl Iterator<Item = &Layer> {
+        let mut layers: Vec<&Layer> = self.layers.iter().collect();
+        layers.sort_by_key(|l| l.z); // stable
+        layers.into_iter()
+    }
+
+    /// Wrap every layer in a `<g>` inside a full SVG document, or None if empty.
+    fn composite(&self, width: u32, height: u32) -> Option<String> {
+        if self.layers.is_empty() {
+            return None;
+        }
+        let mut doc = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        for layer in self.ordered() {
+            doc.push_str(&format!(
+                "\n<g id=\"layer-{}\">{}</g>",
+                html_escape(&layer.name),
+                layer.fragment
+            ));
+        }
+        doc.push_str("\n</svg>");
+        Some(doc)
+    }
+}
+
+/// The `svg_doc()` builtin: wraps children in a full document and hands it to
+/// the same per-run storage as `svg()`.
+#[pyclass]
+struct SvgDocCallback {
+    inner: Arc<Mutex<Vec<String>>>,
+}
+
+#[pymethods]
+impl SvgDocCallback {
+    /// `svg_doc(width, height, *children, **attrs)`; returns the document too.
+    #[pyo3(signature = (width, height, *children, **attrs))]
+    fn __call__(
+        &self,
+        width: f64,
+        height: f64,
+        children: &Bound<'_, PyTuple>,
+        attrs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<String> {
+        let doc = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}"{}>{}</svg>"#,
+            svg_attrs(attrs)?,
+            join_children(children)?
+        );
+        self.inner.lock().unwrap().push(doc.clone());
+        Ok(doc)
+    }
+}
+
+/// Format keyword arguments as SVG attributes. `stroke_width` becomes
+/// `stroke-width`, a trailing underscore is dropped (`class_` → `class`), and
+/// `None` values are omitted.
+fn svg_attrs(attrs: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    let mut out = String::new();
+    let Some(attrs) = attrs else {
+        return Ok(out);
+    };
+    for (key, value) in attrs.iter() {
+        if value.is_none() {
+            continue;
+        }
+        let key: String = key.extract()?;
+        let name = key.strip_suffix('_').unwrap_or(&key).replace('_', "-");
+        out.push_str(&format!(r#" {name}="{}""#, html_escape(&value.str()?.to_cow()?)));
+    }
+    Ok(out)
+}
+
+/// Concatenate child element strings; a list or tuple child is flattened one
+/// level so comprehensions can be passed directly.
+fn join_children(children: &Bound<'_, PyTuple>) -> PyResult<String> {
+    let mut out = String::new();
+    for child in children.iter() {
+        if let Ok(s) = child.cast::<PyString>() {
+            out.push_str(&s.to_cow()?);
+        } else {
+            for item in child.try_iter()? {
+                out.push_str(&item?.extract::<String>()?);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// `rect(x, y, width, height, **attrs)` → `<rect>` element string.
+#[pyfunction]
+#[pyo3(signature = (x, y, width, height, **attrs))]
+fn rect(x: f64, y: f64, width: f64, height: f64, attrs: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    Ok(format!(
+        r#"<rect x="{x}" y="{y}" width="{width}" height="{height}"{}/>"#,
+        svg_attrs(attrs)?
+    ))
+}
+
+/// `circle(cx, cy, r, **attrs)` → `<circle>` element string.
+#[pyfunction]
+#[pyo3(signature = (cx, cy, r, **attrs))]
+fn circle(cx: f64, cy: f64, r: f64, attrs: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    Ok(format!(r#"<circle cx="{cx}" cy="{cy}" r="{r}"{}/>"#, svg_attrs(attrs)?))
+}
+
+/// `line(x1, y1, x2, y2, **attrs)` → `<line>` element string. Lines need a
+/// `stroke` to be visible.
+#[pyfunction]
+#[pyo3(signature = (x1, y1, x2, y2, **attrs))]
+fn line(x1: f64, y1: f64, x2: f64, y2: f64, attrs: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    Ok(format!(
+        r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}"{}/>"#,
+        svg_attrs(attrs)?
+    ))
+}
+
+/// `text(x, y, content, **attrs)` → `<text>` element string; `content` is escaped.
+#[pyfunction]
+#[pyo3(signature = (x, y, content, **attrs))]
+fn text(x: f64, y: f64, content: &str, attrs: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    Ok(format!(
+        r#"<text x="{x}" y="{y}"{}>{}</text>"#,
+        svg_attrs(attrs)?,
+        html_escape(content)
+    ))
+}
+
+/// `group(*children, **attrs)` → `<g>` element string wrapping the children.
+#[pyfunction]
+#[pyo3(signature = (*children, **attrs))]
+fn group(children: &Bound<'_, PyTuple>, attrs: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    Ok(format!("<g{}>{}</g>", svg_attrs(attrs)?, join_children(children)?))
+}
+
+/// Render gradient `<stop>` elements from `(offset, color)` pairs.
+fn gradient_stops(stops: &[(f64, String)]) -> String {
+    stops
+        .iter()
+        .map(|(offset, color)| {
+            format!(
+                r#"<stop offset="{}" stop-color="{}"/>"#,
+                offset.clamp(0.0, 1.0),
+                html_escape(color)
+            )
+        })
+        .collect()
+}
+
+/// `linear_gradient(id, stops, angle=0)` → `<defs>` fragment. `stops` is a list
+/// of `(offset, color)` with offsets in 0..1; `angle` is in degrees, 0 = left→right.
+#[pyfunction]
+#[pyo3(signature = (id, stops, angle=0.0))]
+fn linear_gradient(id: &str, stops: Vec<(f64, String)>, angle: f64) -> String {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (x1, y1) = (0.5 - cos / 2.0, 0.5 - sin / 2.0);
+    let (x2, y2) = (0.5 + cos / 2.0, 0.5 + sin / 2.0);
+    format!(
+        r#"<defs><linearGradient id="{}" x1="{x1:.4}" y1="{y1:.4}" x2="{x2:.4}" y2="{y2:.4}">{}</linearGradient></defs>"#,
+        html_escape(id),
+        gradient_stops(&stops)
+    )
+}
+
+/// `radial_gradient(id, stops)` → `<defs>` fragment centered on the shape.
+#[pyfunction]
+fn radial_gradient(id: &str, stops: Vec<(f64, String)>) -> String {
+    format!(
+        r#"<defs><radialGradient id="{}">{}</radialGradient></defs>"#,
+        html_escape(id),
+        gradient_stops(&stops)
+    )
+}
+
+fn color_err(msg: String) -> PyErr {
+    pyo3::exceptions::PyValueError::new_err(msg)
+}
+
+/// `oklch(l, c, h, alpha=1.0)` → hex string; out-of-gamut colors are clipped.
+#[pyfunction]
+#[pyo3(name = "oklch", signature = (l, c, h, alpha=1.0))]
+fn py_oklch(l: f32, c: f32, h: f32, alpha: f32) -> String {
+    colors::oklch(l, c, h, alpha)
+}
+
+/// `oklab(l, a, b)` → hex string; out-of-gamut colors are clipped.
+#[pyfunction]
+#[pyo3(name = "oklab")]
+fn py_oklab(l: f32, a: f32, b: f32) -> String {
+    colors::oklab(l, a, b, 1.0)
+}
+
+/// `oklaba(l, a, b, alpha)` → hex string with an alpha channel.
+#[pyfunction]
+#[pyo3(name = "oklaba")]
+fn py_oklaba(l: f32, a: f32, b: f32, alpha: f32) -> String {
+    colors::oklab(l, a, b, alpha)
+}
+
+/// `color_mix(a, b, t)` → hex string interpolated in Oklab.
+#[pyfunction]
+#[pyo3(name = "color_mix")]
+fn py_color_mix(a: &str, b: &str, t: f32) -> PyResult<String> {
+    colors::color_mix(a, b, t).map_err(color_err)
+}
+
+/// `hue_shift(color, degrees)` → hex string rotated in Oklch.
+#[pyfunction]
+#[pyo3(name = "hue_shift")]
+fn py_hue_shift(color: &str, degrees: f32) -> PyResult<String> {
+    colors::hue_shift(color, degrees).map_err(color_err)
+}
+
+/// `ease(t, kind="in_out")` → eased value in 0..1.
+#[pyfunction]
+#[pyo3(name = "ease", signature = (t, kind="in_out"))]
+fn py_ease(t: f64, kind: &str) -> PyResult<f64> {
+    colors::ease(kind, t).map_err(color_err)
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct ExecResult {
+    /// The last `svg()` call — the board's canonical render.
     pub svg_content: Option<String>,
+    /// Any earlier `svg()` calls from the same run, in call order.
+    pub earlier_svgs: Vec<String>,
     pub stdout: String,
 }
 
@@ -35,6 +382,16 @@ const BLOCKED_BUILTINS: &[&str] = &[
     "breakpoint", // drops into debugger (blocks)
 ];
 
+/// Stdlib modules pre-imported into every namespace by default.
+const SAFE_MODULES: &[&str] = &[
+    "math", "random", "json", "re", "textwrap", "itertools", "functools",
+    "collections", "colorsys", "hashlib", "string", "dataclasses",
+];
+
+/// numpy submodules imported eagerly. numpy loads some of these lazily, and a
+/// lazy import after the blocklist is applied would fail on `os`/`importlib`.
+const NUMPY_MODULES: &[&str] = &["numpy", "numpy.random", "numpy.linalg", "numpy.fft"];
+
 /// Modules blocked by setting to None in sys.modules.
 /// This prevents `from X import Y` patterns. Combined with __import__
 /// removal, this closes the standard import paths.
@@ -66,8 +423,216 @@ const BLOCKED_MODULES: &[&str] = &[
     "resource",
 ];
 
+/// Default Python recursion limit for sandboxed code (CPython's own default).
+pub const DEFAULT_RECURSION_LIMIT: u32 = 1000;
+
+/// Highest `--recursion-limit` accepted. C-level recursion (`repr()` of a
+/// deeply nested list, say) at this depth still fits in [`PYTHON_STACK_SIZE`];
+/// 100,000 did not.
+pub const MAX_RECURSION_LIMIT: u32 = 20_000;
+
+/// Stack size for threads that run Python. Paired with the recursion limit so
+/// runaway recursion raises `RecursionError` instead of overflowing the native
+/// stack (tokio's default 2 MiB is too tight for C-level recursion).
+pub const PYTHON_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Operator-controlled sandbox settings, fixed at startup. Serializable so
+/// worker processes run under the same settings (see [`crate::worker`]).
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SandboxConfig {
+    pub recursion_limit: u32,
+    /// Modules pre-imported into every namespace.
+    pub safe_modules: Vec<String>,
+    /// Modules poisoned in `sys.modules`.
+    pub blocked_modules: Vec<String>,
+    /// Pre-import numpy as `np`. Off by default: numpy can read and write
+    /// files (`np.load`, `np.fromfile`, `tofile`) and reach `ctypes`.
+    pub allow_numpy: bool,
+    /// Installed third-party packages pre-imported and bound by their
+    /// top-level name (`shapely.geometry` binds `shapely`).
+    pub packages: Vec<String>,
+    /// Process-wide cap on `whiteboard` executions per second (None = unlimited).
+    pub max_exec_per_sec: Option<u32>,
+    /// Refuse every code execution; boards can still be read and exported.
+    pub disable_execution: bool,
+    /// Bytes of printed output kept per run; the rest is dropped with a marker.
+    pub max_stdout_bytes: usize,
+    /// Reject submissions whose syntax tree has more nodes than this.
+    pub max_ast_nodes: Option<usize>,
+    /// Longest JSON array turned into a Python value, see [`check_json_size`].
+    pub max_array_size: usize,
+    /// Most entries in a JSON object turned into a Python value.
+    pub max_map_size: usize,
+    /// Worker processes that hold board namespaces (0 = run in this process).
+    pub python_workers: usize,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        SandboxConfig {
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+            safe_modules: SAFE_MODULES.iter().map(|m| m.to_string()).collect(),
+            blocked_modules: BLOCKED_MODULES.iter().map(|m| m.to_string()).collect(),
+            allow_numpy: false,
+            packages: Vec::new(),
+            max_exec_per_sec: None,
+            disable_execution: false,
+            max_stdout_bytes: DEFAULT_MAX_STDOUT_BYTES,
+            max_ast_nodes: None,
+            max_array_size: DEFAULT_MAX_ARRAY_SIZE,
+            max_map_size: DEFAULT_MAX_MAP_SIZE,
+            python_workers: 0,
+        }
+    }
+}
+
+impl SandboxConfig {
+    /// Apply operator `--allow-module`/`--block-module` overrides to the defaults.
+    ///
+    /// Allowed modules are unblocked and pre-imported (user code still can't
+    /// `import` anything itself). Blocked modules are poisoned and dropped from
+    /// the pre-imports. A module named in both lists stays blocked.
+    pub fn with_module_overrides(mut self, allow: &[String], block: &[String]) -> Self {
+        for m in allow {
+            if block.contains(m) {
+                tracing::warn!("Module {m} is both allowed and blocked; blocking it");
+                continue;
+            }
+            self.blocked_modules.retain(|b| b != m);
+            if !self.safe_modules.contains(m) {
+                self.safe_modules.push(m.clone());
+            }
+        }
+        for m in block {
+            self.safe_modules.retain(|s| s != m);
+            if !self.blocked_modules.contains(m) {
+                self.blocked_modules.push(m.clone());
+            }
+        }
+        self
+    }
+
+    /// Add operator `--allow-package` packages, keeping only those that import.
+    ///
+    /// Missing packages are skipped with a warning rather than failing startup.
+    /// Call this before any namespace exists: once blocked modules are poisoned,
+    /// a package that imports one of them at load time can no longer load.
+    pub fn with_packages(mut self, py: Python<'_>, packages: &[String]) -> Self {
+        for pkg in packages {
+            let top = pkg.split('.').next().unwrap_or_default();
+            if self.blocked_modules.iter().any(|b| b == pkg || b == top) {
+                tracing::warn!("Package {pkg} is blocked; not allowing it");
+                continue;
+            }
+            match PyModule::import(py, pkg.as_str()) {
+                Ok(_) if !self.packages.contains(pkg) => self.packages.push(pkg.clone()),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("--allow-package {pkg} is not importable, skipping: {e}"),
+            }
+        }
+        self
+    }
+}
+
+/// Filename reported in tracebacks for user code.
+const USER_FILENAME: &str = "<whiteboard>";
+
+/// Compile user code under [`USER_FILENAME`] and register its source with
+/// `linecache` so tracebacks show the offending line, not just its number.
+///
+/// Uses the host's real `compile`, which stays out of reach of user code.
+fn compile_user_code<'py>(py: Python<'py>, code: &str) -> PyResult<Bound<'py, PyAny>> {
+    let linecache = PyModule::import(py, "linecache")?;
+    let lines: Vec<String> = code.split_inclusive('\n').map(str::to_owned).collect();
+    linecache
+        .getattr("cache")?
+        .set_item(USER_FILENAME, (code.len(), py.None(), lines, USER_FILENAME))?;
+
+    let builtins = PyModule::import(py, "builtins")?;
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("dont_inherit", true)?;
+    builtins
+        .getattr("compile")?
+        .call((code, USER_FILENAME, "exec"), Some(&kwargs))
+}
+
+/// Reject code whose syntax tree has more than `max` nodes, before it runs.
+/// Code that doesn't parse is left for [`compile_user_code`] to report.
+fn check_complexity(py: Python<'_>, code: &str, max: usize) -> Result<(), ScryError> {
+    let ast = PyModule::import(py, "ast").map_err(ScryError::from)?;
+    let Ok(tree) = ast.call_method1("parse", (code,)) else {
+        return Ok(());
+    };
+    let nodes = ast
+        .call_method1("walk", (tree,))
+        .and_then(|walk| walk.try_iter())
+        .map_err(ScryError::from)?
+        .take(max + 1)
+        .count();
+    if nodes > max {
+        return Err(ScryError::Python(format!(
+            "script too complex (more than {max} syntax nodes)"
+        )));
+    }
+    Ok(())
+}
+
+/// Format a Python exception the way the interpreter would print it,
+/// falling back to the bare message if the traceback module misbehaves.
+fn format_traceback(py: Python<'_>, err: &PyErr) -> String {
+    PyModule::import(py, "traceback")
+        .and_then(|tb| {
+            tb.call_method1(
+                "format_exception",
+                (err.get_type(py), err.value(py), err.traceback(py)),
+            )
+        })
+        .and_then(|lines| lines.extract::<Vec<String>>())
+        .map(|lines| lines.concat().trim_end().to_string())
+        .unwrap_or_else(|_| err.to_string())
+}
+
+/// Import numpy with the `sys.modules` blocklist temporarily lifted, since
+/// numpy itself needs `os`, `threading`, etc. The caller re-applies the
+/// blocklist afterwards. User code can't observe the window: it has no
+/// `__import__`, and no user code runs while the GIL is held here.
+fn import_numpy<'py>(py: Python<'py>, blocked: &[String]) -> PyResult<Bound<'py, PyModule>> {
+    let sys_modules = PyModule::import(py, "sys")?.getattr("modules")?;
+    for module_name in blocked {
+        if sys_modules.get_item(module_name).is_ok_and(|m| m.is_none()) {
+            sys_modules.del_item(module_name)?;
+        }
+    }
+    for module_name in NUMPY_MODULES {
+        PyModule::import(py, *module_name)?;
+    }
+    PyModule::import(py, "numpy")
+}
+
+/// A board's Python globals: in this process, or held by a worker process
+/// when `--python-workers` is set.
+pub enum Namespace {
+    Local(Py<PyDict>),
+    Remote(Arc<RemoteNamespace>),
+}
+
+impl Namespace {
+    /// Another handle to the same namespace.
+    pub fn clone_ref(&self) -> Self {
+        match self {
+            Namespace::Local(ns) => Namespace::Local(Python::attach(|py| ns.clone_ref(py))),
+            Namespace::Remote(ns) => Namespace::Remote(Arc::clone(ns)),
+        }
+    }
+}
+
 /// Create a new Python namespace for a board with safe stdlib imports and sandbox.
-pub fn create_namespace(py: Python<'_>, width: u32, height: u32) -> PyResult<Py<PyDict>> {
+pub fn create_namespace(
+    py: Python<'_>,
+    width: u32,
+    height: u32,
+    config: &SandboxConfig,
+) -> PyResult<Py<PyDict>> {
     let globals = PyDict::new(py);
 
     // Create a sanitized builtins dict (not the module itself)
@@ -82,34 +647,104 @@ pub fn create_namespace(py: Python<'_>, width: u32, height: u32) -> PyResult<Py<
 
     globals.set_item("__builtins__", safe_builtins)?;
 
-    // Pre-import safe stdlib modules
-    let safe_modules = [
-        "math", "random", "json", "re", "textwrap", "itertools", "functools",
-        "collections", "colorsys", "hashlib", "string", "dataclasses",
-    ];
-    for module_name in &safe_modules {
-        match PyModule::import(py, *module_name) {
-            Ok(m) => { globals.set_item(*module_name, m)?; }
+    // Pre-import safe modules
+    for module_name in &config.safe_modules {
+        match PyModule::import(py, module_name.as_str()) {
+            Ok(m) => { globals.set_item(module_name, m)?; }
             Err(e) => {
                 tracing::warn!("Failed to import {module_name}: {e}");
             }
         }
     }
 
+    // Importing the dotted path loads the submodule onto its parent package
+    for pkg in &config.packages {
+        let top = pkg.split('.').next().unwrap_or_default();
+        match PyModule::import(py, pkg.as_str()).and_then(|_| PyModule::import(py, top)) {
+            Ok(m) => globals.set_item(top, m)?,
+            Err(e) => tracing::warn!("Failed to import package {pkg}: {e}"),
+        }
+    }
+
+    if config.allow_numpy {
+        match import_numpy(py, &config.blocked_modules) {
+            Ok(np) => {
+                globals.set_item("np", &np)?;
+                globals.set_item("numpy", np)?;
+            }
+            Err(e) => tracing::warn!("--allow-numpy set but numpy failed to import: {e}"),
+        }
+    }
+
+    // SVG helpers
+    globals.set_item("linear_gradient", wrap_pyfunction!(linear_gradient, py)?)?;
+    globals.set_item("radial_gradient", wrap_pyfunction!(radial_gradient, py)?)?;
+    globals.set_item("rect", wrap_pyfunction!(rect, py)?)?;
+    globals.set_item("circle", wrap_pyfunction!(circle, py)?)?;
+    globals.set_item("line", wrap_pyfunction!(line, py)?)?;
+    globals.set_item("text", wrap_pyfunction!(text, py)?)?;
+    globals.set_item("group", wrap_pyfunction!(group, py)?)?;
+
+    // Color helpers (shared implementation in colors.rs)
+    globals.set_item("oklch", wrap_pyfunction!(py_oklch, py)?)?;
+    globals.set_item("oklab", wrap_pyfunction!(py_oklab, py)?)?;
+    globals.set_item("oklaba", wrap_pyfunction!(py_oklaba, py)?)?;
+    globals.set_item("color_mix", wrap_pyfunction!(py_color_mix, py)?)?;
+    globals.set_item("hue_shift", wrap_pyfunction!(py_hue_shift, py)?)?;
+    globals.set_item("ease", wrap_pyfunction!(py_ease, py)?)?;
+
+    // layer() persists with the namespace, unlike svg() which is per-run
+    globals.set_item("layer", Py::new(py, LayerStack { layers: Vec::new() })?)?;
+
     // Set canvas dimensions
     globals.set_item("WIDTH", width)?;
     globals.set_item("HEIGHT", height)?;
 
     // Block dangerous modules in sys.modules
+    set_recursion_limit(py, config)?;
     let sys = PyModule::import(py, "sys")?;
     let sys_modules = sys.getattr("modules")?;
-    for module_name in BLOCKED_MODULES {
-        sys_modules.set_item(*module_name, py.None())?;
+    for module_name in &config.blocked_modules {
+        sys_modules.set_item(module_name, py.None())?;
     }
 
     Ok(globals.into())
 }
 
+/// Apply the sandbox's recursion limit. It is interpreter-wide, so it is set
+/// for every namespace and again before every run.
+fn set_recursion_limit(py: Python<'_>, config: &SandboxConfig) -> PyResult<()> {
+    #[cfg(test)]
+    tests::wait_for_recursion_pin(py);
+    PyModule::import(py, "sys")?.call_method1("setrecursionlimit", (config.recursion_limit,))?;
+    Ok(())
+}
+
+/// Static checks every submission passes before it is compiled.
+fn precheck(py: Python<'_>, code: &str, config: &SandboxConfig) -> Result<(), ScryError> {
+    if code.contains('\0') {
+        return Err(ScryError::Syntax("Code contains null byte".into()));
+    }
+    match config.max_ast_nodes {
+        Some(max) => check_complexity(py, code, max),
+        None => Ok(()),
+    }
+}
+
+/// Compile code without running it, so syntax can be checked with no
+/// namespace side effects.
+pub fn check_syntax(py: Python<'_>, code: &str, config: &SandboxConfig) -> Result<(), ScryError> {
+    precheck(py, code, config)?;
+    compile_user_code(py, code).map(|_| ()).map_err(|e| {
+        let traceback = format_traceback(py, &e);
+        if e.is_instance_of::<pyo3::exceptions::PySyntaxError>(py) {
+            ScryError::Syntax(traceback)
+        } else {
+            ScryError::Python(traceback)
+        }
+    })
+}
+
 /// Execute Python code in a board's namespace, capturing SVG output and stdout.
 pub fn execute_python(
     py: Python<'_>,
@@ -117,15 +752,19 @@ pub fn execute_python(
     code: &str,
     width: u32,
     height: u32,
+    seed: Option<u64>,
+    config: &SandboxConfig,
 ) -> Result<ExecResult, ScryError> {
     let globals = namespace.bind(py);
+    precheck(py, code, config)?;
+    set_recursion_limit(py, config).map_err(ScryError::from)?;
 
     // Update dimensions in case they changed
     globals.set_item("WIDTH", width).map_err(ScryError::from)?;
     globals.set_item("HEIGHT", height).map_err(ScryError::from)?;
 
     // Create SVG callback
-    let svg_storage: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+    let svg_storage: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
     let callback = Py::new(
         py,
         SvgCallback {
@@ -134,43 +773,66 @@ pub fn execute_python(
     )
     .map_err(ScryError::from)?;
     globals.set_item("svg", callback).map_err(ScryError::from)?;
+    // svg_doc() shares svg()'s storage, so it is rebound every run as well
+    let doc_callback = Py::new(
+        py,
+        SvgDocCallback {
+            inner: Arc::clone(&svg_storage),
+        },
+    )
+    .map_err(ScryError::from)?;
+    globals.set_item("svg_doc", doc_callback).map_err(ScryError::from)?;
 
     // Redirect stdout to capture prints
-    let io_module = PyModule::import(py, "io").map_err(ScryError::from)?;
-    let string_io = io_module.getattr("StringIO").map_err(ScryError::from)?;
-    let captured_out = string_io.call0().map_err(ScryError::from)?;
+    let captured_out = Bound::new(
+        py,
+        StdoutCapture {
+            limit: config.max_stdout_bytes,
+            ..Default::default()
+        },
+    )
+    .map_err(ScryError::from)?;
     let sys = PyModule::import(py, "sys").map_err(ScryError::from)?;
     let old_stdout = sys.getattr("stdout").map_err(ScryError::from)?;
     sys.setattr("stdout", &captured_out).map_err(ScryError::from)?;
 
-    // Convert code to CString for py.run
-    let c_code = CString::new(code)
-        .map_err(|e| ScryError::Python(format!("Code contains null byte: {e}")))?;
-
-    // Execute user code
-    let exec_result = py.run(&c_code, Some(globals), None);
+    // Compile under a stable filename so tracebacks line up with the submitted code
+    let exec_result = compile_user_code(py, code).and_then(|code_obj| {
+        // Seed the shared random module as late as possible so the run is reproducible
+        if let Some(seed) = seed {
+            PyModule::import(py, "random")?.call_method1("seed", (seed,))?;
+        }
+        PyModule::import(py, "builtins")?
+            .getattr("exec")?
+            .call1((code_obj, globals))
+            .map(|_| ())
+    });
 
     // Restore stdout
     let _ = sys.setattr("stdout", old_stdout);
 
     // Capture stdout content
-    let stdout: String = captured_out
-        .call_method0("getvalue")
-        .and_then(|v| v.extract())
-        .unwrap_or_default();
+    let stdout = std::mem::take(&mut *captured_out.borrow_mut()).into_output();
 
     // Check execution result
     match exec_result {
         Ok(()) => {
-            let svg_content = svg_storage.lock().unwrap().take();
+            let mut earlier_svgs = std::mem::take(&mut *svg_storage.lock().unwrap());
+            // Fall back to compositing layers only when svg() wasn't called
+            let svg_content = earlier_svgs.pop().or_else(|| {
+                let layers = globals.get_item("layer").ok()??;
+                let layers = layers.cast::<LayerStack>().ok()?;
+                layers.borrow().composite(width, height)
+            });
             Ok(ExecResult {
                 svg_content,
+                earlier_svgs,
                 stdout,
             })
         }
         Err(py_err) => {
             // Format the traceback for the model to see
-            let traceback = py_err.to_string();
+            let traceback = format_traceback(py, &py_err);
             let mut msg = String::new();
             if !stdout.is_empty() {
                 msg.push_str("--- stdout ---\n");
@@ -178,46 +840,322 @@ pub fn execute_python(
                 msg.push_str("\n--- error ---\n");
             }
             msg.push_str(&traceback);
-            Err(ScryError::Python(msg))
+            // IndentationError/TabError subclass SyntaxError
+            if py_err.is_instance_of::<pyo3::exceptions::PySyntaxError>(py) {
+                Err(ScryError::Syntax(msg))
+            } else {
+                Err(ScryError::Python(msg))
+            }
         }
     }
 }
 
-/// Run Python code in a blocking context, suitable for calling from async code.
+/// Names the host injects on every run; not user state.
+const INJECTED_NAMES: &[&str] = &["WIDTH", "HEIGHT"];
+
+/// Snapshot the JSON-serializable user variables of a namespace.
+///
+/// Skips dunders, modules, callables (functions, classes, `svg`, `layer`), and
+/// host-injected names. Returns the variables plus the names of any values
+/// that couldn't be represented as JSON.
+pub fn namespace_to_json(
+    py: Python<'_>,
+    namespace: &Py<PyDict>,
+) -> PyResult<(serde_json::Map<String, serde_json::Value>, Vec<String>)> {
+    let dumps = PyModule::import(py, "json")?.getattr("dumps")?;
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("allow_nan", false)?; // NaN/Infinity aren't valid JSON
+
+    let mut vars = serde_json::Map::new();
+    let mut skipped = Vec::new();
+    for (key, value) in namespace.bind(py).iter() {
+        let Ok(name) = key.extract::<String>() else {
+            continue;
+        };
+        if name.starts_with("__")
+            || INJECTED_NAMES.contains(&name.as_str())
+            || presets::VARIABLES.contains(&name.as_str())
+            || value.is_instance_of::<PyModule>()
+            || value.is_callable()
+        {
+            continue;
+        }
+        let parsed = dumps
+            .call((&value,), Some(&kwargs))
+            .and_then(|s| s.extract::<String>())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok());
+        match parsed {
+            Some(json) => {
+                vars.insert(name, json);
+            }
+            None => skipped.push(name),
+        }
+    }
+    Ok((vars, skipped))
+}
+
+/// Snapshot a board's variables for saving, logging any that can't be kept.
+pub fn namespace_for_persistence(
+    py: Python<'_>,
+    board: &str,
+    namespace: &Py<PyDict>,
+) -> PyResult<serde_json::Map<String, serde_json::Value>> {
+    let (vars, skipped) = namespace_to_json(py, namespace)?;
+    if !skipped.is_empty() {
+        tracing::warn!(
+            "Board {board}: not persisting non-JSON variables: {}",
+            skipped.join(", ")
+        );
+    }
+    Ok(vars)
+}
+
+/// Repopulate a fresh namespace from a [`namespace_to_json`] snapshot.
+///
+/// Values come back as their JSON equivalents (tuples become lists, dict keys
+/// become strings). Dunders and host-injected names are never overwritten.
+pub fn restore_namespace(
+    py: Python<'_>,
+    namespace: &Py<PyDict>,
+    vars: &serde_json::Map<String, serde_json::Value>,
+    config: &SandboxConfig,
+) -> PyResult<()> {
+    let loads = PyModule::import(py, "json")?.getattr("loads")?;
+    let globals = namespace.bind(py);
+    for (name, value) in vars {
+        if name.starts_with("__")
+            || INJECTED_NAMES.contains(&name.as_str())
+            || presets::VARIABLES.contains(&name.as_str())
+        {
+            continue;
+        }
+        check_json_size(value, config).map_err(|msg| pyo3::exceptions::PyValueError::new_err(format!("{name}: {msg}")))?;
+        globals.set_item(name, loads.call1((value.to_string(),))?)?;
+    }
+    Ok(())
+}
+
+/// [`namespace_for_persistence`] for several boards. Local namespaces are
+/// handled together in one blocking task; remote ones by their workers.
+pub async fn namespaces_for_export(
+    namespaces: Vec<(String, Namespace)>,
+) -> Result<Vec<serde_json::Map<String, serde_json::Value>>, ScryError> {
+    let mut out: Vec<Option<serde_json::Map<String, serde_json::Value>>> = Vec::with_capacity(namespaces.len());
+    let mut local = Vec::new();
+    for (i, (board, namespace)) in namespaces.into_iter().enumerate() {
+        match namespace {
+            Namespace::Local(ns) => {
+                local.push((i, board, ns));
+                out.push(None);
+            }
+            Namespace::Remote(ns) => out.push(Some(ns.export(board).await?)),
+        }
+    }
+    let exported = tokio::task::spawn_blocking(move || {
+        Python::attach(|py| {
+            local
+                .iter()
+                .map(|(i, board, ns)| Ok((*i, namespace_for_persistence(py, board, ns)?)))
+                .collect::<Result<Vec<_>, ScryError>>()
+        })
+    })
+    .await
+    .map_err(|e| ScryError::Internal(format!("Task join error: {e}")))??;
+    for (i, vars) in exported {
+        out[i] = Some(vars);
+    }
+    Ok(out.into_iter().map(Option::unwrap_or_default).collect())
+}
+
+/// Result of inspecting (and optionally pruning) a board's namespace.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct VarsReport {
+    pub vars: serde_json::Map<String, serde_json::Value>,
+    /// User variables that exist but aren't JSON-serializable.
+    pub skipped: Vec<String>,
+    pub cleared: Vec<String>,
+    /// Names requested for clearing that weren't present (or are protected).
+    pub not_found: Vec<String>,
+}
+
+/// Delete `clear` from a namespace, then snapshot what's left.
+///
+/// Dunder names are never deleted: without `__builtins__`, Python would
+/// silently reinstate the unrestricted builtins on the next run.
+pub fn inspect_vars(
+    py: Python<'_>,
+    namespace: &Py<PyDict>,
+    clear: &[String],
+) -> PyResult<VarsReport> {
+    let globals = namespace.bind(py);
+    let mut cleared = Vec::new();
+    let mut not_found = Vec::new();
+    for name in clear {
+        if !name.starts_with("__") && globals.contains(name)? {
+            globals.del_item(name)?;
+            cleared.push(name.clone());
+        } else {
+            not_found.push(name.clone());
+        }
+    }
+    let (vars, skipped) = namespace_to_json(py, namespace)?;
+    Ok(VarsReport {
+        vars,
+        skipped,
+        cleared,
+        not_found,
+    })
+}
+
+/// [`inspect_vars`] in a blocking context, or on the namespace's worker.
+pub async fn inspect_vars_async(
+    namespace: Namespace,
+    clear: Vec<String>,
+) -> Result<VarsReport, ScryError> {
+    let namespace = match namespace {
+        Namespace::Local(ns) => ns,
+        Namespace::Remote(ns) => return ns.inspect_vars(clear).await,
+    };
+    tokio::task::spawn_blocking(move || {
+        Python::attach(|py| inspect_vars(py, &namespace, &clear).map_err(ScryError::from))
+    })
+    .await
+    .map_err(|e| ScryError::Internal(format!("Task join error: {e}")))?
+}
+
+/// Set a style preset's `STYLE_*` variables in a namespace.
+pub fn apply_style(py: Python<'_>, namespace: &Py<PyDict>, preset: &Preset) -> PyResult<()> {
+    let globals = namespace.bind(py);
+    globals.set_item("STYLE_BG", preset.bg)?;
+    globals.set_item("STYLE_FG", preset.fg)?;
+    globals.set_item("STYLE_ACCENT", preset.accent)?;
+    globals.set_item("STYLE_GRID", preset.grid)?;
+    globals.set_item("STYLE_FONT", preset.font)?;
+    globals.set_item("STYLE_STROKE_WIDTH", preset.stroke_width)?;
+    globals.set_item("STYLE_PALETTE", preset.palette.to_vec())?;
+    Ok(())
+}
+
+/// Run Python code in a blocking context (or on the namespace's worker),
+/// suitable for calling from async code. A `style` preset is applied to the
+/// namespace first.
 pub async fn run_python(
-    namespace: Py<PyDict>,
+    namespace: Namespace,
     code: String,
     width: u32,
     height: u32,
-) -> Result<(ExecResult, Py<PyDict>), ScryError> {
+    seed: Option<u64>,
+    style: Option<&'static Preset>,
+    config: &SandboxConfig,
+) -> Result<(ExecResult, Namespace), ScryError> {
+    let namespace = match namespace {
+        Namespace::Local(ns) => ns,
+        Namespace::Remote(ns) => {
+            let result = ns.run(code, width, height, seed, style).await?;
+            return Ok((result, Namespace::Remote(ns)));
+        }
+    };
+    let config = config.clone();
     tokio::task::spawn_blocking(move || {
         Python::attach(|py| {
-            let result = execute_python(py, &namespace, &code, width, height)?;
-            Ok((result, namespace))
+            if let Some(preset) = style {
+                apply_style(py, &namespace, preset)?;
+            }
+            let result = execute_python(py, &namespace, &code, width, height, seed, &config)?;
+            Ok((result, Namespace::Local(namespace)))
         })
     })
     .await
-    .map_err(|e| ScryError::Python(format!("Task join error: {e}")))?
+    .map_err(|e| ScryError::Internal(format!("Task join error: {e}")))?
+}
+
+/// [`check_syntax`] in a blocking context.
+pub async fn check_syntax_async(code: String, config: &SandboxConfig) -> Result<(), ScryError> {
+    let config = config.clone();
+    tokio::task::spawn_blocking(move || Python::attach(|py| check_syntax(py, &code, &config)))
+        .await
+        .map_err(|e| ScryError::Internal(format!("Task join error: {e}")))?
 }
 
 /// Create a new namespace in a blocking context.
-pub async fn create_namespace_async(width: u32, height: u32) -> Result<Py<PyDict>, ScryError> {
+pub async fn create_namespace_async(
+    width: u32,
+    height: u32,
+    config: SandboxConfig,
+) -> Result<Py<PyDict>, ScryError> {
     tokio::task::spawn_blocking(move || {
-        Python::attach(|py| create_namespace(py, width, height).map_err(ScryError::from))
+        Python::attach(|py| create_namespace(py, width, height, &config).map_err(ScryError::from))
     })
     .await
-    .map_err(|e| ScryError::Python(format!("Task join error: {e}")))?
+    .map_err(|e| ScryError::Internal(format!("Task join error: {e}")))?
+}
+
+/// [`create_namespace_async`], then [`restore_namespace`] from saved variables.
+pub async fn restored_namespace_async(
+    width: u32,
+    height: u32,
+    config: SandboxConfig,
+    vars: serde_json::Map<String, serde_json::Value>,
+) -> Result<Py<PyDict>, ScryError> {
+    tokio::task::spawn_blocking(move || {
+        Python::attach(|py| {
+            let namespace = create_namespace(py, width, height, &config)?;
+            restore_namespace(py, &namespace, &vars, &config)?;
+            Ok(namespace)
+        })
+    })
+    .await
+    .map_err(|e| ScryError::Internal(format!("Task join error: {e}")))?
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Condvar;
+    use std::thread::ThreadId;
+
+    /// Thread whose test needs the interpreter-wide recursion limit left
+    /// alone, see [`pin_recursion_limit`].
+    static RECURSION_PIN: (Mutex<Option<ThreadId>>, Condvar) = (Mutex::new(None), Condvar::new());
+
+    /// Block (without the GIL) while another thread has the limit pinned.
+    pub(super) fn wait_for_recursion_pin(py: Python<'_>) {
+        py.detach(|| {
+            let me = std::thread::current().id();
+            let (owner, freed) = &RECURSION_PIN;
+            let mut owner = owner.lock().unwrap();
+            while owner.is_some_and(|t| t != me) {
+                owner = freed.wait(owner).unwrap();
+            }
+        });
+    }
+
+    struct RecursionPin;
+
+    impl Drop for RecursionPin {
+        fn drop(&mut self) {
+            *RECURSION_PIN.0.lock().unwrap() = None;
+            RECURSION_PIN.1.notify_all();
+        }
+    }
+
+    /// Keep other tests from setting the recursion limit until the guard drops.
+    fn pin_recursion_limit() -> RecursionPin {
+        let (owner, freed) = &RECURSION_PIN;
+        let mut owner = owner.lock().unwrap();
+        while owner.is_some() {
+            owner = freed.wait(owner).unwrap();
+        }
+        *owner = Some(std::thread::current().id());
+        RecursionPin
+    }
 
     #[test]
     fn test_sandbox_blocks_import() {
         Python::attach(|py| {
-            let ns = create_namespace(py, 800, 600).unwrap();
-            let result = execute_python(py, &ns, "import os", 800, 600);
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let result = execute_python(py, &ns, "import os", 800, 600, None, &SandboxConfig::default());
             assert!(result.is_err(), "import os should fail in sandbox");
         });
     }
@@ -225,8 +1163,8 @@ mod tests {
     #[test]
     fn test_sandbox_blocks_dunder_import() {
         Python::attach(|py| {
-            let ns = create_namespace(py, 800, 600).unwrap();
-            let result = execute_python(py, &ns, "__import__('os')", 800, 600);
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let result = execute_python(py, &ns, "__import__('os')", 800, 600, None, &SandboxConfig::default());
             assert!(result.is_err(), "__import__ should not be available");
         });
     }
@@ -234,8 +1172,8 @@ mod tests {
     #[test]
     fn test_sandbox_blocks_open() {
         Python::attach(|py| {
-            let ns = create_namespace(py, 800, 600).unwrap();
-            let result = execute_python(py, &ns, "open('/etc/passwd')", 800, 600);
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let result = execute_python(py, &ns, "open('/etc/passwd')", 800, 600, None, &SandboxConfig::default());
             assert!(result.is_err(), "open() should not be available");
         });
     }
@@ -243,8 +1181,8 @@ mod tests {
     #[test]
     fn test_sandbox_blocks_exec() {
         Python::attach(|py| {
-            let ns = create_namespace(py, 800, 600).unwrap();
-            let result = execute_python(py, &ns, "exec('x = 1')", 800, 600);
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let result = execute_python(py, &ns, "exec('x = 1')", 800, 600, None, &SandboxConfig::default());
             assert!(result.is_err(), "exec() should not be available");
         });
     }
@@ -252,8 +1190,8 @@ mod tests {
     #[test]
     fn test_sandbox_blocks_eval() {
         Python::attach(|py| {
-            let ns = create_namespace(py, 800, 600).unwrap();
-            let result = execute_python(py, &ns, "eval('1+1')", 800, 600);
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let result = execute_python(py, &ns, "eval('1+1')", 800, 600, None, &SandboxConfig::default());
             assert!(result.is_err(), "eval() should not be available");
         });
     }
@@ -261,8 +1199,8 @@ mod tests {
     #[test]
     fn test_sandbox_blocks_subprocess() {
         Python::attach(|py| {
-            let ns = create_namespace(py, 800, 600).unwrap();
-            let result = execute_python(py, &ns, "import subprocess", 800, 600);
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let result = execute_python(py, &ns, "import subprocess", 800, 600, None, &SandboxConfig::default());
             assert!(result.is_err(), "import subprocess should fail");
         });
     }
@@ -270,8 +1208,8 @@ mod tests {
     #[test]
     fn test_safe_modules_available() {
         Python::attach(|py| {
-            let ns = create_namespace(py, 800, 600).unwrap();
-            let result = execute_python(py, &ns, "x = math.sqrt(16)\nprint(x)", 800, 600);
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let result = execute_python(py, &ns, "x = math.sqrt(16)\nprint(x)", 800, 600, None, &SandboxConfig::default());
             assert!(result.is_ok(), "math should be available: {:?}", result.err());
             let r = result.unwrap();
             assert!(r.stdout.contains("4.0"), "should print 4.0, got: {}", r.stdout);
@@ -281,27 +1219,382 @@ mod tests {
     #[test]
     fn test_svg_callback() {
         Python::attach(|py| {
-            let ns = create_namespace(py, 800, 600).unwrap();
-            let result = execute_python(py, &ns, "svg('<svg></svg>')", 800, 600).unwrap();
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let result = execute_python(py, &ns, "svg('<svg></svg>')", 800, 600, None, &SandboxConfig::default()).unwrap();
             assert_eq!(result.svg_content, Some("<svg></svg>".to_string()));
+            assert!(result.earlier_svgs.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_svg_called_repeatedly() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let code = "for i in range(3):\n    svg(f'<svg id=\"{i}\"></svg>')";
+            let result = execute_python(py, &ns, code, 800, 600, None, &SandboxConfig::default()).unwrap();
+            assert_eq!(result.svg_content.as_deref(), Some("<svg id=\"2\"></svg>"));
+            assert_eq!(result.earlier_svgs, vec!["<svg id=\"0\"></svg>", "<svg id=\"1\"></svg>"]);
         });
     }
 
     #[test]
     fn test_namespace_persistence() {
         Python::attach(|py| {
-            let ns = create_namespace(py, 800, 600).unwrap();
-            execute_python(py, &ns, "counter = 1", 800, 600).unwrap();
-            let result = execute_python(py, &ns, "counter += 1\nprint(counter)", 800, 600).unwrap();
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            execute_python(py, &ns, "counter = 1", 800, 600, None, &SandboxConfig::default()).unwrap();
+            let result = execute_python(py, &ns, "counter += 1\nprint(counter)", 800, 600, None, &SandboxConfig::default()).unwrap();
             assert!(result.stdout.contains('2'), "counter should be 2, got: {}", result.stdout);
         });
     }
 
+    #[test]
+    fn test_error_kinds() {
+        use crate::error::ErrorKind;
+        Python::attach(|py| {
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let err = execute_python(py, &ns, "def f(:\n  pass", 800, 600, None, &SandboxConfig::default()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::SyntaxError);
+            let err = execute_python(py, &ns, "1/0", 800, 600, None, &SandboxConfig::default()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Runtime);
+        });
+    }
+
+    #[test]
+    fn test_traceback_line_numbers() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let code = "x = 1\ny = 2\nz = 1/0\n";
+            let err = execute_python(py, &ns, code, 800, 600, None, &SandboxConfig::default()).unwrap_err().to_string();
+            assert!(err.contains("<whiteboard>\", line 3"), "should report line 3: {err}");
+            assert!(err.contains("z = 1/0"), "should show the source line: {err}");
+            assert!(err.contains("ZeroDivisionError"), "should name the exception: {err}");
+        });
+    }
+
+    #[test]
+    fn test_recursion_limit() {
+        let _pin = pin_recursion_limit();
+        Python::attach(|py| {
+            let config = SandboxConfig {
+                recursion_limit: 200,
+                ..Default::default()
+            };
+            let ns = create_namespace(py, 800, 600, &config).unwrap();
+            let code = "def f(n):\n    return f(n + 1)\nf(0)";
+            let err = execute_python(py, &ns, code, 800, 600, None, &config).unwrap_err().to_string();
+            assert!(err.contains("RecursionError"), "should raise RecursionError: {err}");
+
+            // The configured limit, not the default of 1000, is what stops it
+            let code = "depth = 0\ndef g(n):\n    global depth\n    depth = n\n    g(n + 1)\ntry:\n    g(0)\nexcept RecursionError:\n    print(depth)";
+            let result = execute_python(py, &ns, code, 800, 600, None, &config).unwrap();
+            let depth: u32 = result.stdout.trim().parse().unwrap();
+            assert!((150..200).contains(&depth), "recursed {depth} deep under a limit of 200");
+        });
+    }
+
+    #[test]
+    fn test_module_overrides() {
+        let config = SandboxConfig::default().with_module_overrides(
+            &["datetime".to_string(), "os".to_string()],
+            &["textwrap".to_string(), "os".to_string()],
+        );
+        assert!(config.safe_modules.contains(&"datetime".to_string()));
+        assert!(!config.safe_modules.contains(&"textwrap".to_string()));
+        // Named in both lists: block wins
+        assert!(config.blocked_modules.contains(&"os".to_string()));
+        assert!(!config.safe_modules.contains(&"os".to_string()));
+
+        Python::attach(|py| {
+            let ns = create_namespace(py, 800, 600, &config).unwrap();
+            let result = execute_python(py, &ns, "print(datetime.date(2024, 1, 2))", 800, 600, None, &SandboxConfig::default());
+            assert!(result.unwrap().stdout.contains("2024-01-02"));
+            assert!(execute_python(py, &ns, "textwrap.dedent('x')", 800, 600, None, &SandboxConfig::default()).is_err());
+        });
+    }
+
+    #[test]
+    fn test_allow_packages() {
+        Python::attach(|py| {
+            let config = SandboxConfig::default().with_packages(
+                py,
+                &["xml.dom.minidom".to_string(), "no_such_package_xyz".to_string(), "os".to_string()],
+            );
+            // Missing and blocked packages are dropped
+            assert_eq!(config.packages, vec!["xml.dom.minidom"]);
+
+            let ns = create_namespace(py, 800, 600, &config).unwrap();
+            let code = "print(xml.dom.minidom.parseString('<a/>').documentElement.tagName)";
+            let result = execute_python(py, &ns, code, 800, 600, None, &SandboxConfig::default()).unwrap();
+            assert_eq!(result.stdout.trim(), "a");
+            // Still no way to import anything else
+            assert!(execute_python(py, &ns, "import xml.sax", 800, 600, None, &SandboxConfig::default()).is_err());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs numpy installed; run with --ignored"]
+    fn test_numpy_when_allowed() {
+        Python::attach(|py| {
+            let config = SandboxConfig {
+                allow_numpy: true,
+                ..Default::default()
+            };
+            let ns = create_namespace(py, 800, 600, &config).unwrap();
+            assert!(ns.bind(py).contains("np").unwrap(), "numpy is not installed");
+            let result = execute_python(py, &ns, "print(np.arange(5).sum())", 800, 600, None, &SandboxConfig::default()).unwrap();
+            assert_eq!(result.stdout.trim(), "10");
+        });
+    }
+
+    #[test]
+    fn test_layers_composite_across_calls() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 100, 50, &SandboxConfig::default()).unwrap();
+            let first = execute_python(py, &ns, "layer('fg', '<circle r=\"5\"/>', z=1)", 100, 50, None, &SandboxConfig::default()).unwrap();
+            assert!(first.svg_content.unwrap().contains("layer-fg"));
+
+            let second = execute_python(py, &ns, "layer('bg', '<rect width=\"100\"/>')", 100, 50, None, &SandboxConfig::default()).unwrap();
+            let svg = second.svg_content.unwrap();
+            let bg = svg.find("layer-bg").unwrap();
+            let fg = svg.find("layer-fg").unwrap();
+            assert!(bg < fg, "lower z should draw first: {svg}");
+            assert!(svg.contains(r#"width="100" height="50""#));
+            crate::render::svg_to_png(&svg).expect("composite should render");
+
+            // An explicit svg() call wins over layers
+            let explicit = execute_python(py, &ns, "svg('<svg></svg>')", 100, 50, None, &SandboxConfig::default()).unwrap();
+            assert_eq!(explicit.svg_content.as_deref(), Some("<svg></svg>"));
+
+            let cleared = execute_python(py, &ns, "layer.clear()", 100, 50, None, &SandboxConfig::default()).unwrap();
+            assert!(cleared.svg_content.is_none());
+        });
+    }
+
+    #[test]
+    fn test_seed_is_reproducible() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let code = "print(random.random())";
+            let a = execute_python(py, &ns, code, 800, 600, Some(42), &SandboxConfig::default()).unwrap();
+            let b = execute_python(py, &ns, code, 800, 600, Some(42), &SandboxConfig::default()).unwrap();
+            assert_eq!(a.stdout, b.stdout);
+        });
+    }
+
+    #[test]
+    fn test_gradient_helpers_render() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 100, 100, &SandboxConfig::default()).unwrap();
+            let code = r##"
+lg = linear_gradient('lg', [(0, '#f00'), (1, 'blue')], angle=90)
+rg = radial_gradient('rg', [(0, 'white'), (0.5, '#00ff0080'), (1, 'black')])
+svg(f'<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">{lg}{rg}'
+    '<rect width="50" height="100" fill="url(#lg)"/>'
+    '<circle cx="75" cy="50" r="25" fill="url(#rg)"/></svg>')
+"##;
+            let result = execute_python(py, &ns, code, 100, 100, None, &SandboxConfig::default()).unwrap();
+            let svg = result.svg_content.unwrap();
+            assert!(svg.contains("<linearGradient id=\"lg\""), "got: {svg}");
+            assert!(svg.contains("<radialGradient id=\"rg\""), "got: {svg}");
+            crate::render::svg_to_png(&svg).expect("gradient defs should render");
+        });
+    }
+
+    #[test]
+    fn test_element_helpers() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 200, 100, &SandboxConfig::default()).unwrap();
+            let code = r##"
+dots = [circle(20 + 30 * i, 50, 10, fill='red') for i in range(3)]
+doc = svg_doc(WIDTH, HEIGHT,
+    rect(0, 0, WIDTH, HEIGHT, fill='white', class_='bg'),
+    group(dots, transform='translate(5 0)'),
+    line(0, 90, 200, 90, stroke='black', stroke_width=2, opacity=None),
+    text(10, 20, 'a < b', font_size=12))
+"##;
+            let result = execute_python(py, &ns, code, 200, 100, None, &SandboxConfig::default()).unwrap();
+            let svg = result.svg_content.unwrap();
+            assert!(svg.contains(r#"<rect x="0" y="0" width="200" height="100" fill="white" class="bg"/>"#), "got: {svg}");
+            assert!(svg.contains(r#"<g transform="translate(5 0)"><circle cx="20" cy="50" r="10" fill="red"/>"#), "got: {svg}");
+            assert!(svg.contains(r#"stroke-width="2"/>"#), "got: {svg}");
+            assert!(!svg.contains("opacity"), "None attrs are omitted: {svg}");
+            assert!(svg.contains(r#"<text x="10" y="20" font-size="12">a &lt; b</text>"#), "got: {svg}");
+            crate::render::svg_to_png(&svg).expect("element helpers should render");
+        });
+    }
+
+    #[test]
+    fn test_color_helpers_match_colors_module() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 100, 100, &SandboxConfig::default()).unwrap();
+            let code = "print(oklch(0.7, 0.15, 200), oklab(0.5, 0.1, -0.1), oklaba(0.5, 0.1, -0.1, 0.5), color_mix('#f00a', 'navy', 0.3), hue_shift('teal', 90), ease(0.25), ease(0.25, 'out'))";
+            let result = execute_python(py, &ns, code, 100, 100, None, &SandboxConfig::default()).unwrap();
+            let expected = format!(
+                "{} {} {} {} {} {} {}\n",
+                colors::oklch(0.7, 0.15, 200.0, 1.0),
+                colors::oklab(0.5, 0.1, -0.1, 1.0),
+                colors::oklab(0.5, 0.1, -0.1, 0.5),
+                colors::color_mix("#f00a", "navy", 0.3).unwrap(),
+                colors::hue_shift("teal", 90.0).unwrap(),
+                colors::ease("in_out", 0.25).unwrap(),
+                colors::ease("out", 0.25).unwrap(),
+            );
+            assert_eq!(result.stdout, expected);
+
+            let err = execute_python(py, &ns, "color_mix('red', 'nope', 0.5)", 100, 100, None, &SandboxConfig::default()).unwrap_err();
+            assert!(err.to_string().contains("Unknown color"), "got: {err}");
+        });
+    }
+
+    #[test]
+    fn test_namespace_to_json_and_clear() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let code = "count = 3\nname = 'x'\npts = [(1, 2.5)]\ndef f(): pass\nobj = object()\nnan = float('nan')";
+            execute_python(py, &ns, code, 800, 600, None, &SandboxConfig::default()).unwrap();
+
+            let (vars, skipped) = namespace_to_json(py, &ns).unwrap();
+            assert_eq!(vars["count"], serde_json::json!(3));
+            assert_eq!(vars["name"], serde_json::json!("x"));
+            assert_eq!(vars["pts"], serde_json::json!([[1, 2.5]]));
+            for hidden in ["f", "math", "svg", "layer", "WIDTH", "__builtins__"] {
+                assert!(!vars.contains_key(hidden), "{hidden} should be skipped");
+            }
+            assert!(skipped.contains(&"obj".to_string()));
+            assert!(skipped.contains(&"nan".to_string()));
+
+            let clear = vec!["count".to_string(), "nope".to_string(), "__builtins__".to_string()];
+            let report = inspect_vars(py, &ns, &clear).unwrap();
+            assert_eq!(report.cleared, vec!["count"]);
+            assert_eq!(report.not_found, vec!["nope", "__builtins__"]);
+            assert!(!report.vars.contains_key("count"));
+            // The sandbox is intact after an attempted __builtins__ delete
+            assert!(execute_python(py, &ns, "open('/etc/passwd')", 800, 600, None, &SandboxConfig::default()).is_err());
+        });
+    }
+
+    #[test]
+    fn test_namespace_persistence_round_trip() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let code = "step = 7\nhistory = [1.5, None, True]\ncfg = {'a': {'b': 'c'}}\nobj = object()";
+            execute_python(py, &ns, code, 800, 600, None, &SandboxConfig::default()).unwrap();
+            let saved = namespace_for_persistence(py, "t", &ns).unwrap();
+            assert!(!saved.contains_key("obj"));
+
+            // Through a string, as it would be on disk
+            let text = serde_json::to_string(&saved).unwrap();
+            let loaded: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&text).unwrap();
+            let fresh = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            restore_namespace(py, &fresh, &loaded, &SandboxConfig::default()).unwrap();
+
+            let result = execute_python(py, &fresh, "step += 1\nprint(step, history, cfg['a']['b'])", 800, 600, None, &SandboxConfig::default()).unwrap();
+            assert_eq!(result.stdout, "8 [1.5, None, True] c\n");
+            let (vars, _) = namespace_to_json(py, &fresh).unwrap();
+            assert_eq!(vars["history"], saved["history"]);
+            // The restored namespace is still sandboxed
+            assert!(execute_python(py, &fresh, "open('/etc/passwd')", 800, 600, None, &SandboxConfig::default()).is_err());
+        });
+    }
+
+    #[test]
+    fn test_json_size_limits() {
+        let config = SandboxConfig::default();
+        let big_array = serde_json::Value::from(vec![0; DEFAULT_MAX_ARRAY_SIZE + 1]);
+        let err = check_json_size(&serde_json::json!({"points": {"xs": big_array}}), &config).unwrap_err();
+        assert!(err.contains(&format!("exceeds the limit of {DEFAULT_MAX_ARRAY_SIZE}")), "{err}");
+        let big_map: serde_json::Map<String, serde_json::Value> =
+            (0..=DEFAULT_MAX_MAP_SIZE).map(|i| (i.to_string(), i.into())).collect();
+        assert!(check_json_size(&serde_json::Value::Object(big_map), &config).is_err());
+        assert!(check_json_size(&serde_json::Value::from(vec![0; DEFAULT_MAX_ARRAY_SIZE]), &config).is_ok());
+        let tight = SandboxConfig {
+            max_array_size: 2,
+            ..Default::default()
+        };
+        assert!(check_json_size(&serde_json::json!([[1, 2, 3]]), &tight).is_err());
+
+        Python::attach(|py| {
+            let ns = create_namespace(py, 100, 100, &SandboxConfig::default()).unwrap();
+            let mut vars = serde_json::Map::new();
+            vars.insert("ok".into(), 1.into());
+            vars.insert("xs".into(), serde_json::Value::from(vec![0; DEFAULT_MAX_ARRAY_SIZE + 1]));
+            let err = restore_namespace(py, &ns, &vars, &config).unwrap_err();
+            assert!(err.to_string().contains("xs: JSON array"), "{err}");
+            assert!(!ns.bind(py).contains("xs").unwrap());
+        });
+    }
+
+    #[test]
+    fn test_apply_style() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            apply_style(py, &ns, presets::get("blueprint").unwrap()).unwrap();
+            let code = "print(STYLE_BG, STYLE_PALETTE[1], STYLE_STROKE_WIDTH)\nmine = 1";
+            let result = execute_python(py, &ns, code, 800, 600, None, &SandboxConfig::default()).unwrap();
+            assert_eq!(result.stdout.trim(), "#0b3d91 #ffd166 1.5");
+            // Preset values aren't user state
+            let (vars, _) = namespace_to_json(py, &ns).unwrap();
+            assert_eq!(vars.keys().collect::<Vec<_>>(), ["mine"]);
+        });
+    }
+
+    #[test]
+    fn test_check_syntax() {
+        Python::attach(|py| {
+            assert!(check_syntax(py, "x = 1\nsvg('<svg/>')", &SandboxConfig::default()).is_ok());
+            let err = check_syntax(py, "def f(:\n    pass", &SandboxConfig::default()).unwrap_err();
+            assert_eq!(err.kind(), crate::error::ErrorKind::SyntaxError);
+            assert!(err.to_string().contains("line 1"), "{err}");
+            assert!(check_syntax(py, "x\0", &SandboxConfig::default()).is_err());
+            // Names are only resolved at run time
+            assert!(check_syntax(py, "undefined_name()", &SandboxConfig::default()).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_check_complexity() {
+        Python::attach(|py| {
+            assert!(check_complexity(py, "x = 1 + 2", 100).is_ok());
+            let long = format!("x = {}", ["1"; 200].join(" + "));
+            let err = check_complexity(py, &long, 100).unwrap_err();
+            assert!(err.to_string().contains("script too complex"), "{err}");
+            // Unparseable code is left to the compiler
+            assert!(check_complexity(py, "def (", 1).is_ok());
+
+            let config = SandboxConfig {
+                max_ast_nodes: Some(100),
+                ..Default::default()
+            };
+            assert!(check_syntax(py, &long, &config).is_err());
+            assert!(check_syntax(py, &long, &SandboxConfig::default()).is_ok(), "no cap by default");
+        });
+    }
+
+    #[test]
+    fn test_stdout_capture_is_capped() {
+        Python::attach(|py| {
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let code = "for i in range(1_000_000):\n    print(i)\nsvg('<svg/>')";
+            let result = execute_python(py, &ns, code, 800, 600, None, &SandboxConfig::default()).unwrap();
+            assert!(result.stdout.len() < DEFAULT_MAX_STDOUT_BYTES + 100);
+            assert!(result.stdout.starts_with("0\n1\n2\n"));
+            assert!(result.stdout.contains("...[truncated "), "missing marker");
+            assert!(result.svg_content.is_some(), "the run continues past the cap");
+
+            let config = SandboxConfig {
+                max_stdout_bytes: 4,
+                ..Default::default()
+            };
+            let result = execute_python(py, &ns, "print('abcdef')", 800, 600, None, &config).unwrap();
+            assert_eq!(result.stdout, "abcd\n...[truncated 3 bytes]\n");
+        });
+    }
+
     #[test]
     fn test_stdout_capture() {
         Python::attach(|py| {
-            let ns = create_namespace(py, 800, 600).unwrap();
-            let result = execute_python(py, &ns, "print('hello world')", 800, 600).unwrap();
+            let ns = create_namespace(py, 800, 600, &SandboxConfig::default()).unwrap();
+            let result = execute_python(py, &ns, "print('hello world')", 800, 600, None, &SandboxConfig::default()).unwrap();
             assert_eq!(result.stdout.trim(), "hello world");
         });
     }
@@ -1,43 +1,945 @@
 use crate::error::ScryError;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use std::sync::{Arc, LazyLock};
 use usvg::fontdb;
 
 /// Shared font database loaded once with system fonts.
 static FONTDB: LazyLock<Arc<fontdb::Database>> = LazyLock::new(|| {
-    let mut db = fontdb::Database::new();
-    db.load_system_fonts();
+    let db = build_fontdb(true);
     tracing::info!("Loaded {} font faces", db.len());
     Arc::new(db)
 });
 
-/// Maximum dimension (width or height) for rendered output in pixels.
-const MAX_DIMENSION: u32 = 8192;
+/// Font compiled in with the `embedded-font` feature, and its family name.
+#[cfg(feature = "embedded-font")]
+static EMBEDDED_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+#[cfg(feature = "embedded-font")]
+const EMBEDDED_FAMILY: &str = "DejaVu Sans";
+
+/// Font database with system fonts (optionally) plus the embedded fallback.
+///
+/// With the `embedded-font` feature, generic families the host can't satisfy
+/// are pointed at the embedded face, so text renders even in containers with
+/// no fonts installed.
+fn build_fontdb(system_fonts: bool) -> fontdb::Database {
+    let mut db = fontdb::Database::new();
+    if system_fonts {
+        db.load_system_fonts();
+    }
+    #[cfg(feature = "embedded-font")]
+    {
+        db.load_font_data(EMBEDDED_FONT.to_vec());
+        let generics = [
+            fontdb::Family::SansSerif,
+            fontdb::Family::Serif,
+            fontdb::Family::Monospace,
+            fontdb::Family::Cursive,
+            fontdb::Family::Fantasy,
+        ];
+        for generic in generics {
+            let query = fontdb::Query {
+                families: &[generic],
+                ..Default::default()
+            };
+            if db.query(&query).is_some() {
+                continue;
+            }
+            match generic {
+                fontdb::Family::SansSerif => db.set_sans_serif_family(EMBEDDED_FAMILY),
+                fontdb::Family::Serif => db.set_serif_family(EMBEDDED_FAMILY),
+                fontdb::Family::Monospace => db.set_monospace_family(EMBEDDED_FAMILY),
+                fontdb::Family::Cursive => db.set_cursive_family(EMBEDDED_FAMILY),
+                _ => db.set_fantasy_family(EMBEDDED_FAMILY),
+            }
+        }
+    }
+    db
+}
+
+/// Every font family available to the renderer, sorted and deduplicated.
+pub fn font_families() -> Vec<String> {
+    let mut families: Vec<String> = FONTDB
+        .faces()
+        .flat_map(|face| face.families.iter().map(|(name, _)| name.clone()))
+        .collect();
+    families.sort_unstable_by_key(|f| f.to_lowercase());
+    families.dedup();
+    families
+}
+
+/// Render settings, fixed at startup.
+#[derive(Clone, Debug)]
+pub struct RenderConfig {
+    /// Stored PNGs larger than this are downscaled until they fit.
+    pub max_png_bytes: Option<usize>,
+    /// Rasterizations allowed to run at once.
+    pub max_concurrent_renders: usize,
+    /// WIDTH/HEIGHT for a new board created without explicit dimensions.
+    pub default_width: u32,
+    pub default_height: u32,
+    /// Label stamped on every rendered PNG (`--watermark`).
+    pub watermark: Option<Watermark>,
+    /// Family for text with no `font-family` (`--default-font-family`).
+    pub default_font_family: Option<String>,
+}
+
+pub const DEFAULT_WIDTH: u32 = 800;
+pub const DEFAULT_HEIGHT: u32 = 600;
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        RenderConfig {
+            max_png_bytes: None,
+            max_concurrent_renders: default_render_concurrency(),
+            default_width: DEFAULT_WIDTH,
+            default_height: DEFAULT_HEIGHT,
+            watermark: None,
+            default_font_family: None,
+        }
+    }
+}
+
+impl RenderConfig {
+    /// `--default-font-family` if given, else the embedded font when compiled
+    /// in, else None to keep usvg's own default (Times New Roman).
+    fn font_family(&self) -> Option<String> {
+        if let Some(ref family) = self.default_font_family {
+            return Some(family.clone());
+        }
+        #[cfg(feature = "embedded-font")]
+        return Some(EMBEDDED_FAMILY.to_string());
+        #[cfg(not(feature = "embedded-font"))]
+        None
+    }
+
+    /// Family used for text that doesn't set `font-family`.
+    pub fn default_font_family_name(&self) -> String {
+        self.font_family().unwrap_or_else(|| "Times New Roman".into())
+    }
+}
+
+/// One render per CPU.
+pub fn default_render_concurrency() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Antialiasing preset for a render. These only set usvg's defaults, so any
+/// `shape-rendering`/`text-rendering`/`image-rendering` the SVG declares wins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Quality {
+    /// Antialiased shapes and text, smoothed image scaling (resvg's defaults).
+    #[default]
+    Smooth,
+    /// No antialiasing and nearest-neighbor image scaling: hard pixel edges for
+    /// grid-aligned diagrams and pixel art, at the cost of jagged curves and
+    /// diagonals.
+    Crisp,
+}
+
+impl Quality {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "smooth" => Ok(Quality::Smooth),
+            "crisp" => Ok(Quality::Crisp),
+            other => Err(format!("Unknown quality {other:?} (expected \"smooth\" or \"crisp\")")),
+        }
+    }
+
+    fn apply(self, options: &mut usvg::Options) {
+        let (shape, text, image) = match self {
+            Quality::Smooth => (
+                usvg::ShapeRendering::GeometricPrecision,
+                usvg::TextRendering::OptimizeLegibility,
+                usvg::ImageRendering::OptimizeQuality,
+            ),
+            Quality::Crisp => (
+                usvg::ShapeRendering::CrispEdges,
+                usvg::TextRendering::OptimizeSpeed,
+                usvg::ImageRendering::OptimizeSpeed,
+            ),
+        };
+        options.shape_rendering = shape;
+        options.text_rendering = text;
+        options.image_rendering = image;
+    }
+}
+
+/// Corner of the image a [`Watermark`] is drawn in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl Corner {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "top-left" => Some(Corner::TopLeft),
+            "top-right" => Some(Corner::TopRight),
+            "bottom-left" => Some(Corner::BottomLeft),
+            "bottom-right" => Some(Corner::BottomRight),
+            _ => None,
+        }
+    }
+}
 
-pub fn svg_to_png(svg_str: &str) -> Result<Vec<u8>, ScryError> {
+/// Attribution label composited over every rendered raster.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Watermark {
+    pub text: String,
+    pub corner: Corner,
+}
+
+impl Watermark {
+    /// Parse a `--watermark` value: `TEXT`, or `CORNER:TEXT` where CORNER is
+    /// `top-left`, `top-right`, `bottom-left` or `bottom-right` (the default).
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (corner, text) = match s.split_once(':') {
+            Some((prefix, rest)) => match Corner::parse(prefix) {
+                Some(corner) => (corner, rest),
+                None => (Corner::default(), s),
+            },
+            None => (Corner::default(), s),
+        };
+        let text = text.trim();
+        if text.is_empty() {
+            return Err("Watermark text is empty".into());
+        }
+        Ok(Watermark {
+            text: text.to_string(),
+            corner,
+        })
+    }
+}
+
+/// Draw a small semi-transparent label into a corner of the pixmap.
+///
+/// The label is laid out as SVG text, so it goes through the same font
+/// database and shaping as the boards themselves. Light fill with a dark
+/// outline keeps it legible on any background.
+fn draw_watermark(pixmap: &mut tiny_skia::Pixmap, watermark: &Watermark, db: &Arc<fontdb::Database>) {
+    let (w, h) = (pixmap.width() as f32, pixmap.height() as f32);
+    let font_size = (w.min(h) / 32.0).clamp(9.0, 20.0);
+    let margin = font_size * 0.6;
+    let (x, anchor) = match watermark.corner {
+        Corner::TopLeft | Corner::BottomLeft => (margin, "start"),
+        Corner::TopRight | Corner::BottomRight => (w - margin, "end"),
+    };
+    let y = match watermark.corner {
+        Corner::TopLeft | Corner::TopRight => margin + font_size * 0.8,
+        Corner::BottomLeft | Corner::BottomRight => h - margin,
+    };
+    // The generic family resolves to a fixed name (Arial) that may not be
+    // installed; fall back to any face so the label still shows up.
+    let sans = fontdb::Query {
+        families: &[fontdb::Family::SansSerif],
+        ..Default::default()
+    };
+    let family = match db.query(&sans) {
+        Some(_) => "sans-serif".to_string(),
+        None => db
+            .faces()
+            .find_map(|face| face.families.first())
+            .map_or_else(|| "sans-serif".to_string(), |(name, _)| crate::board::html_escape(name)),
+    };
+    let svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}"><text x="{x}" y="{y}" text-anchor="{anchor}" font-family="{family}" font-size="{font_size}" fill="#fff" fill-opacity="0.7" stroke="#000" stroke-opacity="0.45" stroke-width="{stroke}" paint-order="stroke">{text}</text></svg>"##,
+        stroke = font_size / 6.0,
+        text = crate::board::html_escape(&watermark.text),
+    );
     let options = usvg::Options {
-        fontdb: FONTDB.clone(),
+        fontdb: db.clone(),
         ..Default::default()
     };
-    let tree = usvg::Tree::from_str(svg_str, &options)?;
-    let size = tree.size().to_int_size();
+    match usvg::Tree::from_str(&svg, &options) {
+        Ok(tree) => resvg::render(&tree, tiny_skia::Transform::identity(), &mut pixmap.as_mut()),
+        Err(e) => tracing::warn!("Failed to lay out watermark: {e}"),
+    }
+}
+
+/// Maximum dimension (width or height) for rendered output in pixels.
+pub const MAX_DIMENSION: u32 = 8192;
 
-    if size.width() == 0 || size.height() == 0 {
-        return Err(ScryError::Render("SVG has zero dimensions".into()));
+/// True if a reference stays inside the document or is inline data.
+fn is_local_ref(target: &str) -> bool {
+    let target = target.trim();
+    target.is_empty()
+        || target.starts_with('#')
+        || target.get(..5).is_some_and(|p| p.eq_ignore_ascii_case("data:"))
+}
+
+/// Find the first `url(...)` target in CSS-ish text that points outside the document.
+fn external_css_url(text: &str) -> Option<String> {
+    let mut rest = text;
+    while let Some(start) = rest.find("url(") {
+        rest = &rest[start + 4..];
+        let end = rest.find(')').unwrap_or(rest.len());
+        let target = rest[..end].trim().trim_matches(|c| c == '"' || c == '\'');
+        if !is_local_ref(target) {
+            return Some(target.to_string());
+        }
+        rest = &rest[end..];
     }
-    if size.width() > MAX_DIMENSION || size.height() > MAX_DIMENSION {
-        return Err(ScryError::Render(format!(
-            "SVG dimensions {}x{} exceed maximum {MAX_DIMENSION}x{MAX_DIMENSION}",
-            size.width(),
-            size.height()
-        )));
+    None
+}
+
+/// Largest decoded `data:` image payload accepted inside an SVG.
+pub const MAX_EMBEDDED_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Validate a `data:` URI used as an image source. The payload must fit in
+/// [`MAX_EMBEDDED_IMAGE_BYTES`]; raster payloads must be base64 and decode
+/// cleanly within [`MAX_DIMENSION`], so malformed or bomb-like images are
+/// refused before usvg sees them. Nested SVG is only size-checked, since it
+/// goes through the same parser as the outer document.
+fn check_data_image(uri: &str) -> Result<(), ScryError> {
+    let invalid = |why: String| Err(ScryError::SvgParse(format!("invalid embedded image: {why}")));
+
+    let Some((meta, payload)) = uri.get(5..).and_then(|rest| rest.split_once(',')) else {
+        return invalid("malformed data URI".into());
+    };
+    let mut params = meta.split(';');
+    let mime = params.next().unwrap_or_default().trim().to_ascii_lowercase();
+    let is_base64 = params.any(|p| p.trim().eq_ignore_ascii_case("base64"));
+
+    let payload: String = payload.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    // Base64 expands 3 bytes to 4; check before decoding anything
+    let approx_len = if is_base64 { payload.len() / 4 * 3 } else { payload.len() };
+    if approx_len > MAX_EMBEDDED_IMAGE_BYTES {
+        return invalid(format!(
+            "{approx_len} bytes exceeds the {MAX_EMBEDDED_IMAGE_BYTES}-byte limit"
+        ));
+    }
+    if mime == "image/svg+xml" {
+        return Ok(());
+    }
+    if !is_base64 {
+        return invalid(format!("{mime} data must be base64-encoded"));
+    }
+
+    let bytes = match BASE64.decode(payload.as_bytes()) {
+        Ok(b) => b,
+        Err(e) => return invalid(format!("bad base64: {e}")),
+    };
+    let mut reader = match image::ImageReader::new(std::io::Cursor::new(&bytes)).with_guessed_format() {
+        Ok(r) => r,
+        Err(e) => return invalid(e.to_string()),
+    };
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    reader.limits(limits);
+    match reader.decode() {
+        Ok(_) => Ok(()),
+        Err(e) => invalid(e.to_string()),
+    }
+}
+
+/// Reject SVGs that reference anything outside the document: `href`s that are
+/// not `#fragment` or `data:` URIs, external CSS `url()`s in attributes and
+/// `<style>` sheets, `@import`, and external DTD entities. Text content is
+/// never treated as CSS. Rendering must never touch the filesystem or network.
+/// `data:` images are allowed but validated with [`check_data_image`].
+///
+/// Returns the parsed document so callers can reuse it instead of parsing the
+/// SVG again.
+fn parse_checked(svg_str: &str) -> Result<roxmltree::Document<'_>, ScryError> {
+    let reject = |what: &str| {
+        Err(ScryError::SvgParse(format!("external references not allowed: {what}")))
+    };
+
+    // The standard `<!DOCTYPE svg PUBLIC ...>` header is harmless (usvg never
+    // fetches DTDs), but external entity declarations are not.
+    let upper_prolog = svg_str
+        .split_once("<svg")
+        .map_or(svg_str, |(prolog, _)| prolog)
+        .to_ascii_uppercase();
+    for decl in upper_prolog.split("<!ENTITY").skip(1) {
+        let decl = decl.split('>').next().unwrap_or_default();
+        if decl.contains("SYSTEM") || decl.contains("PUBLIC") {
+            return reject("external entity");
+        }
+    }
+
+    let opts = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = roxmltree::Document::parse_with_options(svg_str, opts)
+        .map_err(|e| ScryError::SvgParse(e.to_string()))?;
+
+    for node in doc.descendants() {
+        if node.is_text() {
+            // Only stylesheets load anything; rendered text is just text
+            if node.parent().is_some_and(|p| p.tag_name().name() == "style") {
+                let css = node.text().unwrap_or_default();
+                if css.contains("@import") {
+                    return reject("@import");
+                }
+                if let Some(url) = external_css_url(css) {
+                    return reject(&url);
+                }
+            }
+            continue;
+        }
+        for attr in node.attributes() {
+            if attr.name() == "style" && attr.value().contains("@import") {
+                return reject("@import");
+            }
+            if attr.name() == "href" && !is_local_ref(attr.value()) {
+                return reject(attr.value());
+            }
+            if attr.name() == "href"
+                && (node.has_tag_name("image") || node.has_tag_name("feImage"))
+                && attr.value().trim().get(..5).is_some_and(|p| p.eq_ignore_ascii_case("data:"))
+            {
+                check_data_image(attr.value().trim())?;
+            }
+            if let Some(url) = external_css_url(attr.value()) {
+                return reject(&url);
+            }
+        }
+    }
+    Ok(doc)
+}
+
+/// True if some loaded face has this family name.
+fn has_family(db: &fontdb::Database, name: &str) -> bool {
+    db.faces()
+        .any(|face| face.families.iter().any(|(family, _)| family.eq_ignore_ascii_case(name)))
+}
+
+/// Pull every `font-family` value out of attributes, inline styles, and
+/// `<style>` sheets.
+fn font_family_lists(doc: &roxmltree::Document) -> Vec<String> {
+    fn from_css(css: &str, out: &mut Vec<String>) {
+        for part in css.split("font-family").skip(1) {
+            if let Some(value) = part.trim_start().strip_prefix(':') {
+                let end = value.find([';', '}']).unwrap_or(value.len());
+                out.push(value[..end].trim().trim_end_matches("!important").to_string());
+            }
+        }
+    }
+
+    let mut lists = Vec::new();
+    for node in doc.descendants() {
+        if node.is_text() {
+            if node.parent().is_some_and(|p| p.has_tag_name("style")) {
+                from_css(node.text().unwrap_or_default(), &mut lists);
+            }
+            continue;
+        }
+        if let Some(value) = node.attribute("font-family") {
+            lists.push(value.to_string());
+        }
+        if let Some(style) = node.attribute("style") {
+            from_css(style, &mut lists);
+        }
+    }
+    lists
+}
+
+/// Find font families that text asks for but that aren't installed.
+///
+/// Only families actually tried count: in `"Foo", serif` a missing `Foo` is
+/// reported, but nothing after the first available family is. Generic names
+/// are checked through the database's generic mapping, since usvg drops text
+/// outright when nothing in the list resolves.
+fn missing_fonts(doc: &roxmltree::Document, db: &fontdb::Database) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for list in font_family_lists(doc) {
+        for family in list.split(',') {
+            let family = family.trim().trim_matches(|c| c == '"' || c == '\'');
+            let generic = match family {
+                "serif" => Some(fontdb::Family::Serif),
+                "sans-serif" => Some(fontdb::Family::SansSerif),
+                "monospace" => Some(fontdb::Family::Monospace),
+                "cursive" => Some(fontdb::Family::Cursive),
+                "fantasy" => Some(fontdb::Family::Fantasy),
+                _ => None,
+            };
+            if family.is_empty() || family == "inherit" {
+                continue;
+            }
+            let (resolved, warning) = match generic {
+                Some(g) => {
+                    let mapped = db.family_name(&g);
+                    (
+                        has_family(db, mapped),
+                        format!("Generic font family {family} maps to {mapped:?}, which is not installed"),
+                    )
+                }
+                None => (
+                    has_family(db, family),
+                    format!("Font family {family:?} is not installed"),
+                ),
+            };
+            if resolved {
+                break;
+            }
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
     }
+    warnings
+}
+
+/// Parse and rasterize an SVG into a pixmap, enforcing dimension limits.
+/// Also returns non-fatal warnings about the render (e.g. missing fonts).
+///
+/// `fit` optionally scales the drawing, preserving aspect ratio, to fit a
+/// width and/or height in pixels; `(None, None)` renders at the SVG's own size.
+fn render_pixmap(
+    svg_str: &str,
+    quality: Quality,
+    fit: (Option<u32>, Option<u32>),
+    config: &RenderConfig,
+) -> Result<(tiny_skia::Pixmap, Vec<String>), ScryError> {
+    let doc = parse_checked(svg_str)?;
+
+    let mut options = usvg::Options {
+        fontdb: FONTDB.clone(),
+        ..Default::default()
+    };
+    if let Some(family) = config.font_family() {
+        options.font_family = family;
+    }
+    quality.apply(&mut options);
+    // Defense in depth: never resolve non-data image hrefs, even if the scan misses one
+    options.image_href_resolver.resolve_string = Box::new(|_, _| None);
+    let tree = usvg::Tree::from_xmltree(&doc, &options)?;
+    let natural = tree.size();
+    let scale = match fit {
+        (None, None) => 1.0,
+        (w, h) => {
+            let sx = w.map_or(f32::INFINITY, |w| w as f32 / natural.width());
+            let sy = h.map_or(f32::INFINITY, |h| h as f32 / natural.height());
+            sx.min(sy)
+        }
+    };
+    let size = natural
+        .to_int_size()
+        .scale_by(scale)
+        .ok_or_else(|| ScryError::Dimensions("SVG has zero dimensions".into()))?;
+    check_dimensions(size.width(), size.height())?;
 
     let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
         .ok_or_else(|| ScryError::Render("Failed to create pixmap".into()))?;
-    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    if let Some(ref watermark) = config.watermark {
+        draw_watermark(&mut pixmap, watermark, &FONTDB);
+    }
+
+    Ok((pixmap, missing_fonts(&doc, &FONTDB)))
+}
+
+fn check_dimensions(width: u32, height: u32) -> Result<(), ScryError> {
+    if width == 0 || height == 0 {
+        return Err(ScryError::Dimensions("SVG has zero dimensions".into()));
+    }
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(ScryError::Dimensions(format!(
+            "SVG dimensions {width}x{height} exceed maximum {MAX_DIMENSION}x{MAX_DIMENSION}"
+        )));
+    }
+    Ok(())
+}
+
+/// Convert an SVG to a single-page vector PDF sized to the SVG (72 dpi, so
+/// one SVG pixel is one PDF point). Same reference and size limits as PNG.
+pub fn svg_to_pdf(svg_str: &str, config: &RenderConfig) -> Result<Vec<u8>, ScryError> {
+    use svg2pdf::usvg as pdf_usvg;
+
+    // svg2pdf's usvg is on an older roxmltree, so it can't take this document
+    // and parses the text again
+    parse_checked(svg_str)?;
+    // svg2pdf's older usvg is on the same fontdb release as ours, so the
+    // fonts loaded for PNGs (and their generic-family fallbacks) serve PDFs too
+    let mut options = pdf_usvg::Options {
+        fontdb: FONTDB.clone(),
+        ..Default::default()
+    };
+    if let Some(family) = config.font_family() {
+        options.font_family = family;
+    }
+    options.image_href_resolver.resolve_string = Box::new(|_, _| None);
+    let tree = pdf_usvg::Tree::from_str(svg_str, &options)
+        .map_err(|e| ScryError::SvgParse(e.to_string()))?;
+    let size = tree.size().to_int_size();
+    check_dimensions(size.width(), size.height())?;
+
+    svg2pdf::to_pdf(&tree, svg2pdf::ConversionOptions::default(), svg2pdf::PageOptions::default())
+        .map_err(|e| ScryError::Render(format!("PDF conversion failed: {e}")))
+}
+
+/// A rendered PNG and its actual pixel size, which comes from the SVG's own
+/// `width`/`height`/`viewBox` and may differ from the board's requested size.
+#[derive(Debug)]
+pub struct RenderOutput {
+    pub png: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Non-fatal problems worth telling the caller about.
+    pub warnings: Vec<String>,
+}
+
+/// Render with the default [`RenderConfig`] (no watermark).
+pub fn svg_to_png(svg_str: &str) -> Result<RenderOutput, ScryError> {
+    svg_to_png_with_quality(svg_str, Quality::default(), &RenderConfig::default())
+}
+
+pub fn svg_to_png_with_quality(
+    svg_str: &str,
+    quality: Quality,
+    config: &RenderConfig,
+) -> Result<RenderOutput, ScryError> {
+    svg_to_png_fit(svg_str, quality, None, None, config)
+}
+
+/// Render scaled to fit `width` and/or `height`, keeping the aspect ratio.
+pub fn svg_to_png_fit(
+    svg_str: &str,
+    quality: Quality,
+    width: Option<u32>,
+    height: Option<u32>,
+    config: &RenderConfig,
+) -> Result<RenderOutput, ScryError> {
+    let (pixmap, warnings) = render_pixmap(svg_str, quality, (width, height), config)?;
+    let png = pixmap
+        .encode_png()
+        .map_err(|e| ScryError::Render(e.to_string()))?;
+    Ok(RenderOutput {
+        png,
+        width: pixmap.width(),
+        height: pixmap.height(),
+        warnings,
+    })
+}
+
+/// Transcode stored PNG bytes to lossless WebP.
+///
+/// The `image` crate only ships a lossless WebP encoder, which still comes out
+/// well under the size of PNG for the flat-color art boards tend to contain.
+pub fn png_to_webp(png: &[u8]) -> Result<Vec<u8>, ScryError> {
+    let img = image::load_from_memory_with_format(png, image::ImageFormat::Png)
+        .map_err(|e| ScryError::Render(format!("PNG decode failed: {e}")))?;
+    encode_webp(&img)
+}
+
+/// Long-edge size in pixels for gallery/list thumbnails.
+pub const THUMB_MAX_EDGE: u32 = 400;
+
+/// Pixel size from a PNG header, without decoding the image.
+pub fn png_dimensions(png: &[u8]) -> Option<(u32, u32)> {
+    image::ImageReader::with_format(std::io::Cursor::new(png), image::ImageFormat::Png)
+        .into_dimensions()
+        .ok()
+}
+
+/// Downscale PNG bytes so the long edge is at most `max_edge`, re-encoded as PNG.
+/// Images already within bounds are returned unchanged.
+pub fn make_thumbnail(png: &[u8], max_edge: u32) -> Result<Vec<u8>, ScryError> {
+    let img = image::load_from_memory_with_format(png, image::ImageFormat::Png)
+        .map_err(|e| ScryError::Render(format!("PNG decode failed: {e}")))?;
+    if img.width() <= max_edge && img.height() <= max_edge {
+        return Ok(png.to_vec());
+    }
+    let thumb = img.thumbnail(max_edge, max_edge);
+    let mut out = std::io::Cursor::new(Vec::new());
+    thumb
+        .write_to(&mut out, image::ImageFormat::Png)
+        .map_err(|e| ScryError::Render(format!("PNG encode failed: {e}")))?;
+    Ok(out.into_inner())
+}
+
+/// Result of [`diff_png`].
+#[derive(Debug)]
+pub struct DiffOutput {
+    /// Highlight image: unchanged pixels dimmed to gray, changed ones tinted red.
+    pub png: Vec<u8>,
+    pub changed: u64,
+    pub total: u64,
+    /// The two images differed in size. They are aligned top-left and any
+    /// pixel covered by only one of them counts as changed.
+    pub size_mismatch: bool,
+}
+
+/// Pixel-diff two PNGs into a highlight image.
+pub fn diff_png(before: &[u8], after: &[u8]) -> Result<DiffOutput, ScryError> {
+    let decode = |png: &[u8]| {
+        image::load_from_memory_with_format(png, image::ImageFormat::Png)
+            .map(|img| img.to_rgba8())
+            .map_err(|e| ScryError::Render(format!("PNG decode failed: {e}")))
+    };
+    let (before, after) = (decode(before)?, decode(after)?);
+    let width = before.width().max(after.width());
+    let height = before.height().max(after.height());
+    let pixel = |img: &image::RgbaImage, x, y| {
+        (x < img.width() && y < img.height()).then(|| *img.get_pixel(x, y))
+    };
+
+    let mut changed = 0;
+    let out = image::RgbaImage::from_fn(width, height, |x, y| {
+        let (b, a) = (pixel(&before, x, y), pixel(&after, x, y));
+        let shown = a.or(b).unwrap_or(image::Rgba([0, 0, 0, 0]));
+        // Luma over white, so transparent areas read as background
+        let [r, g, bl, alpha] = shown.0;
+        let over_white = |c: u8| (c as u32 * alpha as u32 + 255 * (255 - alpha as u32)) / 255;
+        let luma = (over_white(r) * 299 + over_white(g) * 587 + over_white(bl) * 114) / 1000;
+        if b == a {
+            // Washed out so the highlights stand out
+            let v = (192 + luma / 4) as u8;
+            image::Rgba([v, v, v, 255])
+        } else {
+            changed += 1;
+            image::Rgba([255, (luma / 3) as u8, (luma / 3) as u8, 255])
+        }
+    });
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    out.write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|e| ScryError::Render(format!("PNG encode failed: {e}")))?;
+    Ok(DiffOutput {
+        png: png.into_inner(),
+        changed,
+        total: width as u64 * height as u64,
+        size_mismatch: before.dimensions() != after.dimensions(),
+    })
+}
+
+/// Pixels per QR module in [`qr_png`]; big enough to scan off a projector.
+const QR_MODULE_PX: u32 = 8;
+
+/// Encode `data` as a black-on-white QR code PNG with the standard
+/// four-module quiet zone.
+pub fn qr_png(data: &str) -> Result<Vec<u8>, ScryError> {
+    const QUIET: u32 = 4;
+    let code = qrcode::QrCode::new(data.as_bytes())
+        .map_err(|e| ScryError::Render(format!("QR encode failed: {e}")))?;
+    let modules = code.width() as u32;
+    let colors = code.to_colors();
+    let size = (modules + 2 * QUIET) * QR_MODULE_PX;
+    let img = image::GrayImage::from_fn(size, size, |x, y| {
+        let (mx, my) = (x / QR_MODULE_PX, y / QR_MODULE_PX);
+        let inside = (QUIET..QUIET + modules).contains(&mx) && (QUIET..QUIET + modules).contains(&my);
+        let dark = inside && colors[((my - QUIET) * modules + mx - QUIET) as usize] == qrcode::Color::Dark;
+        image::Luma([if dark { 0 } else { 255 }])
+    });
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|e| ScryError::Render(format!("PNG encode failed: {e}")))?;
+    Ok(png.into_inner())
+}
+
+/// Edge of the square area each board is fitted into on a [`contact_sheet`].
+pub const SHEET_CELL: u32 = 320;
+/// Height of the caption strip under each cell.
+const SHEET_CAPTION: u32 = 28;
+/// Gap around and between cells.
+const SHEET_GAP: u32 = 16;
+/// Captions longer than this many characters are cut with an ellipsis.
+const SHEET_CAPTION_CHARS: usize = 36;
+
+/// Most boards one [`contact_sheet`] can hold at `columns` per row before it
+/// would exceed [`MAX_DIMENSION`].
+pub fn contact_sheet_capacity(columns: u32) -> usize {
+    let rows = (MAX_DIMENSION - SHEET_GAP) / (SHEET_CELL + SHEET_CAPTION + SHEET_GAP);
+    (rows * columns.max(1)) as usize
+}
+
+/// `font-family` value for contact sheet captions: sans-serif when the host
+/// maps it to a face, else the default family, else an installed family,
+/// preferring one named "Sans".
+fn caption_font_family(config: &RenderConfig) -> String {
+    let query = fontdb::Query {
+        families: &[fontdb::Family::SansSerif],
+        ..Default::default()
+    };
+    if FONTDB.query(&query).is_some() {
+        return "sans-serif".into();
+    }
+    config
+        .font_family()
+        .or_else(|| {
+            let families = font_families();
+            let sans = families.iter().position(|f| f.contains("Sans") && !f.contains("Mono"));
+            families.into_iter().nth(sans.unwrap_or(0))
+        })
+        .map_or_else(|| "sans-serif".into(), |family| format!("'{family}'"))
+}
+
+/// Composite labelled board PNGs into one grid image.
+///
+/// Each board is scaled (never up) to fit a [`SHEET_CELL`] square and centered
+/// in it, so boards of any size line up; its name is drawn underneath with the
+/// shared font database. Tiles fill rows left to right, `columns` per row.
+pub fn contact_sheet(tiles: &[(&str, &[u8])], columns: u32, config: &RenderConfig) -> Result<Vec<u8>, ScryError> {
+    if tiles.is_empty() {
+        return Err(ScryError::Render("No boards for the contact sheet".into()));
+    }
+    let columns = columns.clamp(1, tiles.len() as u32);
+    let rows = (tiles.len() as u32).div_ceil(columns);
+    let pitch_x = SHEET_CELL + SHEET_GAP;
+    let pitch_y = SHEET_CELL + SHEET_CAPTION + SHEET_GAP;
+    let width = columns * pitch_x + SHEET_GAP;
+    let height = rows * pitch_y + SHEET_GAP;
+    check_dimensions(width, height)?;
+
+    let mut sheet = image::RgbaImage::from_pixel(width, height, image::Rgba([255, 255, 255, 255]));
+    let mut captions = String::new();
+    for (i, (name, png)) in tiles.iter().enumerate() {
+        let (col, row) = (i as u32 % columns, i as u32 / columns);
+        let (x, y) = (SHEET_GAP + col * pitch_x, SHEET_GAP + row * pitch_y);
+        let cell = image::RgbaImage::from_pixel(SHEET_CELL, SHEET_CELL, image::Rgba([240, 240, 240, 255]));
+        image::imageops::replace(&mut sheet, &cell, x as i64, y as i64);
+
+        let img = image::load_from_memory_with_format(png, image::ImageFormat::Png)
+            .map_err(|e| ScryError::Render(format!("PNG decode failed for {name}: {e}")))?;
+        let thumb = if img.width() > SHEET_CELL || img.height() > SHEET_CELL {
+            img.thumbnail(SHEET_CELL, SHEET_CELL)
+        } else {
+            img
+        };
+        let tx = x + (SHEET_CELL - thumb.width()) / 2;
+        let ty = y + (SHEET_CELL - thumb.height()) / 2;
+        image::imageops::overlay(&mut sheet, &thumb.to_rgba8(), tx as i64, ty as i64);
+
+        let mut label: String = name.chars().take(SHEET_CAPTION_CHARS).collect();
+        if name.chars().count() > SHEET_CAPTION_CHARS {
+            label.push('…');
+        }
+        captions.push_str(&format!(
+            r#"<text x="{}" y="{}">{}</text>"#,
+            x + SHEET_CELL / 2,
+            y + SHEET_CELL + SHEET_CAPTION - 9,
+            crate::board::html_escape(&label)
+        ));
+    }
+
+    // Captions go on top as one SVG layer so they use the same fonts as boards
+    let family = crate::board::html_escape(&caption_font_family(config));
+    let svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}"><g font-family="{family}" font-size="14" fill="#333" text-anchor="middle">{captions}</g></svg>"##
+    );
+    let options = usvg::Options {
+        fontdb: FONTDB.clone(),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_str(&svg, &options)?;
+    let mut png = std::io::Cursor::new(Vec::new());
+    sheet
+        .write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|e| ScryError::Render(format!("PNG encode failed: {e}")))?;
+    let mut pixmap = tiny_skia::Pixmap::decode_png(&png.into_inner())
+        .map_err(|e| ScryError::Render(format!("PNG decode failed: {e}")))?;
+    resvg::render(&tree, tiny_skia::Transform::identity(), &mut pixmap.as_mut());
     pixmap
         .encode_png()
-        .map_err(|e| ScryError::Render(e.to_string()))
+        .map_err(|e| ScryError::Render(format!("PNG encode failed: {e}")))
+}
+
+/// Cap on canvas pixels times frame count for [`frames_to_apng`].
+const MAX_ANIMATION_PIXELS: u64 = 200_000_000;
+
+/// Assemble PNG frames into a looping animated PNG (APNG).
+///
+/// Frames keep full RGBA color, unlike GIF's 256-color palette. The canvas is
+/// the largest frame size; smaller frames are padded with transparency at the
+/// bottom and right. `plays` of 0 loops forever.
+pub fn frames_to_apng(frames: &[&[u8]], delay_ms: u16, plays: u32) -> Result<Vec<u8>, ScryError> {
+    if frames.is_empty() {
+        return Err(ScryError::Render("No frames to animate".into()));
+    }
+    let mut width = 0;
+    let mut height = 0;
+    for frame in frames {
+        let (w, h) = png_dimensions(frame)
+            .ok_or_else(|| ScryError::Render("Frame is not a valid PNG".into()))?;
+        width = width.max(w);
+        height = height.max(h);
+    }
+    let pixels = width as u64 * height as u64 * frames.len() as u64;
+    if pixels > MAX_ANIMATION_PIXELS {
+        return Err(ScryError::Dimensions(format!(
+            "Animation too large: {} frames at {width}x{height} (max {MAX_ANIMATION_PIXELS} pixels in total)",
+            frames.len()
+        )));
+    }
+
+    let encode_err = |e: png::EncodingError| ScryError::Render(format!("APNG encode failed: {e}"));
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, plays).map_err(encode_err)?;
+    encoder.set_frame_delay(delay_ms, 1000).map_err(encode_err)?;
+    // Every frame covers the whole canvas, so no blending with the previous one
+    encoder.set_blend_op(png::BlendOp::Source).map_err(encode_err)?;
+    let mut writer = encoder.write_header().map_err(encode_err)?;
+    // Decode one frame at a time to keep memory at a single canvas
+    for frame in frames {
+        let img = image::load_from_memory_with_format(frame, image::ImageFormat::Png)
+            .map_err(|e| ScryError::Render(format!("PNG decode failed: {e}")))?
+            .to_rgba8();
+        let mut canvas = image::RgbaImage::new(width, height);
+        image::imageops::replace(&mut canvas, &img, 0, 0);
+        writer.write_image_data(canvas.as_raw()).map_err(encode_err)?;
+    }
+    writer.finish().map_err(encode_err)?;
+    Ok(out)
+}
+
+/// Smallest long edge [`fit_png_budget`] will shrink to before giving up.
+const MIN_BUDGET_EDGE: u32 = 16;
+
+/// Downscale PNG bytes until they fit in `max_bytes`, returning the new PNG
+/// and its pixel size. PNG is lossless, so shrinking is the only lever.
+/// Returns `None` when the input already fits.
+pub fn fit_png_budget(png: &[u8], max_bytes: usize) -> Result<Option<RenderOutput>, ScryError> {
+    if png.len() <= max_bytes {
+        return Ok(None);
+    }
+    let img = image::load_from_memory_with_format(png, image::ImageFormat::Png)
+        .map_err(|e| ScryError::Render(format!("PNG decode failed: {e}")))?;
+    let (mut w, mut h) = (img.width(), img.height());
+    let mut len = png.len();
+    loop {
+        // Encoded size scales roughly with pixel count; aim a little low
+        let scale = ((max_bytes as f64 / len as f64).sqrt() * 0.9).min(0.9);
+        w = ((w as f64 * scale) as u32).max(1);
+        h = ((h as f64 * scale) as u32).max(1);
+        if w.max(h) < MIN_BUDGET_EDGE {
+            return Err(ScryError::Render(format!(
+                "PNG cannot be shrunk under {max_bytes} bytes; use smaller dimensions or simpler content"
+            )));
+        }
+        let resized = img.resize_exact(w, h, image::imageops::FilterType::Triangle);
+        let mut out = std::io::Cursor::new(Vec::new());
+        resized
+            .write_to(&mut out, image::ImageFormat::Png)
+            .map_err(|e| ScryError::Render(format!("PNG encode failed: {e}")))?;
+        let out = out.into_inner();
+        if out.len() <= max_bytes {
+            return Ok(Some(RenderOutput {
+                png: out,
+                width: w,
+                height: h,
+                warnings: Vec::new(),
+            }));
+        }
+        len = out.len();
+    }
+}
+
+fn encode_webp(img: &image::DynamicImage) -> Result<Vec<u8>, ScryError> {
+    let mut out = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut out, image::ImageFormat::WebP)
+        .map_err(|e| ScryError::Render(format!("WebP encode failed: {e}")))?;
+    Ok(out.into_inner())
 }
 
 #[cfg(test)]
@@ -49,7 +951,9 @@ mod tests {
         let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
             <rect fill="red" width="100" height="100"/>
         </svg>"#;
-        let png = svg_to_png(svg).expect("render should succeed");
+        let out = svg_to_png(svg).expect("render should succeed");
+        assert_eq!((out.width, out.height), (100, 100));
+        let png = out.png;
         // PNG magic bytes
         assert_eq!(&png[..4], &[137, 80, 78, 71]);
         assert!(png.len() > 100, "PNG should have meaningful content");
@@ -60,10 +964,386 @@ mod tests {
         let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="50">
             <text x="10" y="30" font-family="sans-serif" font-size="20" fill="black">Hello</text>
         </svg>"#;
-        let png = svg_to_png(svg).expect("text render should succeed");
+        let png = svg_to_png(svg).expect("text render should succeed").png;
         assert_eq!(&png[..4], &[137, 80, 78, 71]);
     }
 
+    #[test]
+    fn test_missing_font_warning() {
+        let installed = FONTDB.faces().next().map(|f| f.families[0].0.clone());
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="50">
+            <style>.t { font-family: 'Also Missing'; }</style>
+            <text x="10" y="30" font-family="'No Such Font Family', sans-serif">Hi</text>
+            <text class="t" x="10" y="45">Ok</text>
+        </svg>"#;
+        let out = svg_to_png(svg).expect("render should succeed despite missing font");
+        assert!(out.warnings.iter().any(|w| w.contains("No Such Font Family")), "{:?}", out.warnings);
+        assert!(out.warnings.iter().any(|w| w.contains("Also Missing")), "{:?}", out.warnings);
+
+        // A family that is installed stops the scan, so its fallbacks aren't checked
+        if let Some(family) = installed {
+            let svg = format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+                    <text style="font-family: '{family}', 'Never Tried'">x</text>
+                </svg>"#
+            );
+            assert!(svg_to_png(&svg).unwrap().warnings.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_embedded_data_images() {
+        let red = svg_to_png(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4"><rect width="4" height="4" fill="red"/></svg>"#,
+        )
+        .unwrap()
+        .png;
+        let wrap = |uri: &str| {
+            format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="20" height="20">
+                    <image xlink:href="{uri}" width="20" height="20"/>
+                </svg>"#
+            )
+        };
+
+        let valid = wrap(&format!("data:image/png;base64,{}", BASE64.encode(&red)));
+        let png = svg_to_png(&valid).expect("valid data URI should render").png;
+        let img = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(img.get_pixel(10, 10).0, [255, 0, 0, 255]);
+
+        let oversized = wrap(&format!(
+            "data:image/png;base64,{}",
+            "A".repeat(MAX_EMBEDDED_IMAGE_BYTES / 3 * 4 + 8)
+        ));
+        let err = svg_to_png(&oversized).unwrap_err();
+        assert!(matches!(err, ScryError::SvgParse(_)));
+        assert!(err.to_string().contains("exceeds"), "{err}");
+
+        let malformed = wrap("data:image/png;base64,iVBORw0KGgoAAAA=");
+        assert!(svg_to_png(&malformed).unwrap_err().to_string().contains("invalid embedded image"));
+        assert!(svg_to_png(&wrap("data:image/png,raw")).is_err());
+    }
+
+    #[test]
+    fn test_fit_png_budget() {
+        // A noisy grid compresses poorly, so the PNG is comfortably large
+        let mut svg = String::from(r#"<svg xmlns="http://www.w3.org/2000/svg" width="512" height="512">"#);
+        for i in 0..64u32 {
+            for j in 0..64u32 {
+                let c = (i * 7919 + j * 104729) % 0xFFFFFF;
+                svg.push_str(&format!(r##"<rect x="{}" y="{}" width="8" height="8" fill="#{c:06x}"/>"##, i * 8, j * 8));
+            }
+        }
+        svg.push_str("</svg>");
+        let png = svg_to_png(&svg).unwrap().png;
+
+        assert!(fit_png_budget(&png, png.len()).unwrap().is_none(), "fits already");
+
+        let budget = png.len() / 4;
+        let out = fit_png_budget(&png, budget).unwrap().expect("should downscale");
+        assert!(out.png.len() <= budget);
+        assert!(out.width < 512 && out.height < 512);
+        assert_eq!(out.width, out.height, "aspect ratio kept");
+
+        assert!(fit_png_budget(&png, 10).is_err());
+    }
+
+    #[test]
+    fn test_render_pdf() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50">
+            <rect fill="red" width="100" height="50"/>
+            <text x="5" y="30" font-family="sans-serif" font-size="20">Hi</text>
+        </svg>"#;
+        let pdf = svg_to_pdf(svg, &RenderConfig::default()).expect("pdf conversion should succeed");
+        assert!(pdf.starts_with(b"%PDF-"));
+        // Page is sized to the SVG in points
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.contains("/MediaBox [0 0 100 50]"), "unexpected MediaBox");
+
+        // Text without a font-family uses the configured default, as in PNGs
+        if font_families().iter().any(|f| f == "DejaVu Sans Mono") {
+            let config = RenderConfig {
+                default_font_family: Some("DejaVu Sans Mono".into()),
+                ..Default::default()
+            };
+            let plain = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50"><text x="5" y="30">Hi</text></svg>"#;
+            let pdf = svg_to_pdf(plain, &config).unwrap();
+            assert!(String::from_utf8_lossy(&pdf).contains("DejaVuSansMono"));
+        }
+
+        let external = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <image href="file:///etc/passwd" width="10" height="10"/>
+        </svg>"#;
+        assert!(matches!(svg_to_pdf(external, &RenderConfig::default()), Err(ScryError::SvgParse(_))));
+    }
+
+    #[test]
+    fn test_render_webp() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <rect fill="red" width="100" height="100"/>
+        </svg>"#;
+        let png = svg_to_png(svg).unwrap().png;
+        let webp = png_to_webp(&png).expect("transcode should succeed");
+        assert_eq!(&webp[..4], b"RIFF");
+        assert_eq!(&webp[8..12], b"WEBP");
+    }
+
+    #[test]
+    fn test_make_thumbnail() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="1600" height="800">
+            <rect fill="blue" width="1600" height="800"/>
+        </svg>"#;
+        let png = svg_to_png(svg).unwrap().png;
+        let thumb = make_thumbnail(&png, THUMB_MAX_EDGE).expect("thumbnail should succeed");
+        let img = image::load_from_memory(&thumb).unwrap();
+        assert_eq!((img.width(), img.height()), (400, 200));
+
+        // Small images pass through untouched
+        let small = svg_to_png(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>"#).unwrap().png;
+        assert_eq!(make_thumbnail(&small, THUMB_MAX_EDGE).unwrap(), small);
+    }
+
+    #[test]
+    fn test_rejects_external_image() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="10" height="10">
+            <image href="http://example.com/x.png" width="10" height="10"/>
+        </svg>"#;
+        let err = svg_to_png(svg).unwrap_err().to_string();
+        assert!(err.contains("external references not allowed"), "got: {err}");
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="10" height="10">
+            <image xlink:href="file:///etc/passwd" width="10" height="10"/>
+        </svg>"#;
+        assert!(svg_to_png(svg).is_err());
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <rect width="10" height="10" style="fill: url('http://example.com/p.svg#g')"/>
+        </svg>"#;
+        assert!(svg_to_png(svg).is_err());
+
+        let svg = r#"<?xml version="1.0"?>
+<!DOCTYPE svg [<!ENTITY x SYSTEM "file:///etc/passwd">]>
+<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><text>&x;</text></svg>"#;
+        assert!(svg_to_png(svg).is_err());
+
+        let svg = r#"<?xml version="1.0"?>
+<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd">
+<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>"#;
+        assert!(svg_to_png(svg).is_ok(), "standard DOCTYPE should be accepted");
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <style>@import "http://example.com/x.css";</style>
+        </svg>"#;
+        assert!(svg_to_png(svg).is_err());
+    }
+
+    #[test]
+    fn test_css_lookalike_text_renders() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="40">
+            <text x="5" y="15" font-family="sans-serif">see url(http://x)</text>
+            <text x="5" y="35" font-family="sans-serif">@import <tspan>url('https://example.com')</tspan></text>
+        </svg>"#;
+        assert!(parse_checked(svg).is_ok());
+        assert!(svg_to_png(svg).is_ok());
+    }
+
+    #[test]
+    fn test_allows_local_and_data_refs() {
+        // 1x1 red PNG
+        let data_png = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8DwHwAFBQIAX8jx0gAAAABJRU5ErkJggg==";
+        let svg = format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <defs><linearGradient id="g"><stop offset="0" stop-color="red"/></linearGradient></defs>
+            <rect width="10" height="10" fill="url(#g)"/>
+            <image href="data:image/png;base64,{data_png}" width="10" height="10"/>
+        </svg>"##
+        );
+        svg_to_png(&svg).expect("data: and #fragment refs should render");
+    }
+
+    #[test]
+    fn test_rendered_size_follows_svg() {
+        // The SVG's own size wins over whatever the board asked for
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="320" height="200"/>"#;
+        let out = svg_to_png(svg).unwrap();
+        assert_eq!((out.width, out.height), (320, 200));
+        let img = image::load_from_memory(&out.png).unwrap();
+        assert_eq!((img.width(), img.height()), (320, 200));
+    }
+
+    #[test]
+    fn test_quality_modes() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="40"><circle cx="20" cy="20" r="15" fill="black"/></svg>"#;
+        let partial_alpha = |quality| {
+            let png = svg_to_png_with_quality(svg, quality, &RenderConfig::default()).unwrap().png;
+            let img = image::load_from_memory(&png).expect("valid PNG").to_rgba8();
+            img.pixels().filter(|p| p[3] != 0 && p[3] != 255).count()
+        };
+        assert!(partial_alpha(Quality::Smooth) > 0, "smooth edges are antialiased");
+        assert_eq!(partial_alpha(Quality::Crisp), 0, "crisp edges are hard");
+        assert_eq!(Quality::parse("crisp"), Ok(Quality::Crisp));
+        assert!(Quality::parse("fast").is_err());
+    }
+
+    #[test]
+    fn test_diff_png() {
+        let base = svg_to_png(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="white"/></svg>"#).unwrap().png;
+        let dot = svg_to_png(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="white"/><rect x="2" y="2" width="2" height="2" fill="blue"/></svg>"#).unwrap().png;
+
+        let same = diff_png(&base, &base).unwrap();
+        assert_eq!((same.changed, same.total, same.size_mismatch), (0, 100, false));
+
+        let diff = diff_png(&base, &dot).unwrap();
+        assert_eq!(diff.changed, 4);
+        let img = image::load_from_memory(&diff.png).unwrap().to_rgba8();
+        assert_eq!(img.get_pixel(2, 2)[0], 255, "changed pixel is tinted red");
+        assert_eq!(img.get_pixel(2, 2)[1], img.get_pixel(2, 2)[2]);
+        assert!(img.get_pixel(0, 0)[1] > 200, "unchanged pixel is light gray");
+
+        let wide = svg_to_png(r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"><rect width="20" height="10" fill="white"/></svg>"#).unwrap().png;
+        let grown = diff_png(&base, &wide).unwrap();
+        assert!(grown.size_mismatch);
+        assert_eq!((grown.changed, grown.total), (100, 200));
+    }
+
+    #[test]
+    fn test_render_fit() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50"><rect width="100" height="50" fill="red"/></svg>"#;
+        let out = svg_to_png_fit(svg, Quality::Smooth, Some(400), None, &RenderConfig::default()).unwrap();
+        assert_eq!((out.width, out.height), (400, 200));
+        let out = svg_to_png_fit(svg, Quality::Smooth, Some(400), Some(100), &RenderConfig::default()).unwrap();
+        assert_eq!((out.width, out.height), (200, 100));
+        let img = image::load_from_memory(&out.png).unwrap().to_rgba8();
+        assert_eq!(img.get_pixel(199, 99).0, [255, 0, 0, 255], "drawing is scaled, not cropped");
+        assert!(svg_to_png_fit(svg, Quality::Smooth, Some(100_000), None, &RenderConfig::default()).is_err());
+    }
+
+    #[test]
+    fn test_watermark() {
+        assert_eq!(
+            Watermark::parse("top-left:(c) Ana: 2026").unwrap(),
+            Watermark { text: "(c) Ana: 2026".into(), corner: Corner::TopLeft }
+        );
+        assert_eq!(Watermark::parse("note: hi").unwrap().corner, Corner::BottomRight);
+        assert_eq!(Watermark::parse("note: hi").unwrap().text, "note: hi");
+        assert!(Watermark::parse("bottom-left:  ").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-font")]
+    fn test_watermark_draws_in_corner() {
+        // Only the compiled-in face, so the result doesn't depend on the host
+        let db = Arc::new(build_fontdb(false));
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100"><rect width="200" height="100" fill="#336"/></svg>"##;
+        let (before, _) = render_pixmap(svg, Quality::default(), (None, None), &RenderConfig::default()).unwrap();
+        let mut pixmap = before.clone();
+        draw_watermark(&mut pixmap, &Watermark::parse("bottom-right:scry").unwrap(), &db);
+        let changed: Vec<(u32, u32)> = (0..100u32)
+            .flat_map(|y| (0..200u32).map(move |x| (x, y)))
+            .filter(|&(x, y)| pixmap.pixel(x, y) != before.pixel(x, y))
+            .collect();
+        assert!(!changed.is_empty(), "label drew something");
+        assert!(changed.iter().all(|&(x, y)| x >= 100 && y >= 50), "label stays in its corner");
+    }
+
+    #[test]
+    fn test_fonts_on_empty_host() {
+        // A container with no system fonts
+        let db = build_fontdb(false);
+        if cfg!(not(feature = "embedded-font")) {
+            assert!(db.is_empty());
+            return;
+        }
+        let mut options = usvg::Options {
+            fontdb: Arc::new(db),
+            ..Default::default()
+        };
+        options.font_family = RenderConfig::default().font_family().unwrap();
+        let drawn = |svg: &str| {
+            let tree = usvg::Tree::from_str(svg, &options).unwrap();
+            let mut pixmap = tiny_skia::Pixmap::new(100, 40).unwrap();
+            resvg::render(&tree, tiny_skia::Transform::identity(), &mut pixmap.as_mut());
+            pixmap.pixels().iter().any(|p| p.alpha() > 0)
+        };
+        let text = |family: &str| {
+            format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="40"><text x="5" y="30" font-size="20"{family}>Hi</text></svg>"#)
+        };
+        assert!(drawn(&text("")), "no font-family uses the default");
+        assert!(drawn(&text(r#" font-family="sans-serif""#)), "generic family falls back");
+        assert!(drawn(&text(r#" font-family="monospace""#)));
+    }
+
+    #[test]
+    fn test_font_families() {
+        let families = font_families();
+        assert!(families.windows(2).all(|w| w[0].to_lowercase() <= w[1].to_lowercase() && w[0] != w[1]));
+        assert_eq!(families.is_empty(), FONTDB.is_empty());
+    }
+
+    #[test]
+    fn test_qr_png() {
+        let png = qr_png("http://127.0.0.1:3000/gallery/board/demo").unwrap();
+        let img = image::load_from_memory(&png).unwrap().to_luma8();
+        assert_eq!(img.width(), img.height());
+        assert_eq!(img.width() % QR_MODULE_PX, 0);
+        // Quiet zone is white, the top-left finder pattern starts right after it
+        assert_eq!(img.get_pixel(0, 0).0, [255]);
+        let finder = 4 * QR_MODULE_PX;
+        assert_eq!(img.get_pixel(finder, finder).0, [0]);
+    }
+
+    #[test]
+    fn test_contact_sheet() {
+        let red = svg_to_png(r#"<svg xmlns="http://www.w3.org/2000/svg" width="800" height="400"><rect width="800" height="400" fill="red"/></svg>"#).unwrap().png;
+        let blue = svg_to_png(r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="40"><rect width="40" height="40" fill="blue"/></svg>"#).unwrap().png;
+        let sheet = contact_sheet(&[("red", &red), ("blue", &blue), ("<red & blue>", &red)], 2, &RenderConfig::default()).unwrap();
+        let img = image::load_from_memory(&sheet).unwrap().to_rgba8();
+        let pitch_x = SHEET_CELL + SHEET_GAP;
+        assert_eq!(img.width(), 2 * pitch_x + SHEET_GAP);
+        assert_eq!(img.height(), 2 * (SHEET_CELL + SHEET_CAPTION + SHEET_GAP) + SHEET_GAP);
+        // Wide board is scaled down to fit its cell, the small one is centered as-is
+        let center = SHEET_GAP + SHEET_CELL / 2;
+        assert_eq!(img.get_pixel(SHEET_GAP + 1, center).0, [255, 0, 0, 255]);
+        assert_eq!(img.get_pixel(pitch_x + center, center).0, [0, 0, 255, 255]);
+        assert_eq!(img.get_pixel(pitch_x + SHEET_GAP + 1, center).0, [240, 240, 240, 255]);
+        // Caption ink under the first cell
+        let caption_top = SHEET_GAP + SHEET_CELL;
+        let inked = (caption_top..caption_top + SHEET_CAPTION)
+            .flat_map(|y| (SHEET_GAP..SHEET_GAP + SHEET_CELL).map(move |x| (x, y)))
+            .any(|(x, y)| img.get_pixel(x, y).0[0] < 128);
+        assert!(inked, "caption not drawn");
+
+        assert!(contact_sheet(&[], 4, &RenderConfig::default()).is_err());
+
+        // A full page fits the dimension cap; one more row would not
+        let pitch_y = SHEET_CELL + SHEET_CAPTION + SHEET_GAP;
+        for columns in [1, 4, 12] {
+            let rows = contact_sheet_capacity(columns) as u32 / columns;
+            assert!(check_dimensions(columns * pitch_x + SHEET_GAP, rows * pitch_y + SHEET_GAP).is_ok());
+            assert!(check_dimensions(columns * pitch_x + SHEET_GAP, (rows + 1) * pitch_y + SHEET_GAP).is_err());
+        }
+    }
+
+    #[test]
+    fn test_frames_to_apng() {
+        let small = svg_to_png(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="red"/></svg>"#).unwrap().png;
+        let wide = svg_to_png(r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"><rect width="20" height="10" fill="blue"/></svg>"#).unwrap().png;
+        let apng = frames_to_apng(&[&small, &wide, &small], 250, 0).unwrap();
+
+        let decoder = png::Decoder::new(std::io::Cursor::new(apng));
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (20, 10), "canvas fits the largest frame");
+        let control = info.animation_control().expect("animated");
+        assert_eq!((control.num_frames, control.num_plays), (3, 0));
+        // Still a valid PNG for viewers without APNG support
+        let first = image::load_from_memory(&frames_to_apng(&[&small, &wide], 250, 0).unwrap()).unwrap().to_rgba8();
+        assert_eq!(first.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(first.get_pixel(15, 5).0[3], 0, "padding is transparent");
+
+        assert!(frames_to_apng(&[], 100, 0).is_err());
+    }
+
     #[test]
     fn test_render_invalid_svg() {
         let result = svg_to_png("not svg at all");
@@ -1,38 +1,506 @@
-use crate::board::{Board, BoardEvent, BoardEventType, SharedState, Snapshot, sanitize_filename, validate_board_name};
-use pyo3::Python;
+use crate::error::ScryError;
+use crate::board::{self, Board, BoardEventType, SharedState, board_file_stem, normalize_description, normalize_tags, url_encode, validate_board_name};
+use crate::colors;
+use crate::presets;
 use crate::python;
 use crate::render;
+use crate::svg_util;
+use crate::templates;
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64;
 use chrono::Utc;
+use std::time::Instant;
 use rmcp::ServerHandler;
+use rmcp::handler::server::router::prompt::PromptRouter;
 use rmcp::handler::server::tool::ToolRouter;
 use rmcp::handler::server::wrapper::Parameters;
 use rmcp::model::{
-    CallToolResult, Content, Implementation, ProtocolVersion, ServerCapabilities, ServerInfo,
+    AnnotateAble, CallToolResult, Content, GetPromptRequestParams, GetPromptResult, Implementation,
+    ListPromptsResult, ListResourcesResult, PaginatedRequestParams, PromptMessage,
+    PromptMessageRole, ProtocolVersion, RawResource, ReadResourceRequestParams,
+    ReadResourceResult, ResourceContents, ServerCapabilities, ServerInfo,
 };
+use rmcp::service::RequestContext;
+use rmcp::RoleServer;
 use rmcp::schemars;
-use rmcp::{tool, tool_handler, tool_router};
+use rmcp::{prompt, prompt_handler, prompt_router, tool, tool_handler, tool_router};
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Default, serde::Deserialize, schemars::JsonSchema)]
 pub struct WhiteboardParams {
     /// Name of the board (creates new if doesn't exist)
     pub name: String,
     /// Python code to execute. Call svg('<svg>...</svg>') to set SVG content.
-    /// Variables persist across calls to the same board.
+    /// Calling svg() more than once returns every image; the last one is kept
+    /// as the board's render. Variables persist across calls to the same board.
+    /// layer(name, fragment, z=0) accumulates named SVG fragments across calls;
+    /// if svg() isn't called they are composited into the render (layer.clear()
+    /// to reset, layer.remove(name) to drop one).
+    /// linear_gradient(id, [(offset, color), ...], angle=0) and
+    /// radial_gradient(id, stops) return <defs> fragments for fill="url(#id)".
+    /// rect(x, y, w, h, **attrs), circle(cx, cy, r, ...), line(x1, y1, x2, y2, ...),
+    /// text(x, y, content, ...) and group(*children, ...) return element strings
+    /// (stroke_width= → stroke-width, class_= → class); svg_doc(w, h, *children)
+    /// wraps them in a document and calls svg() with it.
+    /// oklch(l, c, h, alpha=1), oklab(l, a, b), oklaba(l, a, b, alpha),
+    /// color_mix(a, b, t) (Oklab), hue_shift(color, deg) return hex strings;
+    /// ease(t, kind='in_out') eases 0..1 (linear/in/out/in_out).
     /// Available: math, random, json, re, textwrap, itertools, functools,
     /// collections, colorsys, hashlib, string, dataclasses.
     /// WIDTH and HEIGHT are preset to board dimensions.
     pub code: String,
-    /// Board width in pixels (default 800)
+    /// Board width in pixels. Omit to keep the board's current width (the
+    /// server default, usually 800, for a new board) as WIDTH and let the
+    /// SVG's own size set the stored width.
+    pub width: Option<u32>,
+    /// Board height in pixels. Omit to keep the board's current height (the
+    /// server default, usually 600, for a new board) as HEIGHT and let the
+    /// SVG's own size set the stored height.
+    pub height: Option<u32>,
+    /// Seed for the `random` module, applied right before the code runs.
+    /// Omit for the usual nondeterministic behavior.
+    pub seed: Option<u64>,
+    /// Strip comments and insignificant whitespace from the SVG before it is
+    /// rendered and stored (default false). Rendered output is unchanged.
+    pub minify: Option<bool>,
+    /// Tags for organizing boards (same characters as names are allowed).
+    /// Replaces the board's tags when given; omit to keep the existing tags,
+    /// pass [] to clear them.
+    pub tags: Option<Vec<String>>,
+    /// Pin (true) or unpin (false) the board; omit to leave it unchanged.
+    /// Pinned boards are kept by any automatic cleanup.
+    pub pinned: Option<bool>,
+    /// Short note on what the board is for (max 1000 characters), shown in
+    /// the gallery and whiteboard_list. Omit to keep it, "" to clear it.
+    pub description: Option<String>,
+    /// Antialiasing: "smooth" (default) for curves, text and photos, or "crisp"
+    /// for hard pixel edges on grid-aligned diagrams and pixel art (diagonals
+    /// and curves come out jagged). shape-rendering etc. in the SVG still win.
+    pub quality: Option<String>,
+    /// Also return the board's SVG as an image/svg+xml resource, for clients
+    /// that render vectors directly (default false). The PNG is always included.
+    pub return_svg: Option<bool>,
+    /// Return images downscaled to at most 512px on the long edge to save
+    /// context (default false). The board still stores the full render.
+    pub thumbnail: Option<bool>,
+    /// Only compile the code and report syntax errors, without running it or
+    /// touching the board (default false).
+    pub check_only: Option<bool>,
+    /// Style preset: "dark", "light", "blueprint" or "minimal". Sets STYLE_BG,
+    /// STYLE_FG, STYLE_ACCENT, STYLE_GRID (hex colors), STYLE_FONT (font-family),
+    /// STYLE_STROKE_WIDTH (px) and STYLE_PALETTE (list of hex colors) before
+    /// the code runs. They stay set for later calls until another style is given.
+    pub style: Option<String>,
+    /// Treat a run that never calls svg() as an error instead of a success
+    /// with a note (default false). Variables it set are still kept.
+    pub require_svg: Option<bool>,
+    /// Snapshot the previous render into the board's history before replacing
+    /// it (default true). Pass false for scratch iterations not worth keeping.
+    pub keep_history: Option<bool>,
+    /// Accessibility remap applied to every fill/stroke/stop-color (hex or
+    /// named) before rendering and storing: "protanopia" or "deuteranopia"
+    /// shift red-green contrast to blue-yellow, "high-contrast" spreads
+    /// lightness and boosts saturation.
+    pub remap_palette: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WhiteboardListParams {
+    /// Encode thumbnails as lossless WebP instead of PNG (smaller, default false)
+    pub webp: Option<bool>,
+    /// Include thumbnail images (default true). Set false for metadata only.
+    pub thumbnails: Option<bool>,
+    /// Maximum number of boards to return (default: all)
+    pub limit: Option<usize>,
+    /// Number of boards to skip, in the chosen sort order (default 0)
+    pub offset: Option<usize>,
+    /// Sort by "updated" (default), "created" or "name"
+    pub sort: Option<String>,
+    /// Reverse order. Defaults to newest first for times and A→Z for names.
+    pub descending: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct BoardNameParams {
+    /// Name of the board
+    pub name: String,
+}
+
+/// Build a tool-level error result carrying a stable `code` in `structuredContent`
+/// so clients can branch on the failure class without parsing the message.
+fn scry_error_result(err: &ScryError, message: String) -> CallToolResult {
+    let mut result = CallToolResult::error(vec![Content::text(message.clone())]);
+    result.structured_content = Some(serde_json::json!({
+        "code": err.kind().code(),
+        "message": message,
+    }));
+    result
+}
+
+/// [`scry_error_result`] for an error whose message is the whole response.
+fn error_result(err: ScryError) -> CallToolResult {
+    let message = err.to_string();
+    scry_error_result(&err, message)
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct WhiteboardVarsParams {
+    /// Name of the board
+    pub name: String,
+    /// Variable names to delete from the namespace before listing
+    pub clear: Option<Vec<String>>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct PlotFunctionArgs {
+    /// Python expression in x, e.g. "math.sin(x) * x"
+    pub expression: String,
+    /// Left end of the x range (default -10)
+    pub x_min: Option<String>,
+    /// Right end of the x range (default 10)
+    pub x_max: Option<String>,
+    /// Board to draw on (default "plot")
+    pub board: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct BarChartArgs {
+    /// JSON data: an object like {"apples": 3, "pears": 5} or a list of
+    /// [label, value] pairs
+    pub data: String,
+    /// Chart title (optional)
+    pub title: Option<String>,
+    /// Board to draw on (default "bar-chart")
+    pub board: Option<String>,
+}
+
+/// Python for the `plot_function` prompt. `__EXPR__`, `__X_MIN__` and
+/// `__X_MAX__` are substituted; the rest runs as-is in the sandbox.
+const PLOT_FUNCTION_PY: &str = r##"def fn(x):
+    return __EXPR__
+
+X_MIN, X_MAX, N, M = __X_MIN__, __X_MAX__, 400, 50
+pts = []
+for i in range(N + 1):
+    x = X_MIN + (X_MAX - X_MIN) * i / N
+    try:
+        y = float(fn(x))
+    except (ValueError, ZeroDivisionError, OverflowError):
+        y = math.nan
+    pts.append((x, y if math.isfinite(y) else None))
+
+ys = [y for _, y in pts if y is not None]
+Y_MIN, Y_MAX = (min(ys), max(ys)) if ys else (-1.0, 1.0)
+if Y_MAX == Y_MIN:
+    Y_MIN, Y_MAX = Y_MIN - 1, Y_MAX + 1
+
+def sx(x):
+    return M + (x - X_MIN) / (X_MAX - X_MIN) * (WIDTH - 2 * M)
+
+def sy(y):
+    return HEIGHT - M - (y - Y_MIN) / (Y_MAX - Y_MIN) * (HEIGHT - 2 * M)
+
+# Break the curve wherever the function is undefined
+segments, cur = [], []
+for x, y in pts:
+    if y is None:
+        if cur:
+            segments.append(cur)
+        cur = []
+    else:
+        cur.append(f"{sx(x):.1f},{sy(y):.1f}")
+if cur:
+    segments.append(cur)
+
+parts = [f'<rect width="{WIDTH}" height="{HEIGHT}" fill="white"/>']
+if X_MIN <= 0 <= X_MAX:
+    parts.append(f'<line x1="{sx(0):.1f}" y1="{M}" x2="{sx(0):.1f}" y2="{HEIGHT - M}" stroke="#999"/>')
+if Y_MIN <= 0 <= Y_MAX:
+    parts.append(f'<line x1="{M}" y1="{sy(0):.1f}" x2="{WIDTH - M}" y2="{sy(0):.1f}" stroke="#999"/>')
+for seg in segments:
+    parts.append(f'<polyline points="{" ".join(seg)}" fill="none" stroke="#e94560" stroke-width="2"/>')
+label = 'font-family="sans-serif" font-size="12" fill="#555"'
+parts.append(f'<text x="{M}" y="{HEIGHT - M + 18}" {label}>{X_MIN:g}</text>')
+parts.append(f'<text x="{WIDTH - M}" y="{HEIGHT - M + 18}" text-anchor="end" {label}>{X_MAX:g}</text>')
+parts.append(f'<text x="{M - 6}" y="{M + 4}" text-anchor="end" {label}>{Y_MAX:.3g}</text>')
+parts.append(f'<text x="{M - 6}" y="{HEIGHT - M + 4}" text-anchor="end" {label}>{Y_MIN:.3g}</text>')
+svg(f'<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}">{"".join(parts)}</svg>')
+"##;
+
+/// Python for the `bar_chart` prompt. `__DATA__` and `__TITLE__` are
+/// substituted with JSON string literals, which are valid Python too.
+const BAR_CHART_PY: &str = r##"from_json = json.loads(__DATA__)
+data = list(from_json.items()) if isinstance(from_json, dict) else [tuple(p) for p in from_json]
+title = __TITLE__
+
+M, TOP = 60, 60 if title else 30
+vmax = max([v for _, v in data] + [0])
+vmin = min([v for _, v in data] + [0])
+span = (vmax - vmin) or 1
+plot_h = HEIGHT - TOP - M
+slot = (WIDTH - 2 * M) / max(len(data), 1)
+
+def sy(v):
+    return TOP + (vmax - v) / span * plot_h
+
+def esc(s):
+    return str(s).replace("&", "&amp;").replace("<", "&lt;")
+
+parts = [f'<rect width="{WIDTH}" height="{HEIGHT}" fill="white"/>']
+if title:
+    parts.append(f'<text x="{WIDTH / 2}" y="36" text-anchor="middle" font-family="sans-serif" font-size="20">{esc(title)}</text>')
+for i, (name, v) in enumerate(data):
+    hue = i / max(len(data), 1)
+    r, g, b = (int(c * 255) for c in colorsys.hsv_to_rgb(hue, 0.55, 0.85))
+    x = M + i * slot + slot * 0.15
+    y0, y1 = sorted((sy(0), sy(v)))
+    parts.append(f'<rect x="{x:.1f}" y="{y0:.1f}" width="{slot * 0.7:.1f}" height="{y1 - y0:.1f}" fill="rgb({r},{g},{b})"/>')
+    parts.append(f'<text x="{x + slot * 0.35:.1f}" y="{HEIGHT - M + 18}" text-anchor="middle" font-family="sans-serif" font-size="12">{esc(name)}</text>')
+    parts.append(f'<text x="{x + slot * 0.35:.1f}" y="{y0 - 4:.1f}" text-anchor="middle" font-family="sans-serif" font-size="11" fill="#555">{v:g}</text>')
+parts.append(f'<line x1="{M}" y1="{sy(0):.1f}" x2="{WIDTH - M}" y2="{sy(0):.1f}" stroke="#333"/>')
+svg(f'<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}">{"".join(parts)}</svg>')
+"##;
+
+/// URI of the [`EXAMPLES`] resource.
+const EXAMPLES_URI: &str = "scry://examples";
+
+/// Known-good snippets served as the `scry://examples` resource:
+/// (title, what the render should look like, code).
+const EXAMPLES: &[(&str, &str, &str)] = &[
+    (
+        "Sine wave",
+        "Two periods of a blue sine curve across a white canvas, with a gray axis through the middle.",
+        r##"M = 40
+pts = []
+for i in range(201):
+    t = i / 200
+    x = M + t * (WIDTH - 2 * M)
+    y = HEIGHT / 2 - math.sin(t * 4 * math.pi) * (HEIGHT / 2 - M)
+    pts.append(f"{x:.1f},{y:.1f}")
+axis = line(M, HEIGHT / 2, WIDTH - M, HEIGHT / 2, stroke="#999")
+curve = f'<polyline points="{" ".join(pts)}" fill="none" stroke="#1f77b4" stroke-width="3"/>'
+svg_doc(WIDTH, HEIGHT, rect(0, 0, WIDTH, HEIGHT, fill="white"), axis, curve)
+"##,
+    ),
+    (
+        "Bar chart",
+        "Five evenly spaced bars, one per weekday, in Oklch colors of equal lightness, labelled with the day below and the value above.",
+        r##"data = {"Mon": 12, "Tue": 19, "Wed": 7, "Thu": 15, "Fri": 22}
+M = 50
+slot = (WIDTH - 2 * M) / len(data)
+top = max(data.values())
+items = [rect(0, 0, WIDTH, HEIGHT, fill="white")]
+for i, (day, v) in enumerate(data.items()):
+    h = v / top * (HEIGHT - 2 * M)
+    x = M + i * slot + slot * 0.15
+    items.append(rect(x, HEIGHT - M - h, slot * 0.7, h, fill=oklch(0.65, 0.15, i * 60)))
+    items.append(text(x + slot * 0.35, HEIGHT - M + 20, day, text_anchor="middle", font_size=14, font_family="sans-serif"))
+    items.append(text(x + slot * 0.35, HEIGHT - M - h - 6, str(v), text_anchor="middle", font_size=12, font_family="sans-serif"))
+svg_doc(WIDTH, HEIGHT, *items)
+"##,
+    ),
+    (
+        "Spirograph",
+        "A dense purple hypotrochoid rosette centered on a near-black canvas.",
+        r##"R, r, d = 220, 83, 110
+cx, cy = WIDTH / 2, HEIGHT / 2
+scale = min(WIDTH, HEIGHT) / 2 / (R - r + d) * 0.9
+pts = []
+for i in range(3001):
+    t = i / 3000 * 2 * math.pi * r / math.gcd(R, r)
+    x = (R - r) * math.cos(t) + d * math.cos((R - r) / r * t)
+    y = (R - r) * math.sin(t) - d * math.sin((R - r) / r * t)
+    pts.append(f"{cx + x * scale:.1f},{cy + y * scale:.1f}")
+path = f'<polyline points="{" ".join(pts)}" fill="none" stroke="{color_mix("#ff006e", "#3a86ff", 0.5)}" stroke-width="1.2"/>'
+svg_doc(WIDTH, HEIGHT, rect(0, 0, WIDTH, HEIGHT, fill="#111"), path)
+"##,
+    ),
+];
+
+/// Render [`EXAMPLES`] as one markdown document.
+fn examples_markdown() -> String {
+    let mut doc = String::from(
+        "# Scry examples\n\nEach snippet is complete: pass it as `code` to the whiteboard tool. \
+         Sizes follow WIDTH and HEIGHT, so any board dimensions work.\n",
+    );
+    for (title, expected, code) in EXAMPLES {
+        doc.push_str(&format!("\n## {title}\n\nExpected: {expected}\n\n```python\n{code}```\n"));
+    }
+    doc
+}
+
+/// Expand [`PLOT_FUNCTION_PY`] for one expression and x range.
+fn plot_function_code(expression: &str, x_min: f64, x_max: f64) -> String {
+    PLOT_FUNCTION_PY
+        .replace("__EXPR__", expression)
+        .replace("__X_MIN__", &format!("{x_min:?}"))
+        .replace("__X_MAX__", &format!("{x_max:?}"))
+}
+
+/// Expand [`BAR_CHART_PY`] for parsed data and a title (empty for none).
+fn bar_chart_code(data: &[(String, f64)], title: &str) -> serde_json::Result<String> {
+    let data_json = serde_json::to_string(&serde_json::to_string(data)?)?;
+    let title_json = serde_json::to_string(title)?;
+    Ok(BAR_CHART_PY
+        .replace("__DATA__", &data_json)
+        .replace("__TITLE__", &title_json))
+}
+
+/// Wrap generated code in a user message asking for a `whiteboard` call.
+fn whiteboard_prompt(board: &str, intro: &str, code: &str) -> Vec<PromptMessage> {
+    vec![PromptMessage::new_text(
+        PromptMessageRole::User,
+        format!(
+            "{intro} Call the whiteboard tool with name={board:?} and this code, \
+             then adjust colors, labels, or ranges as needed:\n\n```python\n{code}```"
+        ),
+    )]
+}
+
+/// Parse bar chart data from a JSON object or a list of `[label, value]` pairs.
+fn parse_bar_data(data: &str) -> Result<Vec<(String, f64)>, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(data).map_err(|e| format!("data is not valid JSON: {e}"))?;
+    let label = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let pairs: Vec<(String, Option<f64>)> = match &value {
+        serde_json::Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v.as_f64())).collect(),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(|item| match item.as_array().map(Vec::as_slice) {
+                Some([name, v]) => Ok((label(name), v.as_f64())),
+                _ => Err("data list items must be [label, value] pairs".to_string()),
+            })
+            .collect::<Result<_, _>>()?,
+        _ => return Err("data must be a JSON object or a list of [label, value] pairs".into()),
+    };
+    if pairs.is_empty() {
+        return Err("data is empty".into());
+    }
+    pairs
+        .into_iter()
+        .map(|(name, v)| v.map(|v| (name.clone(), v)).ok_or(format!("value for {name:?} is not a number")))
+        .collect()
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct BatchEntry {
+    /// Name of the board (creates new if doesn't exist)
+    pub name: String,
+    /// Python code to execute, as for the whiteboard tool
+    pub code: String,
+    /// Board width in pixels, as for the whiteboard tool
+    pub width: Option<u32>,
+    /// Board height in pixels, as for the whiteboard tool
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct BatchParams {
+    /// Executions to run, in order
+    pub entries: Vec<BatchEntry>,
+    /// Stop at the first failing entry (default false: run them all)
+    pub fail_fast: Option<bool>,
+}
+
+/// Long-edge cap for images returned with `thumbnail: true`.
+const RESPONSE_THUMB_MAX_EDGE: u32 = 512;
+
+/// Most entries one `whiteboard_batch` call may run.
+const MAX_BATCH_ENTRIES: usize = 32;
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct TemplateParams {
+    /// Template name (a .py file in the server's template directory, without
+    /// the extension). Call with an unknown name to list what's available.
+    pub template: String,
+    /// Board to run the template on (creates new if doesn't exist)
+    pub name: String,
+    /// Values for the template's {{placeholders}}. Strings are inserted as-is;
+    /// numbers, booleans, lists and objects become Python literals.
+    pub params: Option<serde_json::Map<String, serde_json::Value>>,
+    /// Board width in pixels, as for the whiteboard tool
+    pub width: Option<u32>,
+    /// Board height in pixels, as for the whiteboard tool
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ExportAllParams {
+    /// Also include each board's JSON-serializable Python variables as {name}.vars.json (default false)
+    pub include_namespace: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ImportParams {
+    /// Base64 zip archive as returned by whiteboard_export_all
+    pub archive: String,
+    /// Overwrite boards that already exist instead of skipping them (default false)
+    pub replace: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ApngParams {
+    /// Name of the board
+    pub name: String,
+    /// Delay between frames in milliseconds (default 500, 10-10000)
+    pub delay_ms: Option<u16>,
+    /// Times to play the animation; 0 loops forever (default 0)
+    pub loops: Option<u32>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ContactSheetParams {
+    /// Boards per row (default 4, 1-12)
+    pub columns: Option<u32>,
+    /// Maximum boards on the sheet (default and cap: as many as fit in
+    /// 8192px, 22 rows)
+    pub limit: Option<usize>,
+    /// Number of boards to skip, in name order (default 0)
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct RenderSvgParams {
+    /// Complete SVG document to render
+    pub svg: String,
+    /// Scale the drawing to this width in pixels, keeping its aspect ratio
+    /// (default: the SVG's own size). With height too, it fits inside both.
     pub width: Option<u32>,
-    /// Board height in pixels (default 600)
+    /// Scale the drawing to this height in pixels, keeping its aspect ratio
     pub height: Option<u32>,
 }
 
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct PinParams {
+    /// Name of the board
+    pub name: String,
+    /// New pin state; omit to toggle
+    pub pinned: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct DescribeParams {
+    /// Name of the board
+    pub name: String,
+    /// What the board is for (max 1000 characters); "" clears it
+    pub description: String,
+}
+
+#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+pub struct ClearAllParams {
+    /// Must be exactly "yes-delete-everything"
+    pub confirm: String,
+}
+
+/// Token `whiteboard_clear_all` requires, so a wipe is never accidental.
+const CLEAR_ALL_CONFIRM: &str = "yes-delete-everything";
+
 #[derive(Clone)]
 pub struct ScryServer {
     tool_router: ToolRouter<Self>,
+    prompt_router: PromptRouter<Self>,
     state: SharedState,
 }
 
@@ -40,62 +508,134 @@ pub struct ScryServer {
 impl ScryServer {
     pub fn new(state: SharedState) -> Self {
         let tool_router = Self::tool_router();
-        Self { tool_router, state }
+        let prompt_router = Self::prompt_router();
+        Self {
+            tool_router,
+            prompt_router,
+            state,
+        }
     }
 
     #[tool(
         name = "whiteboard",
-        description = "Execute Python code to generate SVG visuals on a named board. Call svg('<svg>...</svg>') in your code to set the board's SVG content, which gets rendered to PNG automatically. Variables persist between calls to the same board. Returns the rendered PNG image and a gallery URL."
+        description = "Execute Python code to generate SVG visuals on a named board. Call svg('<svg>...</svg>') in your code to set the board's SVG content (or build it with the rect/circle/line/text/group helpers and svg_doc(WIDTH, HEIGHT, *children)), which gets rendered to PNG automatically. Variables persist between calls to the same board. Returns the rendered PNG image and a gallery URL."
     )]
     // NOTE: tool description above is static; actual response adapts based on --port/--output-dir
     async fn whiteboard(
         &self,
         Parameters(params): Parameters<WhiteboardParams>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
+        self.run_whiteboard(params).await
+    }
+
+    /// Remember a failed run's message on the board for `whiteboard_last_error`
+    /// and turn it into the tool error.
+    async fn fail_run(&self, name: &str, err: &ScryError, message: String) -> CallToolResult {
+        if let Some(board) = self.state.boards.write().await.get_mut(name) {
+            board.last_error = Some(message.clone());
+        }
+        scry_error_result(err, message)
+    }
+
+    /// Execute code on one board, render, store, and build the tool response.
+    /// Shared by `whiteboard` and `whiteboard_batch`.
+    async fn run_whiteboard(
+        &self,
+        params: WhiteboardParams,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        if self.state.sandbox.disable_execution {
+            return Ok(error_result(ScryError::ExecutionDisabled("Execution disabled on this server".into())));
+        }
+        if let Err(wait) = self.state.try_acquire_exec() {
+            return Ok(error_result(ScryError::RateLimited(format!(
+                "Rate limit exceeded: too many whiteboard executions. Slow down and retry in {} ms.",
+                wait.as_millis().max(1)
+            ))));
+        }
         let name = params.name;
         let code = params.code;
-        let w = params.width.unwrap_or(800);
-        let h = params.height.unwrap_or(600);
 
         // Validate inputs
         if let Err(msg) = validate_board_name(&name) {
-            return Ok(CallToolResult::error(vec![Content::text(msg)]));
+            return Ok(error_result(ScryError::InvalidParams(msg)));
         }
-        if w == 0 || h == 0 {
-            return Ok(CallToolResult::error(vec![Content::text(
-                "Width and height must be greater than zero",
-            )]));
+        if params.width == Some(0) || params.height == Some(0) {
+            return Ok(error_result(ScryError::InvalidParams("Width and height must be greater than zero".into())));
         }
-        if w > 8192 || h > 8192 {
-            return Ok(CallToolResult::error(vec![Content::text(
-                "Width and height must be at most 8192",
-            )]));
+        if params.width.is_some_and(|w| w > render::MAX_DIMENSION)
+            || params.height.is_some_and(|h| h > render::MAX_DIMENSION)
+        {
+            return Ok(error_result(ScryError::InvalidParams(format!(
+                "Width and height must be at most {}",
+                render::MAX_DIMENSION
+            ))));
         }
+        let tags = match params.tags.as_deref().map(normalize_tags).transpose() {
+            Ok(t) => t,
+            Err(msg) => return Ok(error_result(ScryError::InvalidParams(msg))),
+        };
+        let description = match params.description.as_deref().map(normalize_description).transpose() {
+            Ok(d) => d,
+            Err(msg) => return Ok(error_result(ScryError::InvalidParams(msg))),
+        };
+        let style = match params.style.as_deref().map(presets::get).transpose() {
+            Ok(s) => s,
+            Err(msg) => return Ok(error_result(ScryError::InvalidParams(msg))),
+        };
+        let quality = match params.quality.as_deref().map(render::Quality::parse).transpose() {
+            Ok(q) => q.unwrap_or_default(),
+            Err(msg) => return Ok(error_result(ScryError::InvalidParams(msg))),
+        };
+        let remap = match params.remap_palette.as_deref().map(colors::Remap::parse).transpose() {
+            Ok(r) => r,
+            Err(msg) => return Ok(error_result(ScryError::InvalidParams(msg))),
+        };
         const MAX_CODE_LEN: usize = 1_000_000; // 1 MB
         if code.len() > MAX_CODE_LEN {
-            return Ok(CallToolResult::error(vec![Content::text(format!(
+            return Ok(error_result(ScryError::TooLarge(format!(
                 "Code too large ({} bytes, max {MAX_CODE_LEN})",
                 code.len()
-            ))]));
+            ))));
+        }
+
+        if params.check_only.unwrap_or(false) {
+            return Ok(match python::check_syntax_async(code, &self.state.sandbox).await {
+                Ok(()) => CallToolResult::success(vec![Content::text(format!(
+                    "Board: {name}\nSyntax OK (check_only: nothing was run)"
+                ))]),
+                Err(e) => scry_error_result(&e, e.to_string()),
+            });
         }
 
+        // Serialize calls to this board through execute + store; held until return
+        let _board_guard = self.state.lock_board(&name).await;
+
         // Get or create namespace atomically under write lock to prevent
         // TOCTOU race where two concurrent requests for a new board both
         // create independent namespaces.
         //
         // Py<PyDict>::clone requires the thread to be attached to the Python
         // interpreter, so we must do it inside Python::attach.
-        let (namespace, is_new_board) = {
+        //
+        // Omitted dimensions fall back to the board's current size, so WIDTH
+        // and HEIGHT stay stable across calls that don't pass them.
+        let (namespace, is_new_board, w, h) = {
             let mut boards = self.state.boards.write().await;
-            if boards.contains_key(&name) {
-                let ns = Python::attach(|py| boards.get(&name).unwrap().namespace.clone_ref(py));
-                (ns, false)
+            if let Some(board) = boards.get(&name) {
+                let ns = board.namespace.clone_ref();
+                let w = params.width.unwrap_or(board.width);
+                let h = params.height.unwrap_or(board.height);
+                (ns, false, w, h)
             } else {
+                let w = params.width.unwrap_or(self.state.render.default_width);
+                let h = params.height.unwrap_or(self.state.render.default_height);
                 // Create namespace and placeholder board under the lock
-                let ns = python::create_namespace_async(w, h)
+                let ns = self
+                    .state
+                    .new_namespace(w, h, None)
                     .await
                     .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
-                let ns_copy = Python::attach(|py| ns.clone_ref(py));
+                let ns_copy = ns.clone_ref();
                 let now = Utc::now();
                 boards.insert(
                     name.clone(),
@@ -105,22 +645,35 @@ impl ScryServer {
                         height: h,
                         svg: String::new(),
                         png: Vec::new(),
+                        thumb_png: Vec::new(),
                         namespace: ns,
                         created_at: now,
                         updated_at: now,
                         history: Vec::new(),
+                        seed: None,
+                        tags: Vec::new(),
+                        pinned: false,
+                        last_error: None,
+                        description: None,
+                        share_token: self.state.issue_share_token(&name),
                     },
                 );
-                (ns_copy, true)
+                (ns_copy, true, w, h)
             }
         };
 
-        // Execute Python code
-        let (result, namespace) = match python::run_python(namespace, code, w, h).await {
+        // Execute Python code. The code body only goes to debug logs.
+        let code_len = code.len();
+        tracing::debug!(board = %name, code = %code, "whiteboard code");
+        let exec_start = Instant::now();
+        let exec_result = python::run_python(namespace, code, w, h, params.seed, style, &self.state.sandbox).await;
+        let exec_ms = exec_start.elapsed().as_millis() as u64;
+        let (result, namespace) = match exec_result {
             Ok(r) => r,
             Err(e) => {
+                tracing::info!(board = %name, code_len, exec_ms, error = e.kind().code(), "whiteboard failed");
                 // Python errors → CallToolResult::error so the model sees the traceback
-                return Ok(CallToolResult::error(vec![Content::text(e.to_string())]));
+                return Ok(self.fail_run(&name, &e, e.to_string()).await);
             }
         };
 
@@ -128,34 +681,173 @@ impl ScryServer {
         let svg_content = match result.svg_content {
             Some(svg) => svg,
             None => {
-                let mut msg =
-                    String::from("Code executed successfully but svg() was not called.\n");
+                let require_svg = params.require_svg.unwrap_or(false);
+                let mut msg = if require_svg {
+                    String::from("Code ran but svg() was never called (require_svg is set).\n")
+                } else {
+                    String::from("Code executed successfully but svg() was not called.\n")
+                };
                 if !result.stdout.is_empty() {
                     msg.push_str("\n--- stdout ---\n");
                     msg.push_str(&result.stdout);
                 }
-                // Save updated namespace back to board
+                // Save updated namespace back to board; the code did run
                 let mut boards = self.state.boards.write().await;
                 if let Some(board) = boards.get_mut(&name) {
                     board.namespace = namespace;
+                    board.last_error = require_svg.then(|| msg.clone());
+                    if let Some(tags) = tags {
+                        board.tags = tags;
+                    }
+                    if let Some(pinned) = params.pinned {
+                        board.pinned = pinned;
+                    }
+                    if let Some(description) = description {
+                        board.description = description;
+                    }
                     board.updated_at = Utc::now();
                 }
-                return Ok(CallToolResult::success(vec![Content::text(msg)]));
+                tracing::info!(board = %name, code_len, svg = false, exec_ms, "whiteboard executed");
+                return Ok(if require_svg {
+                    error_result(ScryError::NoSvg(msg))
+                } else {
+                    CallToolResult::success(vec![Content::text(msg)])
+                });
+            }
+        };
+
+        let (svg_content, earlier_svgs) = if params.minify.unwrap_or(false) {
+            let earlier = result.earlier_svgs.iter().map(|s| svg_util::minify(s)).collect();
+            (svg_util::minify(&svg_content), earlier)
+        } else {
+            (svg_content, result.earlier_svgs)
+        };
+        // The renderer never runs scripts, but the gallery serves this SVG as-is
+        let (svg_content, stripped) = svg_util::sanitize(&svg_content);
+        let earlier_svgs: Vec<String> = earlier_svgs.iter().map(|s| svg_util::sanitize(s).0).collect();
+        let (svg_content, earlier_svgs) = match remap {
+            Some(remap) => {
+                let recolor = |svg: &str| svg_util::map_colors(svg, |c| remap.apply_str(c).ok()).0;
+                (recolor(&svg_content), earlier_svgs.iter().map(|s| recolor(s)).collect())
             }
+            None => (svg_content, earlier_svgs),
         };
 
         // Render SVG to PNG
-        let png_bytes = match render::svg_to_png(&svg_content) {
-            Ok(png) => png,
+        let render_start = Instant::now();
+        let (png_bytes, rendered_w, rendered_h, mut warnings) = match self.state.render_png(svg_content.clone(), quality).await {
+            Ok(out) => (out.png, out.width, out.height, out.warnings),
             Err(e) => {
+                tracing::info!(board = %name, code_len, exec_ms, error = e.kind().code(), "whiteboard render failed");
                 // Render errors are also tool-level so the model can fix its SVG
-                return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "SVG render failed: {e}"
-                ))]));
+                return Ok(self.fail_run(&name, &e, format!("SVG render failed: {e}")).await);
             }
         };
+        if stripped > 0 {
+            warnings.push(format!(
+                "Removed {stripped} active content item(s) (scripts, event handlers, javascript: links, embedded HTML, DTD entities) from the SVG"
+            ));
+        }
+
+        // Enforce the PNG size budget before anything is stored
+        let mut downscaled = None;
+        let png_bytes = match self.state.render.max_png_bytes {
+            Some(max) => match self
+                .state
+                .run_render(move || render::fit_png_budget(&png_bytes, max).map(|fit| (png_bytes, fit)))
+                .await
+            {
+                Ok((png_bytes, None)) => png_bytes,
+                Ok((png_bytes, Some(out))) => {
+                    tracing::info!(
+                        "Downscaled {name} from {rendered_w}x{rendered_h} ({} bytes) to {}x{} ({} bytes) to fit --max-png-bytes {max}",
+                        png_bytes.len(),
+                        out.width,
+                        out.height,
+                        out.png.len()
+                    );
+                    downscaled = Some((out.width, out.height, max));
+                    out.png
+                }
+                Err(e) => {
+                    tracing::info!(board = %name, code_len, exec_ms, error = e.kind().code(), "whiteboard render failed");
+                    return Ok(self.fail_run(&name, &e, format!("SVG render failed: {e}")).await);
+                }
+            },
+            None => png_bytes,
+        };
+
+        let stored_size = downscaled.map_or((rendered_w, rendered_h), |(dw, dh, _)| (dw, dh));
+        // Earlier svg() calls from this run are returned too, but only the last
+        // one becomes the board's render
+        let total_svgs = earlier_svgs.len() + 1;
+        let mut earlier_pngs = Vec::with_capacity(earlier_svgs.len());
+        for (i, svg) in earlier_svgs.iter().enumerate() {
+            match self.state.render_png(svg.clone(), quality).await {
+                Ok(out) => {
+                    for warning in out.warnings {
+                        if !warnings.contains(&warning) {
+                            warnings.push(warning);
+                        }
+                    }
+                    earlier_pngs.push(out.png);
+                }
+                Err(e) => {
+                    tracing::info!(board = %name, code_len, exec_ms, error = e.kind().code(), "whiteboard render failed");
+                    return Ok(self
+                        .fail_run(
+                            &name,
+                            &e,
+                            format!("SVG render failed (svg() call {} of {total_svgs}): {e}", i + 1),
+                        )
+                        .await);
+                }
+            }
+        }
+
+        let render_ms = render_start.elapsed().as_millis() as u64;
+        tracing::info!(
+            board = %name,
+            code_len,
+            svg = true,
+            images = total_svgs,
+            exec_ms,
+            render_ms,
+            svg_bytes = svg_content.len(),
+            png_bytes = png_bytes.len(),
+            "whiteboard executed"
+        );
 
-        let png_base64 = BASE64.encode(&png_bytes);
+        // Thumbnails only shrink what the caller sees; the board keeps the full render
+        let thumbnail = params.thumbnail.unwrap_or(false);
+        let job_name = name.clone();
+        let thumbs = self.state.run_render(move || {
+            // Thumbnail failure isn't fatal; the gallery falls back to the full PNG
+            let thumb_png = render::make_thumbnail(&png_bytes, render::THUMB_MAX_EDGE)
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Thumbnail generation failed for {job_name}: {e}");
+                    Vec::new()
+                });
+            let encode_png = |png: &[u8]| {
+                if thumbnail {
+                    match render::make_thumbnail(png, RESPONSE_THUMB_MAX_EDGE) {
+                        Ok(thumb) => return BASE64.encode(thumb),
+                        Err(e) => tracing::warn!("Response thumbnail failed for {job_name}: {e}"),
+                    }
+                }
+                BASE64.encode(png)
+            };
+            let images: Vec<String> = earlier_pngs
+                .iter()
+                .chain(std::iter::once(&png_bytes))
+                .map(|png| encode_png(png))
+                .collect();
+            Ok((png_bytes, thumb_png, images))
+        });
+        let (png_bytes, thumb_png, images) = match thumbs.await {
+            Ok(out) => out,
+            Err(e) => return Ok(self.fail_run(&name, &e, format!("SVG render failed: {e}")).await),
+        };
 
         // Clone bytes for file output before the board lock takes ownership
         let png_for_file = if self.state.output_dir.is_some() {
@@ -166,25 +858,35 @@ impl ScryServer {
 
         // Store results in board (board always exists — created in get-or-create above)
         let now = Utc::now();
+        let mut dimensions_changed = true;
+        let mut share_token = None;
         {
             let mut boards = self.state.boards.write().await;
             if let Some(board) = boards.get_mut(&name) {
-                if !board.svg.is_empty() {
-                    const MAX_HISTORY: usize = 50;
-                    if board.history.len() >= MAX_HISTORY {
-                        board.history.remove(0);
-                    }
-                    board.history.push(Snapshot {
-                        svg: board.svg.clone(),
-                        png: board.png.clone(),
-                        timestamp: board.updated_at,
-                    });
+                share_token = Some(board.share_token.clone());
+                let prev_size = render::png_dimensions(&board.png);
+                dimensions_changed = prev_size != Some(stored_size);
+                if params.keep_history.unwrap_or(true) {
+                    board.push_history();
                 }
                 board.svg = svg_content.clone();
                 board.png = png_bytes;
+                board.thumb_png = thumb_png;
+                board.seed = params.seed;
+                board.last_error = None;
+                if let Some(tags) = tags {
+                    board.tags = tags;
+                }
+                if let Some(pinned) = params.pinned {
+                    board.pinned = pinned;
+                }
+                if let Some(description) = description {
+                    board.description = description;
+                }
                 board.namespace = namespace;
-                board.width = w;
-                board.height = h;
+                // Explicit dimensions win; otherwise the SVG's own size does
+                board.width = params.width.unwrap_or(rendered_w);
+                board.height = params.height.unwrap_or(rendered_h);
                 board.updated_at = now;
             }
         }
@@ -195,16 +897,13 @@ impl ScryServer {
         } else {
             BoardEventType::Updated
         };
-        let _ = self.state.event_tx.send(BoardEvent {
-            board_name: name.clone(),
-            event_type,
-        });
+        self.state.publish_render(&name, event_type, Some(stored_size), dimensions_changed);
 
         // Write files to output_dir if configured (best-effort)
         let mut png_path = None;
         let mut svg_path = None;
         if let Some(ref dir) = self.state.output_dir {
-            let safe_name = sanitize_filename(&name);
+            let safe_name = board_file_stem(&name);
             let png_file = dir.join(format!("{safe_name}.png"));
             let svg_file = dir.join(format!("{safe_name}.svg"));
             match std::fs::write(&png_file, png_for_file.as_ref().unwrap()) {
@@ -225,13 +924,45 @@ impl ScryServer {
             }
             format!("{}...", &svg_content[..end])
         } else {
-            svg_content
+            svg_content.clone()
         };
 
-        let mut header = format!("Board: {name}\nSize: {w}x{h}");
+        let explicit = params.width.is_some() && params.height.is_some();
+        let mut header = format!(
+            "Board: {name}\nSize: {w}x{h} {}, {rendered_w}x{rendered_h} rendered",
+            if explicit { "requested" } else { "as WIDTH/HEIGHT" }
+        );
+        if (rendered_w, rendered_h) != (w, h) {
+            header.push_str(if explicit {
+                "\nNote: the SVG's own width/height/viewBox overrides WIDTH/HEIGHT"
+            } else {
+                "\nNote: width/height omitted, so the board takes its size from the SVG"
+            });
+        }
+        if let Some((dw, dh, max)) = downscaled {
+            header.push_str(&format!(
+                "\nNote: stored PNG downscaled to {dw}x{dh} to fit the {max}-byte size budget"
+            ));
+        }
+        if thumbnail && stored_size.0.max(stored_size.1) > RESPONSE_THUMB_MAX_EDGE {
+            header.push_str(&format!(
+                "\nNote: images are thumbnails (max {RESPONSE_THUMB_MAX_EDGE}px); the board keeps the full render"
+            ));
+        }
+        if let Some(seed) = params.seed {
+            header.push_str(&format!("\nSeed: {seed}"));
+        }
+        if total_svgs > 1 {
+            header.push_str(&format!(
+                "\nImages: {total_svgs} (svg() called {total_svgs} times; the last is the board render)"
+            ));
+        }
         if let Some(url) = self.state.board_url(&name) {
             header.push_str(&format!("\nURL: {url}"));
         }
+        if let Some(url) = share_token.and_then(|t| self.state.share_url(&t)) {
+            header.push_str(&format!("\nShare: {url}"));
+        }
         if let Some(ref p) = png_path {
             header.push_str(&format!("\nPNG: {}", p.display()));
         }
@@ -240,22 +971,149 @@ impl ScryServer {
         }
 
         let mut text_parts = vec![header];
+        if !warnings.is_empty() {
+            text_parts.push(format!("--- warnings ---\n{}", warnings.join("\n")));
+        }
         if !result.stdout.is_empty() {
             text_parts.push(format!("--- stdout ---\n{}", result.stdout));
         }
         text_parts.push(format!("--- SVG (snippet) ---\n{svg_snippet}"));
 
-        Ok(CallToolResult::success(vec![
-            Content::image(png_base64, "image/png"),
-            Content::text(text_parts.join("\n\n")),
-        ]))
+        let mut content: Vec<Content> = images
+            .into_iter()
+            .map(|png| Content::image(png, "image/png"))
+            .collect();
+        if params.return_svg.unwrap_or(false) {
+            content.push(Content::resource(ResourceContents::BlobResourceContents {
+                uri: format!("scry://board/{}/svg", url_encode(&name)),
+                mime_type: Some("image/svg+xml".into()),
+                blob: BASE64.encode(&svg_content),
+                meta: None,
+            }));
+        }
+        content.push(Content::text(text_parts.join("\n\n")));
+        Ok(CallToolResult::success(content))
+    }
+
+    #[tool(
+        name = "whiteboard_template",
+        description = "Run an operator-provided code template on a board. Fills the template's {{placeholders}} from params, then behaves exactly like the whiteboard tool. Only available when the server has a template directory."
+    )]
+    async fn whiteboard_template(
+        &self,
+        Parameters(params): Parameters<TemplateParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let Some(ref dir) = self.state.template_dir else {
+            return Ok(error_result(ScryError::NotFound("No templates configured (start the server with --template-dir)".into())));
+        };
+        let source = match templates::load(dir, &params.template) {
+            Ok(source) => source,
+            Err(msg) => {
+                let available = templates::list(dir);
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "{msg}\nAvailable templates: {}",
+                    if available.is_empty() { "(none)".to_string() } else { available.join(", ") }
+                ))));
+            }
+        };
+        let template_params = params.params.unwrap_or_default();
+        let code = match templates::substitute(&source, &template_params) {
+            Ok(code) => code,
+            Err(msg) => return Ok(error_result(ScryError::InvalidParams(msg))),
+        };
+        self.run_whiteboard(WhiteboardParams {
+            name: params.name,
+            code,
+            width: params.width,
+            height: params.height,
+            ..Default::default()
+        })
+        .await
+    }
+
+    #[tool(
+        name = "whiteboard_batch",
+        description = "Run several whiteboard executions in one call, in order. Each entry is {name, code, width?, height?} with the same semantics as the whiteboard tool. Returns every entry's images and output with a per-entry ok/error status. Set fail_fast=true to stop at the first error."
+    )]
+    async fn whiteboard_batch(
+        &self,
+        Parameters(params): Parameters<BatchParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        if params.entries.is_empty() {
+            return Ok(error_result(ScryError::InvalidParams("entries is empty".into())));
+        }
+        if params.entries.len() > MAX_BATCH_ENTRIES {
+            return Ok(error_result(ScryError::InvalidParams(format!(
+                "Too many entries ({}, max {MAX_BATCH_ENTRIES})",
+                params.entries.len()
+            ))));
+        }
+        let fail_fast = params.fail_fast.unwrap_or(false);
+        let total = params.entries.len();
+        let (mut ok, mut failed) = (0, 0);
+        let mut content = Vec::new();
+
+        // One at a time: entries may share boards, and the interpreter is single-threaded anyway
+        for (i, entry) in params.entries.into_iter().enumerate() {
+            let name = entry.name.clone();
+            let result = self
+                .run_whiteboard(WhiteboardParams {
+                    name: entry.name,
+                    code: entry.code,
+                    width: entry.width,
+                    height: entry.height,
+                    ..Default::default()
+                })
+                .await;
+            let (status, entry_content) = match result {
+                Ok(r) if r.is_error != Some(true) => ("ok", r.content),
+                Ok(r) => ("error", r.content),
+                Err(e) => ("error", vec![Content::text(e.message.to_string())]),
+            };
+            content.push(Content::text(format!("=== [{}/{total}] {name}: {status} ===", i + 1)));
+            content.extend(entry_content);
+            if status == "ok" {
+                ok += 1;
+            } else {
+                failed += 1;
+                if fail_fast {
+                    break;
+                }
+            }
+        }
+
+        let skipped = total - ok - failed;
+        let mut summary = format!("Batch: {ok} ok, {failed} failed");
+        if skipped > 0 {
+            summary.push_str(&format!(", {skipped} skipped (fail_fast)"));
+        }
+        content.insert(0, Content::text(summary));
+        Ok(if failed > 0 {
+            CallToolResult::error(content)
+        } else {
+            CallToolResult::success(content)
+        })
     }
 
     #[tool(
         name = "whiteboard_list",
-        description = "List all active boards with their thumbnails, URLs, and metadata."
+        description = "List active boards, most recently updated first, with thumbnails, URLs, and metadata. Use sort=created|name (with descending) to reorder, thumbnails=false for text only, and limit/offset to page through many boards."
     )]
-    async fn whiteboard_list(&self) -> Result<CallToolResult, rmcp::ErrorData> {
+    async fn whiteboard_list(
+        &self,
+        Parameters(params): Parameters<WhiteboardListParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let webp = params.webp.unwrap_or(false);
+        let thumbnails = params.thumbnails.unwrap_or(true);
+        let offset = params.offset.unwrap_or(0);
+        let sort = params.sort.as_deref().unwrap_or("updated");
+        if !matches!(sort, "updated" | "created" | "name") {
+            return Ok(error_result(ScryError::InvalidParams(format!(
+                "Unknown sort {sort:?} (expected \"updated\", \"created\" or \"name\")"
+            ))));
+        }
+        let descending = params.descending.unwrap_or(sort != "name");
+
         // Collect data under read lock, release before base64 encoding
         struct BoardSummary {
             name: String,
@@ -265,10 +1123,13 @@ impl ScryServer {
             created: String,
             updated: String,
             history_len: usize,
+            tags: Vec<String>,
+            pinned: bool,
+            description: Option<String>,
             png: Vec<u8>,
         }
 
-        let board_data: Vec<BoardSummary> = {
+        let (total, mut board_data): (usize, Vec<BoardSummary>) = {
             let boards = self.state.boards.read().await;
             if boards.is_empty() {
                 return Ok(CallToolResult::success(vec![Content::text(
@@ -276,8 +1137,19 @@ impl ScryServer {
                 )]));
             }
             let mut list: Vec<_> = boards.values().collect();
-            list.sort_by_key(|b| b.created_at);
-            list.into_iter()
+            list.sort_by(|a, b| {
+                let order = match sort {
+                    "created" => a.created_at.cmp(&b.created_at),
+                    "name" => a.name.cmp(&b.name),
+                    _ => a.updated_at.cmp(&b.updated_at),
+                };
+                let order = if descending { order.reverse() } else { order };
+                order.then_with(|| a.name.cmp(&b.name))
+            });
+            let page = list
+                .into_iter()
+                .skip(offset)
+                .take(params.limit.unwrap_or(usize::MAX))
                 .map(|b| BoardSummary {
                     name: b.name.clone(),
                     url: self.state.board_url(&b.name),
@@ -286,37 +1158,987 @@ impl ScryServer {
                     created: b.created_at.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
                     updated: b.updated_at.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
                     history_len: b.history.len(),
-                    png: b.png.clone(),
+                    tags: b.tags.clone(),
+                    pinned: b.pinned,
+                    description: b.description.clone(),
+                    png: if !thumbnails {
+                        Vec::new()
+                    } else if b.thumb_png.is_empty() {
+                        b.png.clone()
+                    } else {
+                        b.thumb_png.clone()
+                    },
                 })
-                .collect()
+                .collect();
+            (boards.len(), page)
         }; // read lock released
 
         let mut content = Vec::new();
-        for b in board_data {
+        if board_data.is_empty() {
+            content.push(Content::text(format!(
+                "No boards at offset {offset} ({total} total)."
+            )));
+        } else if board_data.len() < total {
+            content.push(Content::text(format!(
+                "Showing boards {}-{} of {total}",
+                offset + 1,
+                offset + board_data.len()
+            )));
+        }
+        // WebP transcoding is image work, so it takes a render slot
+        let images: Vec<(Vec<u8>, &'static str)> = if webp {
+            let pngs: Vec<(String, Vec<u8>)> = board_data
+                .iter_mut()
+                .map(|b| (b.name.clone(), std::mem::take(&mut b.png)))
+                .collect();
+            let transcode = move || {
+                Ok(pngs
+                    .into_iter()
+                    .map(|(name, png)| {
+                        if png.is_empty() {
+                            return (png, "image/png");
+                        }
+                        match render::png_to_webp(&png) {
+                            Ok(bytes) => (bytes, "image/webp"),
+                            Err(e) => {
+                                tracing::warn!("WebP transcode failed for {name}: {e}");
+                                (png, "image/png")
+                            }
+                        }
+                    })
+                    .collect())
+            };
+            match self.state.run_render(transcode).await {
+                Ok(images) => images,
+                Err(e) => return Ok(scry_error_result(&e, format!("WebP transcode failed: {e}"))),
+            }
+        } else {
+            board_data
+                .iter_mut()
+                .map(|b| (std::mem::take(&mut b.png), "image/png"))
+                .collect()
+        };
+        for (b, (image, mime_type)) in board_data.into_iter().zip(images) {
             let mut info = format!(
                 "Board: {}\nSize: {}x{}\nCreated: {}\nUpdated: {}\nHistory: {} snapshots",
                 b.name, b.width, b.height, b.created, b.updated, b.history_len,
             );
+            if !b.tags.is_empty() {
+                info.push_str(&format!("\nTags: {}", b.tags.join(", ")));
+            }
+            if b.pinned {
+                info.push_str("\nPinned: yes");
+            }
+            if let Some(ref description) = b.description {
+                info.push_str(&format!("\nDescription: {description}"));
+            }
             if let Some(ref url) = b.url {
                 info.push_str(&format!("\nURL: {url}"));
             }
             content.push(Content::text(info));
-            if !b.png.is_empty() {
-                content.push(Content::image(BASE64.encode(&b.png), "image/png"));
+            if !image.is_empty() {
+                content.push(Content::image(BASE64.encode(&image), mime_type));
             }
         }
 
         Ok(CallToolResult::success(content))
     }
+
+    #[tool(
+        name = "whiteboard_vars",
+        description = "List a board's persisted Python variables as JSON (modules, functions, and non-serializable values are omitted). Pass clear=[names] to delete those variables first."
+    )]
+    async fn whiteboard_vars(
+        &self,
+        Parameters(params): Parameters<WhiteboardVarsParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let name = params.name;
+        // Clearing mutates the namespace, so order it with whiteboard runs
+        let _board_guard = self.state.lock_board(&name).await;
+
+        let namespace = {
+            let boards = self.state.boards.read().await;
+            let Some(board) = boards.get(&name) else {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board not found: {name}"
+                ))));
+            };
+            board.namespace.clone_ref()
+        };
+
+        let report = match python::inspect_vars_async(namespace, params.clear.unwrap_or_default()).await {
+            Ok(r) => r,
+            Err(e) => return Ok(scry_error_result(&e, e.to_string())),
+        };
+
+        let json = serde_json::to_string_pretty(&report.vars)
+            .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+        let mut text = format!("Board: {name}\nVariables: {}\n\n{json}", report.vars.len());
+        if !report.cleared.is_empty() {
+            text.push_str(&format!("\n\nCleared: {}", report.cleared.join(", ")));
+        }
+        if !report.not_found.is_empty() {
+            text.push_str(&format!("\nNot found: {}", report.not_found.join(", ")));
+        }
+        if !report.skipped.is_empty() {
+            text.push_str(&format!(
+                "\nNot JSON-serializable (omitted): {}",
+                report.skipped.join(", ")
+            ));
+        }
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        name = "whiteboard_last_error",
+        description = "Fetch the full error (including any Python traceback) from a board's most recent failed whiteboard run. Cleared by the next successful run."
+    )]
+    async fn whiteboard_last_error(
+        &self,
+        Parameters(params): Parameters<BoardNameParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let name = params.name;
+        let boards = self.state.boards.read().await;
+        let Some(board) = boards.get(&name) else {
+            return Ok(error_result(ScryError::NotFound(format!(
+                "Board not found: {name}"
+            ))));
+        };
+        let text = match board.last_error {
+            Some(ref error) => format!("Board: {name}\nLast error:\n{error}"),
+            None => format!("Board: {name}\nNo error since the last successful run."),
+        };
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        name = "whiteboard_describe",
+        description = "Set or clear a board's description (what it's for) without re-running its code. Shown in the gallery and whiteboard_list."
+    )]
+    async fn whiteboard_describe(
+        &self,
+        Parameters(params): Parameters<DescribeParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let name = params.name;
+        let description = match normalize_description(&params.description) {
+            Ok(d) => d,
+            Err(msg) => return Ok(error_result(ScryError::InvalidParams(msg))),
+        };
+        {
+            let mut boards = self.state.boards.write().await;
+            let Some(board) = boards.get_mut(&name) else {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board not found: {name}"
+                ))));
+            };
+            board.description = description.clone();
+        }
+        self.state.publish(&name, BoardEventType::Updated);
+        let text = match description {
+            Some(d) => format!("Board {name} description: {d}"),
+            None => format!("Board {name} description cleared"),
+        };
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        name = "whiteboard_snapshot",
+        description = "Checkpoint a board: push its current SVG and PNG onto its history without re-running code, e.g. before an experimental change you may want to undo or diff against. Returns the new history length."
+    )]
+    async fn whiteboard_snapshot(
+        &self,
+        Parameters(params): Parameters<BoardNameParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let name = params.name;
+        // Don't interleave with a run that is about to store into this board
+        let _board_guard = self.state.lock_board(&name).await;
+        let history_len = {
+            let mut boards = self.state.boards.write().await;
+            let Some(board) = boards.get_mut(&name) else {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board not found: {name}"
+                ))));
+            };
+            if board.svg.is_empty() {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board {name} has no render to snapshot yet"
+                ))));
+            }
+            board.push_history();
+            board.history.len()
+        };
+        Ok(CallToolResult::success(vec![Content::text(format!(
+            "Board {name} snapshot saved\nHistory: {history_len} snapshots (max {})",
+            board::MAX_HISTORY
+        ))]))
+    }
+
+    #[tool(
+        name = "whiteboard_pin",
+        description = "Pin or unpin a board. Pinned boards are marked in the gallery and kept by any automatic cleanup. Omit pinned to toggle."
+    )]
+    async fn whiteboard_pin(
+        &self,
+        Parameters(params): Parameters<PinParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let name = params.name;
+        let pinned = {
+            let mut boards = self.state.boards.write().await;
+            let Some(board) = boards.get_mut(&name) else {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board not found: {name}"
+                ))));
+            };
+            board.pinned = params.pinned.unwrap_or(!board.pinned);
+            board.pinned
+        };
+        self.state.publish(&name, BoardEventType::Updated);
+        let state = if pinned { "pinned" } else { "unpinned" };
+        Ok(CallToolResult::success(vec![Content::text(format!("Board {name} is now {state}"))]))
+    }
+
+    #[tool(
+        name = "whiteboard_clear_all",
+        description = "Delete every board, its history and namespace, and its files in the output directory. Irreversible. Requires confirm=\"yes-delete-everything\"."
+    )]
+    async fn whiteboard_clear_all(
+        &self,
+        Parameters(params): Parameters<ClearAllParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        if params.confirm != CLEAR_ALL_CONFIRM {
+            return Ok(error_result(ScryError::InvalidParams(format!(
+                "Not confirmed. Pass confirm=\"{CLEAR_ALL_CONFIRM}\" to delete all boards."
+            ))));
+        }
+
+        // Wait out in-flight whiteboard calls so none stores into a removed board
+        let mut names: Vec<String> = self.state.boards.read().await.keys().cloned().collect();
+        names.sort();
+        let mut guards = Vec::with_capacity(names.len());
+        for name in &names {
+            guards.push(self.state.lock_board(name).await);
+        }
+
+        let removed: Vec<String> = {
+            let mut boards = self.state.boards.write().await;
+            names
+                .into_iter()
+                .filter(|n| match boards.remove(n) {
+                    Some(board) => {
+                        self.state.revoke_share_token(&board.share_token);
+                        true
+                    }
+                    None => false,
+                })
+                .collect()
+        };
+        drop(guards);
+
+        let mut files_removed = 0;
+        for name in &removed {
+            if let Some(ref dir) = self.state.output_dir {
+                let safe_name = board_file_stem(name);
+                for ext in ["png", "svg", "pdf", "apng"] {
+                    let path = dir.join(format!("{safe_name}.{ext}"));
+                    match std::fs::remove_file(&path) {
+                        Ok(()) => files_removed += 1,
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(e) => tracing::warn!("Failed to remove {}: {e}", path.display()),
+                    }
+                }
+            }
+            self.state.publish(name, BoardEventType::Deleted);
+        }
+        tracing::info!("Cleared {} boards ({files_removed} output files)", removed.len());
+
+        let mut text = format!("Removed {} boards", removed.len());
+        if self.state.output_dir.is_some() {
+            text.push_str(&format!(" and {files_removed} output files"));
+        }
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        name = "scry_fonts",
+        description = "List the font families the renderer can use, so text can name a font-family that will actually render. Unknown families fall back to the default."
+    )]
+    async fn scry_fonts(&self) -> Result<CallToolResult, rmcp::ErrorData> {
+        const MAX_FAMILIES: usize = 200;
+        let families = render::font_families();
+        let mut text = format!(
+            "Default (no font-family): {}\nFamilies: {}\n",
+            self.state.render.default_font_family_name(),
+            families.len()
+        );
+        for family in families.iter().take(MAX_FAMILIES) {
+            text.push_str(&format!("\n{family}"));
+        }
+        if families.len() > MAX_FAMILIES {
+            text.push_str(&format!("\n... and {} more", families.len() - MAX_FAMILIES));
+        }
+        Ok(CallToolResult::success(vec![Content::text(text)]))
+    }
+
+    #[tool(
+        name = "scry_ping",
+        description = "Health check: runs a tiny Python snippet and renders a tiny SVG. Returns ok plus the server version, or a diagnostic naming the failing subsystem."
+    )]
+    async fn scry_ping(&self) -> Result<CallToolResult, rmcp::ErrorData> {
+        let mut failures = Vec::new();
+
+        // Throwaway namespace so the check never touches a real board. With
+        // execution disabled the interpreter is deliberately left alone.
+        let python_check = if self.state.sandbox.disable_execution {
+            Ok(())
+        } else {
+            match self.state.new_namespace(1, 1, None).await {
+                Ok(ns) => python::run_python(ns, "print('ok')".into(), 1, 1, None, None, &self.state.sandbox)
+                    .await
+                    .and_then(|(result, _)| match result.stdout.trim() {
+                        "ok" => Ok(()),
+                        other => Err(ScryError::Internal(format!("unexpected stdout: {other:?}"))),
+                    }),
+                Err(e) => Err(e),
+            }
+        };
+        if let Err(e) = python_check {
+            failures.push(format!("python: {e}"));
+        }
+
+        const PING_SVG: &str =
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="1" height="1"><rect width="1" height="1"/></svg>"#;
+        if let Err(e) = self.state.run_render(|| render::svg_to_png(PING_SVG)).await {
+            failures.push(format!("render: {e}"));
+        }
+
+        if failures.is_empty() {
+            Ok(CallToolResult::success(vec![Content::text(format!(
+                "ok scry-mcp {}",
+                env!("CARGO_PKG_VERSION")
+            ))]))
+        } else {
+            Ok(error_result(ScryError::Internal(format!(
+                "scry-mcp {} unhealthy\n{}",
+                env!("CARGO_PKG_VERSION"),
+                failures.join("\n")
+            ))))
+        }
+    }
+
+    #[tool(
+        name = "render_svg",
+        description = "Render an SVG document to PNG and return it, without creating or updating a board (nothing appears in the gallery). For quick previews; no Python runs."
+    )]
+    async fn render_svg(
+        &self,
+        Parameters(params): Parameters<RenderSvgParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        const MAX_SVG_LEN: usize = 5_000_000;
+        if params.svg.len() > MAX_SVG_LEN {
+            return Ok(error_result(ScryError::TooLarge(format!(
+                "SVG too large ({} bytes, max {MAX_SVG_LEN})",
+                params.svg.len()
+            ))));
+        }
+        if params.width == Some(0) || params.height == Some(0) {
+            return Ok(error_result(ScryError::InvalidParams("Width and height must be greater than zero".into())));
+        }
+        let RenderSvgParams { svg, width, height } = params;
+        let config = self.state.render.clone();
+        let out = match self
+            .state
+            .run_render(move || render::svg_to_png_fit(&svg, render::Quality::default(), width, height, &config))
+            .await
+        {
+            Ok(out) => out,
+            Err(e) => return Ok(scry_error_result(&e, format!("SVG render failed: {e}"))),
+        };
+        let mut text = format!("Rendered: {}x{} ({} bytes, not stored)", out.width, out.height, out.png.len());
+        if !out.warnings.is_empty() {
+            text.push_str(&format!("\n\n--- warnings ---\n{}", out.warnings.join("\n")));
+        }
+        Ok(CallToolResult::success(vec![
+            Content::image(BASE64.encode(&out.png), "image/png"),
+            Content::text(text),
+        ]))
+    }
+
+    #[tool(
+        name = "whiteboard_diff",
+        description = "Compare a board's current render with its previous snapshot. Returns a highlight image (changed pixels red, unchanged washed-out gray) and the percentage of pixels that changed."
+    )]
+    async fn whiteboard_diff(
+        &self,
+        Parameters(params): Parameters<BoardNameParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let name = params.name;
+        let (before, after) = {
+            let boards = self.state.boards.read().await;
+            let Some(board) = boards.get(&name) else {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board not found: {name}"
+                ))));
+            };
+            let Some(prev) = board.history.last() else {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board {name} has no previous snapshot to compare against"
+                ))));
+            };
+            (prev.png.clone(), board.png.clone())
+        }; // read lock released before decoding
+
+        let diff = match self.state.run_render(move || render::diff_png(&before, &after)).await {
+            Ok(diff) => diff,
+            Err(e) => return Ok(scry_error_result(&e, format!("Diff failed: {e}"))),
+        };
+        let mut text = format!(
+            "Board: {name}\nChanged: {:.2}% ({} of {} pixels)",
+            diff.changed as f64 * 100.0 / diff.total.max(1) as f64,
+            diff.changed,
+            diff.total
+        );
+        if diff.size_mismatch {
+            text.push_str("\nNote: dimensions changed; images aligned top-left and non-overlapping area counts as changed");
+        }
+        Ok(CallToolResult::success(vec![
+            Content::image(BASE64.encode(&diff.png), "image/png"),
+            Content::text(text),
+        ]))
+    }
+
+    #[tool(
+        name = "whiteboard_get",
+        description = "Fetch a board's current rendered PNG and full SVG source without re-running any code."
+    )]
+    async fn whiteboard_get(
+        &self,
+        Parameters(params): Parameters<BoardNameParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let name = params.name;
+
+        // Copy what we need under the read lock, encode after releasing it
+        let (info, png) = {
+            let boards = self.state.boards.read().await;
+            let Some(board) = boards.get(&name) else {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board not found: {name}"
+                ))));
+            };
+            if board.png.is_empty() {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board {name} has no render yet. Call svg() from the whiteboard tool first."
+                ))));
+            }
+
+            let mut info = format!(
+                "Board: {}\nSize: {}x{}\nCreated: {}\nUpdated: {}\nHistory: {} snapshots",
+                board.name,
+                board.width,
+                board.height,
+                board.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                board.updated_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                board.history.len(),
+            );
+            if let Some(seed) = board.seed {
+                info.push_str(&format!("\nSeed: {seed}"));
+            }
+            if let Some(url) = self.state.board_url(&board.name) {
+                info.push_str(&format!("\nURL: {url}"));
+            }
+            info.push_str(&format!("\n\n--- SVG ---\n{}", board.svg));
+            (info, board.png.clone())
+        }; // read lock released
+
+        Ok(CallToolResult::success(vec![
+            Content::image(BASE64.encode(&png), "image/png"),
+            Content::text(info),
+        ]))
+    }
+
+    #[tool(
+        name = "whiteboard_pdf",
+        description = "Export a board's current SVG as a single-page vector PDF (application/pdf). Also writes {name}.pdf to the output directory when one is configured."
+    )]
+    async fn whiteboard_pdf(
+        &self,
+        Parameters(params): Parameters<BoardNameParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let name = params.name;
+
+        let svg = {
+            let boards = self.state.boards.read().await;
+            let Some(board) = boards.get(&name) else {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board not found: {name}"
+                ))));
+            };
+            if board.svg.is_empty() {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board {name} has no render yet. Call svg() from the whiteboard tool first."
+                ))));
+            }
+            board.svg.clone()
+        }; // read lock released
+
+        let config = self.state.render.clone();
+        let pdf = match self.state.run_render(move || render::svg_to_pdf(&svg, &config)).await {
+            Ok(bytes) => bytes,
+            Err(e) => return Ok(scry_error_result(&e, format!("PDF export failed: {e}"))),
+        };
+
+        let mut text = format!("Board: {name}\nPDF: {} bytes", pdf.len());
+        if let Some(ref dir) = self.state.output_dir {
+            let pdf_file = dir.join(format!("{}.pdf", board_file_stem(&name)));
+            match std::fs::write(&pdf_file, &pdf) {
+                Ok(()) => text.push_str(&format!("\nFile: {}", pdf_file.display())),
+                Err(e) => tracing::warn!("Failed to write {}: {e}", pdf_file.display()),
+            }
+        }
+
+        Ok(CallToolResult::success(vec![
+            Content::resource(ResourceContents::BlobResourceContents {
+                uri: format!("scry://board/{}/pdf", url_encode(&name)),
+                mime_type: Some("application/pdf".into()),
+                blob: BASE64.encode(&pdf),
+                meta: None,
+            }),
+            Content::text(text),
+        ]))
+    }
+
+    #[tool(
+        name = "whiteboard_export_all",
+        description = "Back up every board as one zip archive (application/zip): {name}.png and {name}.svg per board, plus {name}.vars.json with the board's variables when include_namespace is true."
+    )]
+    async fn whiteboard_export_all(
+        &self,
+        Parameters(params): Parameters<ExportAllParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let include_namespace = params.include_namespace.unwrap_or(false);
+
+        let (mut entries, namespaces) = {
+            let boards = self.state.boards.read().await;
+            let mut list: Vec<&Board> = boards.values().collect();
+            list.sort_by(|a, b| a.name.cmp(&b.name));
+            let entries: Vec<board::ExportBoard> = list
+                .iter()
+                .map(|b| board::ExportBoard {
+                    name: b.name.clone(),
+                    png: b.png.clone(),
+                    svg: b.svg.clone(),
+                    vars: None,
+                })
+                .collect();
+            let namespaces: Vec<_> = if include_namespace {
+                list.iter().map(|b| (b.name.clone(), b.namespace.clone_ref())).collect()
+            } else {
+                Vec::new()
+            };
+            (entries, namespaces)
+        }; // read lock released
+
+        if entries.is_empty() {
+            return Ok(error_result(ScryError::NotFound("No boards to export. Use the whiteboard tool to create one.".into())));
+        }
+        if include_namespace {
+            match python::namespaces_for_export(namespaces).await {
+                Ok(vars) => {
+                    for (entry, vars) in entries.iter_mut().zip(vars) {
+                        entry.vars = Some(vars);
+                    }
+                }
+                Err(e) => return Ok(scry_error_result(&e, format!("Export failed: {e}"))),
+            }
+        }
+
+        let count = entries.len();
+        let archive = match tokio::task::spawn_blocking(move || board::export_zip(&entries)).await {
+            Ok(Ok(bytes)) => bytes,
+            Ok(Err(e)) => return Ok(scry_error_result(&e, format!("Export failed: {e}"))),
+            Err(e) => {
+                return Ok(error_result(ScryError::Internal(format!(
+                    "Export failed: task join error: {e}"
+                ))));
+            }
+        };
+        if archive.len() > board::MAX_EXPORT_BYTES {
+            return Ok(error_result(ScryError::TooLarge(format!(
+                "Export too large ({} bytes, max {}). Delete or clear boards you no longer need.",
+                archive.len(),
+                board::MAX_EXPORT_BYTES
+            ))));
+        }
+
+        let text = format!("Exported {count} boards: {} bytes", archive.len());
+        Ok(CallToolResult::success(vec![
+            Content::resource(ResourceContents::BlobResourceContents {
+                uri: "scry://export/boards.zip".into(),
+                mime_type: Some("application/zip".into()),
+                blob: BASE64.encode(&archive),
+                meta: None,
+            }),
+            Content::text(text),
+        ]))
+    }
+
+    #[tool(
+        name = "whiteboard_import",
+        description = "Reload boards from a whiteboard_export_all zip, e.g. after a restart: each {name}.svg is rendered again and each {name}.vars.json repopulates a fresh namespace, so later whiteboard calls can keep using those variables. Existing boards are skipped unless replace is true."
+    )]
+    async fn whiteboard_import(
+        &self,
+        Parameters(params): Parameters<ImportParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let bytes = match BASE64.decode(params.archive.trim()) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                return Ok(error_result(ScryError::InvalidParams(format!(
+                    "archive is not valid base64: {e}"
+                ))));
+            }
+        };
+        if bytes.len() > board::MAX_EXPORT_BYTES {
+            return Ok(error_result(ScryError::TooLarge(format!(
+                "Archive too large ({} bytes, max {})",
+                bytes.len(),
+                board::MAX_EXPORT_BYTES
+            ))));
+        }
+        let entries = match tokio::task::spawn_blocking(move || board::import_zip(&bytes)).await {
+            Ok(Ok(entries)) => entries,
+            Ok(Err(msg)) => return Ok(error_result(ScryError::InvalidParams(msg))),
+            Err(e) => {
+                return Ok(error_result(ScryError::Internal(format!(
+                    "Import failed: task join error: {e}"
+                ))));
+            }
+        };
+        if entries.is_empty() {
+            return Ok(error_result(ScryError::InvalidParams("No boards found in the archive (expected {name}.svg / {name}.vars.json files)".into())));
+        }
+
+        let replace = params.replace.unwrap_or(false);
+        let mut imported = Vec::new();
+        let mut skipped = Vec::new();
+        let mut failed = Vec::new();
+        for entry in entries {
+            let name = entry.name;
+            let _board_guard = self.state.lock_board(&name).await;
+            let exists = self.state.boards.read().await.contains_key(&name);
+            if exists && !replace {
+                skipped.push(name);
+                continue;
+            }
+
+            // The SVG is the source of truth; sanitize and render it like a fresh run
+            let (svg, png, thumb_png, size) = if entry.svg.is_empty() {
+                (String::new(), Vec::new(), Vec::new(), None)
+            } else {
+                let (svg, _) = svg_util::sanitize(&entry.svg);
+                let out = match self.state.render_png(svg.clone(), render::Quality::default()).await {
+                    Ok(out) => out,
+                    Err(e) => {
+                        failed.push(format!("{name}: {e}"));
+                        continue;
+                    }
+                };
+                let (png, thumb_png) = match self
+                    .state
+                    .run_render(move || {
+                        let thumb = render::make_thumbnail(&out.png, render::THUMB_MAX_EDGE).unwrap_or_default();
+                        Ok((out.png, thumb))
+                    })
+                    .await
+                {
+                    Ok(pair) => pair,
+                    Err(e) => {
+                        failed.push(format!("{name}: {e}"));
+                        continue;
+                    }
+                };
+                (svg, png, thumb_png, Some((out.width, out.height)))
+            };
+            let (w, h) = size.unwrap_or((self.state.render.default_width, self.state.render.default_height));
+            let vars = entry.vars.unwrap_or_default();
+            let var_count = vars.len();
+            let namespace = match self.state.new_namespace(w, h, Some(vars)).await {
+                Ok(ns) => ns,
+                Err(e) => {
+                    failed.push(format!("{name}: {e}"));
+                    continue;
+                }
+            };
+
+            let now = Utc::now();
+            {
+                let mut boards = self.state.boards.write().await;
+                match boards.get_mut(&name) {
+                    Some(board) => {
+                        board.push_history();
+                        board.svg = svg;
+                        board.png = png;
+                        board.thumb_png = thumb_png;
+                        board.namespace = namespace;
+                        board.width = w;
+                        board.height = h;
+                        board.seed = None;
+                        board.last_error = None;
+                        board.updated_at = now;
+                    }
+                    None => {
+                        let share_token = self.state.issue_share_token(&name);
+                        boards.insert(
+                            name.clone(),
+                            Board {
+                                name: name.clone(),
+                                width: w,
+                                height: h,
+                                svg,
+                                png,
+                                thumb_png,
+                                namespace,
+                                created_at: now,
+                                updated_at: now,
+                                history: Vec::new(),
+                                seed: None,
+                                tags: Vec::new(),
+                                pinned: false,
+                                last_error: None,
+                                description: None,
+                                share_token,
+                            },
+                        );
+                    }
+                }
+            }
+            let event_type = if exists { BoardEventType::Updated } else { BoardEventType::Created };
+            self.state.publish_render(&name, event_type, size, true);
+            imported.push(format!("{name} ({var_count} variables)"));
+        }
+
+        let mut text = format!("Imported {} boards", imported.len());
+        if !imported.is_empty() {
+            text.push_str(&format!(": {}", imported.join(", ")));
+        }
+        if !skipped.is_empty() {
+            text.push_str(&format!("\nSkipped (already exist; pass replace=true): {}", skipped.join(", ")));
+        }
+        if !failed.is_empty() {
+            text.push_str(&format!("\nFailed:\n{}", failed.join("\n")));
+        }
+        Ok(if imported.is_empty() && !failed.is_empty() {
+            CallToolResult::error(vec![Content::text(text)])
+        } else {
+            CallToolResult::success(vec![Content::text(text)])
+        })
+    }
+
+    #[tool(
+        name = "whiteboard_contact_sheet",
+        description = "One-image overview of the session: every rendered board's current PNG, scaled to a uniform cell and labelled with its name, in a grid sorted by name. A sheet holds up to 22 rows; use limit/offset to page through more boards."
+    )]
+    async fn whiteboard_contact_sheet(
+        &self,
+        Parameters(params): Parameters<ContactSheetParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let columns = params.columns.unwrap_or(4);
+        if !(1..=12).contains(&columns) {
+            return Ok(error_result(ScryError::InvalidParams("columns must be between 1 and 12".into())));
+        }
+
+        let capacity = render::contact_sheet_capacity(columns);
+        let limit = params.limit.unwrap_or(capacity);
+        if !(1..=capacity).contains(&limit) {
+            return Ok(error_result(ScryError::InvalidParams(format!(
+                "limit must be between 1 and {capacity} at {columns} columns"
+            ))));
+        }
+        let offset = params.offset.unwrap_or(0);
+
+        let (total, tiles): (usize, Vec<(String, Vec<u8>)>) = {
+            let boards = self.state.boards.read().await;
+            let mut list: Vec<&Board> = boards.values().filter(|b| !b.png.is_empty()).collect();
+            list.sort_by(|a, b| a.name.cmp(&b.name));
+            let page = list.iter().skip(offset).take(limit).map(|b| (b.name.clone(), b.png.clone())).collect();
+            (list.len(), page)
+        }; // read lock released before compositing
+
+        if total == 0 {
+            return Ok(error_result(ScryError::NotFound("No rendered boards yet. Use the whiteboard tool to create one.".into())));
+        }
+        if tiles.is_empty() {
+            return Ok(error_result(ScryError::NotFound(format!(
+                "No boards at offset {offset} ({total} rendered)."
+            ))));
+        }
+        let count = tiles.len();
+        let config = self.state.render.clone();
+        let sheet = match self
+            .state
+            .run_render(move || {
+                let refs: Vec<(&str, &[u8])> =
+                    tiles.iter().map(|(name, png)| (name.as_str(), png.as_slice())).collect();
+                render::contact_sheet(&refs, columns, &config)
+            })
+            .await
+        {
+            Ok(bytes) => bytes,
+            Err(e) => return Ok(scry_error_result(&e, format!("Contact sheet failed: {e}"))),
+        };
+
+        let mut text = match render::png_dimensions(&sheet) {
+            Some((w, h)) => format!("Contact sheet: {count} boards, {w}x{h} ({} bytes)", sheet.len()),
+            None => format!("Contact sheet: {count} boards ({} bytes)", sheet.len()),
+        };
+        if count < total {
+            text.push_str(&format!("\nShowing boards {}-{} of {total}", offset + 1, offset + count));
+            if offset + count < total {
+                text.push_str(&format!("; next page: offset={}", offset + count));
+            }
+        }
+        Ok(CallToolResult::success(vec![
+            Content::image(BASE64.encode(&sheet), "image/png"),
+            Content::text(text),
+        ]))
+    }
+
+    #[tool(
+        name = "whiteboard_apng",
+        description = "Animate a board's history: every stored snapshot plus the current render, oldest first, as an animated PNG (image/apng) with full color. Also writes {name}.apng to the output directory when one is configured."
+    )]
+    async fn whiteboard_apng(
+        &self,
+        Parameters(params): Parameters<ApngParams>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let name = params.name;
+        let delay_ms = params.delay_ms.unwrap_or(500);
+        if !(10..=10_000).contains(&delay_ms) {
+            return Ok(error_result(ScryError::InvalidParams("delay_ms must be between 10 and 10000".into())));
+        }
+
+        let frames: Vec<Vec<u8>> = {
+            let boards = self.state.boards.read().await;
+            let Some(board) = boards.get(&name) else {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board not found: {name}"
+                ))));
+            };
+            if board.png.is_empty() {
+                return Ok(error_result(ScryError::NotFound(format!(
+                    "Board {name} has no render yet. Call svg() from the whiteboard tool first."
+                ))));
+            }
+            board
+                .history
+                .iter()
+                .map(|snap| snap.png.clone())
+                .chain(std::iter::once(board.png.clone()))
+                .collect()
+        }; // read lock released before encoding
+
+        let frame_count = frames.len();
+        let loops = params.loops.unwrap_or(0);
+        let apng = match self
+            .state
+            .run_render(move || {
+                let refs: Vec<&[u8]> = frames.iter().map(Vec::as_slice).collect();
+                render::frames_to_apng(&refs, delay_ms, loops)
+            })
+            .await
+        {
+            Ok(bytes) => bytes,
+            Err(e) => return Ok(scry_error_result(&e, format!("APNG export failed: {e}"))),
+        };
+
+        let mut text = format!(
+            "Board: {name}\nAPNG: {frame_count} frames, {delay_ms} ms each, {} bytes",
+            apng.len()
+        );
+        if let Some(ref dir) = self.state.output_dir {
+            let apng_file = dir.join(format!("{}.apng", board_file_stem(&name)));
+            match std::fs::write(&apng_file, &apng) {
+                Ok(()) => text.push_str(&format!("\nFile: {}", apng_file.display())),
+                Err(e) => tracing::warn!("Failed to write {}: {e}", apng_file.display()),
+            }
+        }
+
+        Ok(CallToolResult::success(vec![
+            Content::image(BASE64.encode(&apng), "image/apng"),
+            Content::text(text),
+        ]))
+    }
+}
+
+#[prompt_router]
+impl ScryServer {
+    #[prompt(
+        name = "plot_function",
+        description = "Plot y = f(x) on a whiteboard. Expands to ready-to-run Python for the whiteboard tool."
+    )]
+    async fn plot_function(
+        &self,
+        Parameters(args): Parameters<PlotFunctionArgs>,
+    ) -> Result<Vec<PromptMessage>, rmcp::ErrorData> {
+        let parse = |v: Option<&str>, default: f64, what: &str| match v {
+            None => Ok(default),
+            Some(s) => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .ok_or_else(|| rmcp::ErrorData::invalid_params(format!("{what} must be a number"), None)),
+        };
+        let x_min = parse(args.x_min.as_deref(), -10.0, "x_min")?;
+        let x_max = parse(args.x_max.as_deref(), 10.0, "x_max")?;
+        if x_min >= x_max {
+            return Err(rmcp::ErrorData::invalid_params("x_min must be less than x_max", None));
+        }
+        let expression = args.expression.trim();
+        if expression.is_empty() || expression.contains('\n') {
+            return Err(rmcp::ErrorData::invalid_params(
+                "expression must be a single-line Python expression in x",
+                None,
+            ));
+        }
+        let code = plot_function_code(expression, x_min, x_max);
+        let board = args.board.unwrap_or_else(|| "plot".into());
+        Ok(whiteboard_prompt(
+            &board,
+            &format!("Plot y = {expression} for x in [{x_min}, {x_max}]."),
+            &code,
+        ))
+    }
+
+    #[prompt(
+        name = "bar_chart",
+        description = "Draw a bar chart from JSON data ({\"label\": value} or [[label, value], ...]). Expands to ready-to-run Python for the whiteboard tool."
+    )]
+    async fn bar_chart(
+        &self,
+        Parameters(args): Parameters<BarChartArgs>,
+    ) -> Result<Vec<PromptMessage>, rmcp::ErrorData> {
+        let data = parse_bar_data(&args.data).map_err(|e| rmcp::ErrorData::invalid_params(e, None))?;
+        let title = args.title.unwrap_or_default();
+        let code = bar_chart_code(&data, &title).map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+        let board = args.board.unwrap_or_else(|| "bar-chart".into());
+        Ok(whiteboard_prompt(
+            &board,
+            &format!("Draw a bar chart of {} values.", data.len()),
+            &code,
+        ))
+    }
 }
 
 #[tool_handler]
+#[prompt_handler]
 impl ServerHandler for ScryServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2025_03_26,
             capabilities: ServerCapabilities::builder()
                 .enable_tools()
+                .enable_prompts()
+                .enable_resources()
                 .build(),
             server_info: Implementation {
                 name: "scry-mcp".into(),
@@ -329,9 +2151,129 @@ impl ServerHandler for ScryServer {
             instructions: Some(
                 "Scry: computational scrying glass. Use 'whiteboard' to execute Python code \
                  that generates SVG visuals. Call svg('<svg>...</svg>') in your code to render. \
-                 Variables persist per board."
+                 Variables persist per board. Read the scry://examples resource for \
+                 runnable starting points."
                     .into(),
             ),
         }
     }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, rmcp::ErrorData> {
+        let mut examples = RawResource::new(EXAMPLES_URI, "examples");
+        examples.title = Some("Scry examples".into());
+        examples.description = Some(
+            "Runnable whiteboard snippets (sine wave, bar chart, spirograph) with the expected output"
+                .into(),
+        );
+        examples.mime_type = Some("text/markdown".into());
+        Ok(ListResourcesResult::with_all_items(vec![examples.no_annotation()]))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, rmcp::ErrorData> {
+        if request.uri != EXAMPLES_URI {
+            return Err(rmcp::ErrorData::resource_not_found(
+                format!("Unknown resource: {}", request.uri),
+                None,
+            ));
+        }
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: EXAMPLES_URI.into(),
+                mime_type: Some("text/markdown".into()),
+                text: examples_markdown(),
+                meta: None,
+            }],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::python::{self, SandboxConfig};
+    use pyo3::Python;
+
+    /// Run expanded prompt code on a fresh 400x300 board and return its SVG.
+    fn run_prompt_code(code: &str) -> String {
+        Python::attach(|py| {
+            let config = SandboxConfig::default();
+            let ns = python::create_namespace(py, 400, 300, &config).unwrap();
+            let result = python::execute_python(py, &ns, code, 400, 300, None, &config).unwrap();
+            result.svg_content.expect("prompt code should call svg()")
+        })
+    }
+
+    #[test]
+    fn test_error_result_codes() {
+        let result = error_result(ScryError::RateLimited("Slow down".into()));
+        assert_eq!(result.is_error, Some(true));
+        let structured = result.structured_content.unwrap();
+        assert_eq!(structured["code"], "rate_limited");
+        assert_eq!(structured["message"], "Slow down");
+        assert_eq!(result.content[0].as_text().unwrap().text, "Slow down");
+
+        let code = |err: ScryError| error_result(err).structured_content.unwrap()["code"].clone();
+        assert_eq!(code(ScryError::NotFound("Board not found: a".into())), "not_found");
+        assert_eq!(code(ScryError::InvalidParams("bad".into())), "invalid_params");
+        assert_eq!(code(ScryError::ExecutionDisabled("off".into())), "execution_disabled");
+        assert_eq!(code(ScryError::TooLarge("big".into())), "too_large");
+        assert_eq!(code(ScryError::NoSvg("no svg".into())), "no_svg");
+    }
+
+    #[test]
+    fn test_parse_bar_data() {
+        assert_eq!(
+            parse_bar_data(r#"{"apples": 3, "pears": 5.5}"#).unwrap(),
+            [("apples".to_string(), 3.0), ("pears".to_string(), 5.5)]
+        );
+        // Pairs keep their order; non-string labels are written as JSON
+        assert_eq!(
+            parse_bar_data(r#"[["Q2", -1], [2024, 7]]"#).unwrap(),
+            [("Q2".to_string(), -1.0), ("2024".to_string(), 7.0)]
+        );
+
+        let err = |data: &str| parse_bar_data(data).unwrap_err();
+        assert!(err("{apples: 3}").starts_with("data is not valid JSON"));
+        assert_eq!(err("{}"), "data is empty");
+        assert_eq!(err("[]"), "data is empty");
+        assert_eq!(err("5"), "data must be a JSON object or a list of [label, value] pairs");
+        assert_eq!(err(r#"[["a", 1, 2]]"#), "data list items must be [label, value] pairs");
+        assert_eq!(err(r#"["a", 1]"#), "data list items must be [label, value] pairs");
+        assert_eq!(err(r#"{"a": "3"}"#), r#"value for "a" is not a number"#);
+        assert_eq!(err(r#"[["b", null]]"#), r#"value for "b" is not a number"#);
+    }
+
+    #[test]
+    fn test_bar_chart_code_runs() {
+        let data = parse_bar_data(r#"[["<small>", 2], ["big", 10], ["neg", -3]]"#).unwrap();
+        let code = bar_chart_code(&data, r#"Sales "Q1" & more"#).unwrap();
+        assert!(!code.contains("__DATA__") && !code.contains("__TITLE__"));
+        let svg = run_prompt_code(&code);
+        assert_eq!(svg.matches("<rect").count(), 4, "background plus one bar per value");
+        assert!(svg.contains("&lt;small>"), "labels are escaped");
+        assert!(svg.contains(r#"Sales "Q1" &amp; more"#));
+        crate::render::svg_to_png(&svg).expect("bar chart should render");
+
+        // No title: no title text, bars still drawn
+        let svg = run_prompt_code(&bar_chart_code(&data, "").unwrap());
+        assert!(!svg.contains("font-size=\"20\""));
+    }
+
+    #[test]
+    fn test_plot_function_code_runs() {
+        let code = plot_function_code("1 / x", -2.0, 2.0);
+        assert!(code.contains("return 1 / x") && code.contains("-2.0, 2.0"));
+        let svg = run_prompt_code(&code);
+        // The pole at x = 0 splits the curve in two
+        assert_eq!(svg.matches("<polyline").count(), 2, "{svg}");
+        crate::render::svg_to_png(&svg).expect("plot should render");
+    }
 }
@@ -0,0 +1,648 @@
+use std::ops::Range;
+
+/// Elements whose character content is significant (rendered text, CSS,
+/// metadata). Everything inside them is copied through verbatim.
+const PRESERVE_ELEMENTS: &[&str] = &["text", "tspan", "textPath", "style", "script", "title", "desc"];
+
+/// Strip comments and insignificant whitespace from an SVG document.
+///
+/// Whitespace-only text between tags is dropped, and runs of whitespace inside
+/// tags (outside quoted attribute values) collapse to a single space. Content
+/// of text-bearing elements, CDATA sections, and attribute values is left
+/// untouched, so the rendered output is identical. Malformed input is passed
+/// through as-is from the point it stops making sense; the renderer reports it.
+pub fn minify(svg: &str) -> String {
+    let bytes = svg.as_bytes();
+    let mut out = String::with_capacity(svg.len());
+    // Depth inside PRESERVE_ELEMENTS; also counts nested elements within them
+    let mut preserve_depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'<' {
+            let end = svg[i..].find('<').map_or(svg.len(), |p| i + p);
+            let text = &svg[i..end];
+            if preserve_depth > 0 || !text.trim().is_empty() {
+                out.push_str(text);
+            }
+            i = end;
+            continue;
+        }
+
+        let rest = &svg[i..];
+        if rest.starts_with("<!--") {
+            match rest.find("-->") {
+                Some(p) => i += p + 3,
+                None => break, // unterminated comment: drop the remainder
+            }
+            continue;
+        }
+        let verbatim_end = if rest.starts_with("<![CDATA[") {
+            Some(rest.find("]]>").map_or(rest.len(), |p| p + 3))
+        } else if rest.starts_with("<?") {
+            Some(rest.find("?>").map_or(rest.len(), |p| p + 2))
+        } else if rest.starts_with("<!") {
+            // DOCTYPE, possibly with an internal subset
+            let gt = rest.find('>').unwrap_or(rest.len());
+            let close = if rest[..gt].contains('[') { "]>" } else { ">" };
+            Some(rest.find(close).map_or(rest.len(), |p| p + close.len()))
+        } else {
+            None
+        };
+        if let Some(len) = verbatim_end {
+            out.push_str(&rest[..len]);
+            i += len;
+            continue;
+        }
+
+        // Ordinary start/end tag
+        let Some(end) = tag_end(rest) else {
+            out.push_str(rest);
+            break;
+        };
+        let tag = &rest[..=end];
+        push_collapsed_tag(&mut out, tag);
+
+        let closing = tag.starts_with("</");
+        let self_closing = tag[..tag.len() - 1].trim_end().ends_with('/');
+        let name: String = tag[if closing { 2 } else { 1 }..]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != '/' && *c != '>')
+            .collect();
+        if closing {
+            preserve_depth = preserve_depth.saturating_sub(1);
+        } else if !self_closing && (preserve_depth > 0 || PRESERVE_ELEMENTS.contains(&name.as_str())) {
+            preserve_depth += 1;
+        }
+        i += end + 1;
+    }
+    out
+}
+
+/// Append a tag with whitespace runs outside quotes collapsed to one space and
+/// trailing whitespace before `>` / `/>` removed.
+fn push_collapsed_tag(out: &mut String, tag: &str) {
+    let mut quote = None;
+    let mut pending_space = false;
+    for c in tag.chars() {
+        if quote.is_none() && c.is_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space && c != '>' && !(c == '/' && quote.is_none()) {
+            out.push(' ');
+        }
+        pending_space = false;
+        match quote {
+            Some(q) if c == q => quote = None,
+            None if c == '"' || c == '\'' => quote = Some(c),
+            _ => {}
+        }
+        out.push(c);
+    }
+}
+
+/// Elements removed along with everything inside them: scripts, and
+/// containers that embed HTML or other documents.
+const ACTIVE_ELEMENTS: &[&str] = &["script", "foreignObject", "iframe", "embed", "object", "handler"];
+
+/// Attributes holding a URL, plus the animation attributes that can set one.
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "action", "formaction", "to", "from", "values"];
+
+/// URL schemes that run code when followed or loaded.
+const ACTIVE_SCHEMES: &[&str] = &["javascript:", "vbscript:", "data:text/html"];
+
+/// Remove active content: `<script>`, `<foreignObject>` and other embedding
+/// elements, `on*` event handler attributes, `javascript:`-style URLs, and
+/// DOCTYPEs with an internal subset (whose entities could expand to markup
+/// after this pass).
+///
+/// The raster path never runs scripts, but the raw SVG is served by the
+/// gallery and shown in its source view, where a browser would. Returns the
+/// cleaned document and how many items were removed. Comments, CDATA and
+/// other markup pass through unchanged.
+pub fn sanitize(svg: &str) -> (String, usize) {
+    let mut out = String::with_capacity(svg.len());
+    let mut removed = 0;
+    let mut i = 0;
+
+    while let Some(p) = svg[i..].find('<') {
+        out.push_str(&svg[i..i + p]);
+        i += p;
+        let rest = &svg[i..];
+        let skip_to = |end: &str| rest.find(end).map_or(rest.len(), |p| p + end.len());
+        let verbatim = if rest.starts_with("<!--") {
+            Some(skip_to("-->"))
+        } else if rest.starts_with("<![CDATA[") {
+            Some(skip_to("]]>"))
+        } else if rest.starts_with("<?") {
+            Some(skip_to("?>"))
+        } else {
+            None
+        };
+        if let Some(len) = verbatim {
+            out.push_str(&rest[..len]);
+            i += len;
+            continue;
+        }
+        if rest.get(..9).is_some_and(|d| d.eq_ignore_ascii_case("<!DOCTYPE")) {
+            let (len, has_subset) = doctype_len(rest);
+            if has_subset {
+                removed += 1;
+            } else {
+                out.push_str(&rest[..len]);
+            }
+            i += len;
+            continue;
+        }
+        let Some(end) = tag_end(rest) else {
+            out.push_str(rest);
+            return (out, removed);
+        };
+        let tag = &rest[..=end];
+        if tag.starts_with("<!") || tag.starts_with("</") {
+            out.push_str(tag);
+            i += end + 1;
+            continue;
+        }
+
+        let name_len = tag[1..]
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .map_or(tag.len() - 1, |n| n + 1);
+        let name = &tag[1..name_len];
+        if ACTIVE_ELEMENTS.iter().any(|e| local_name(name).eq_ignore_ascii_case(e)) {
+            removed += 1;
+            i += element_len(rest, end, name);
+            continue;
+        }
+
+        out.push_str(&tag[..name_len]);
+        removed += push_safe_attributes(&mut out, &tag[name_len..]);
+        i += end + 1;
+    }
+    out.push_str(&svg[i..]);
+    (out, removed)
+}
+
+/// Length of the DOCTYPE declaration at the start of `rest`, and whether it
+/// has an internal subset (`[...]`), where entities are declared.
+fn doctype_len(rest: &str) -> (usize, bool) {
+    let mut quote = None;
+    let mut in_subset = false;
+    let mut has_subset = false;
+    for (j, &b) in rest.as_bytes().iter().enumerate() {
+        match (quote, b) {
+            (Some(q), _) if b == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'"' | b'\'') => quote = Some(b),
+            (None, b'[') if !in_subset => (in_subset, has_subset) = (true, true),
+            (None, b']') if in_subset => in_subset = false,
+            (None, b'>') if !in_subset => return (j + 1, has_subset),
+            _ => {}
+        }
+    }
+    (rest.len(), has_subset)
+}
+
+/// Length of the element at the start of `rest`, whose start tag ends at
+/// `end`, through its matching close tag. Same-named descendants nest.
+fn element_len(rest: &str, end: usize, name: &str) -> usize {
+    if rest[..end].trim_end().ends_with('/') {
+        return end + 1;
+    }
+    let mut depth = 1;
+    let mut j = end + 1;
+    while let Some(p) = rest[j..].find('<') {
+        j += p;
+        let tail = &rest[j..];
+        let skip = if tail.starts_with("<!--") {
+            Some(tail.find("-->").map_or(tail.len(), |e| e + 3))
+        } else if tail.starts_with("<![CDATA[") {
+            Some(tail.find("]]>").map_or(tail.len(), |e| e + 3))
+        } else {
+            None
+        };
+        if let Some(len) = skip {
+            j += len;
+            continue;
+        }
+        let Some(close) = tag_end(tail) else {
+            break;
+        };
+        let tag = &tail[..=close];
+        let tag_name = tag[1..]
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .next()
+            .unwrap_or("");
+        if tag_name == name {
+            if tag.starts_with("</") {
+                depth -= 1;
+                if depth == 0 {
+                    return j + close + 1;
+                }
+            } else if !tag[..tag.len() - 1].trim_end().ends_with('/') {
+                depth += 1;
+            }
+        }
+        j += close + 1;
+    }
+    rest.len()
+}
+
+/// Whether a URL attribute value points at an [`ACTIVE_SCHEMES`] URL, the
+/// way a browser reads it: character references decoded, with whitespace
+/// and control characters ignored.
+fn is_active_url(value: &str) -> bool {
+    let decoded = decode_char_refs(value);
+    let normalized: String = decoded
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .flat_map(char::to_lowercase)
+        .collect();
+    ACTIVE_SCHEMES.iter().any(|scheme| normalized.contains(scheme))
+}
+
+/// Decode numeric character references and XML's predefined entities.
+fn decode_char_refs(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(p) = rest.find('&') {
+        out.push_str(&rest[..p]);
+        rest = &rest[p..];
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Presentation properties whose value is a color.
+const COLOR_PROPERTIES: &[&str] = &["fill", "stroke", "stop-color", "flood-color", "lighting-color", "color"];
+
+/// Rewrite every color value in an SVG through `map`.
+///
+/// Covers color presentation attributes (`fill`, `stroke`, `stop-color`, ...),
+/// the same properties in `style` attributes, and declarations inside
+/// `<style>` elements. `map` gets the trimmed value and returns its
+/// replacement, or None to keep it (`none`, `url(#...)` and so on). Returns
+/// the new document and how many values were replaced.
+pub fn map_colors(svg: &str, mut map: impl FnMut(&str) -> Option<String>) -> (String, usize) {
+    let mut out = String::with_capacity(svg.len());
+    let mut replaced = 0;
+    let mut i = 0;
+
+    while let Some(p) = svg[i..].find('<') {
+        out.push_str(&svg[i..i + p]);
+        i += p;
+        let rest = &svg[i..];
+        let skip_to = |end: &str| rest.find(end).map_or(rest.len(), |p| p + end.len());
+        let verbatim = if rest.starts_with("<!--") {
+            Some(skip_to("-->"))
+        } else if rest.starts_with("<![CDATA[") {
+            Some(skip_to("]]>"))
+        } else if rest.starts_with("<?") {
+            Some(skip_to("?>"))
+        } else {
+            None
+        };
+        if let Some(len) = verbatim {
+            out.push_str(&rest[..len]);
+            i += len;
+            continue;
+        }
+        let Some(end) = tag_end(rest) else {
+            out.push_str(rest);
+            return (out, replaced);
+        };
+        let tag = &rest[..=end];
+        i += end + 1;
+        if tag.starts_with("<!") || tag.starts_with("</") {
+            out.push_str(tag);
+            continue;
+        }
+
+        let name_len = tag[1..]
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .map_or(tag.len() - 1, |n| n + 1);
+        out.push_str(&tag[..name_len]);
+        let attrs = &tag[name_len..];
+        let mut copied = 0;
+        for attr in attributes(attrs).0 {
+            let Some(value) = attr.value else { continue };
+            let property = local_name(&attrs[attr.name]);
+            let new = if property == "style" {
+                let (css, n) = map_css_colors(&attrs[value.clone()], &mut map);
+                replaced += n;
+                (n > 0).then_some(css)
+            } else if COLOR_PROPERTIES.contains(&property) {
+                let new = map(attrs[value.clone()].trim());
+                replaced += usize::from(new.is_some());
+                new
+            } else {
+                None
+            };
+            if let Some(new) = new {
+                out.push_str(&attrs[copied..value.start]);
+                out.push_str(&new);
+                copied = value.end;
+            }
+        }
+        out.push_str(&attrs[copied..]);
+
+        let self_closing = tag[..tag.len() - 1].trim_end().ends_with('/');
+        if local_name(&tag[1..name_len]) == "style" && !self_closing {
+            let body = &svg[i..];
+            let close = body.find("</").unwrap_or(body.len());
+            let (css, n) = map_css_colors(&body[..close], &mut map);
+            out.push_str(&css);
+            replaced += n;
+            i += close;
+        }
+    }
+    out.push_str(&svg[i..]);
+    (out, replaced)
+}
+
+/// [`map_colors`] for CSS text: declarations of [`COLOR_PROPERTIES`], with
+/// any `!important` kept.
+fn map_css_colors(css: &str, map: &mut impl FnMut(&str) -> Option<String>) -> (String, usize) {
+    let mut out = String::with_capacity(css.len());
+    let mut replaced = 0;
+    for part in css.split_inclusive([';', '{', '}']) {
+        let body = part.trim_end_matches([';', '{', '}']);
+        let replacement = body.split_once(':').and_then(|(property, value)| {
+            if !COLOR_PROPERTIES.contains(&property.trim().to_ascii_lowercase().as_str()) {
+                return None;
+            }
+            let color = value.split('!').next().unwrap_or(value).trim();
+            let start = property.len() + 1 + (value.len() - value.trim_start().len());
+            map(color).map(|new| (start..start + color.len(), new))
+        });
+        match replacement {
+            Some((range, new)) => {
+                out.push_str(&part[..range.start]);
+                out.push_str(&new);
+                out.push_str(&part[range.end..]);
+                replaced += 1;
+            }
+            None => out.push_str(part),
+        }
+    }
+    (out, replaced)
+}
+
+/// Index of the `>` closing the tag at the start of `rest`, skipping quoted values.
+fn tag_end(rest: &str) -> Option<usize> {
+    let mut quote = None;
+    for (j, &b) in rest.as_bytes().iter().enumerate().skip(1) {
+        match (quote, b) {
+            (Some(q), _) if b == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'"' | b'\'') => quote = Some(b),
+            (None, b'>') => return Some(j),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Name without its namespace prefix (`svg:script` → `script`).
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// One attribute in a tag's attribute section, as byte ranges into it.
+struct Attribute {
+    /// From the whitespace before the attribute, which belongs to it, to its end
+    span: Range<usize>,
+    name: Range<usize>,
+    /// Inside the quotes, or the bare value; None for a valueless attribute
+    value: Option<Range<usize>>,
+}
+
+/// Split the part of a tag after its name into attributes. Also returns
+/// where the closing `/` or `>` (with any whitespace before it) starts.
+fn attributes(attrs: &str) -> (Vec<Attribute>, usize) {
+    let bytes = attrs.as_bytes();
+    let mut list = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let name_start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !matches!(bytes[i], b'=' | b'/' | b'>') {
+            i += 1;
+        }
+        if name_start == i {
+            // `/` or `>`: the rest of the tag
+            return (list, start);
+        }
+        let name = name_start..i;
+        let mut value = None;
+        let mut j = i;
+        while j < bytes.len() && bytes[j].is_ascii_whitespace() {
+            j += 1;
+        }
+        if j < bytes.len() && bytes[j] == b'=' {
+            j += 1;
+            while j < bytes.len() && bytes[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            match bytes.get(j) {
+                Some(&q @ (b'"' | b'\'')) => {
+                    let close = attrs[j + 1..].find(q as char).map_or(bytes.len(), |p| j + 1 + p);
+                    value = Some(j + 1..close);
+                    j = (close + 1).min(bytes.len());
+                }
+                _ => {
+                    let bare = j;
+                    while j < bytes.len() && !bytes[j].is_ascii_whitespace() && bytes[j] != b'>' {
+                        j += 1;
+                    }
+                    value = Some(bare..j);
+                }
+            }
+            i = j;
+        }
+        list.push(Attribute { span: start..i, name, value });
+    }
+    (list, bytes.len())
+}
+
+/// Copy a tag's attribute section, dropping `on*` attributes and URL
+/// attributes with an active scheme. Returns how many were dropped.
+fn push_safe_attributes(out: &mut String, attrs: &str) -> usize {
+    let (list, tail) = attributes(attrs);
+    let mut dropped = 0;
+    for attr in list {
+        let name = local_name(&attrs[attr.name]);
+        let is_handler = name.get(..2).is_some_and(|prefix| prefix.eq_ignore_ascii_case("on"));
+        let is_active_url = URL_ATTRIBUTES.iter().any(|a| name.eq_ignore_ascii_case(a))
+            && attr.value.is_some_and(|v| is_active_url(&attrs[v]));
+        if is_handler || is_active_url {
+            dropped += 1;
+        } else {
+            out.push_str(&attrs[attr.span]);
+        }
+    }
+    out.push_str(&attrs[tail..]);
+    dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::svg_to_png;
+
+    const VERBOSE: &str = r#"<?xml version="1.0"?>
+<!-- generated by a script -->
+<svg xmlns="http://www.w3.org/2000/svg"
+     width="200"   height="100">
+    <defs>
+        <linearGradient id="g"  x1="0" x2="1">
+            <stop offset="0" stop-color="red"/>
+            <stop offset="1" stop-color="blue" />
+        </linearGradient>
+    </defs>
+    <!-- background -->
+    <rect width="200" height="100" fill="url(#g)"/>
+    <text x="10" y="60" font-family="sans-serif" font-size="24">A <tspan fill="white">B</tspan> C</text>
+    <style><![CDATA[ rect { stroke: black; } ]]></style>
+</svg>
+"#;
+
+    #[test]
+    fn test_minify_strips_comments_and_whitespace() {
+        let min = minify(VERBOSE);
+        assert!(!min.contains("<!--"));
+        assert!(!min.contains('\n'));
+        assert!(min.contains(r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">"#));
+        assert!(min.contains(r#"<stop offset="1" stop-color="blue"/>"#));
+        // Text content and CDATA are untouched
+        assert!(min.contains(r#">A <tspan fill="white">B</tspan> C</text>"#));
+        assert!(min.contains("<![CDATA[ rect { stroke: black; } ]]>"));
+        assert!(min.len() < VERBOSE.len());
+    }
+
+    #[test]
+    fn test_minify_preserves_attribute_values() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M 0 0   L 10 10" data-x='a > b'/></svg>"#;
+        assert_eq!(minify(svg), svg);
+    }
+
+    #[test]
+    fn test_minify_renders_identically() {
+        let before = svg_to_png(VERBOSE).expect("original renders").png;
+        let after = svg_to_png(&minify(VERBOSE)).expect("minified renders").png;
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn test_sanitize_strips_scripts_and_handlers() {
+        let evil = r#"<svg xmlns="http://www.w3.org/2000/svg" onload="alert(1)" width="10" height="10"><script>alert('<b>')</script><rect width="10" height="10" fill="red" ONCLICK='x()' data-on="keep"/><svg:script href="x.js"/></svg>"#;
+        let (clean, removed) = sanitize(evil);
+        assert_eq!(
+            clean,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="red" data-on="keep"/></svg>"#
+        );
+        assert_eq!(removed, 4);
+        assert!(svg_to_png(&clean).is_ok());
+    }
+
+    #[test]
+    fn test_sanitize_leaves_clean_svg_alone() {
+        assert_eq!(sanitize(VERBOSE), (VERBOSE.to_string(), 0));
+        // Markup inside quoted values, comments and CDATA isn't parsed as tags
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><!-- <script> --><text data-x="<script>" opacity="1">on</text><style><![CDATA[ a::after { content: "<script>" } ]]></style></svg>"#;
+        assert_eq!(sanitize(svg), (svg.to_string(), 0));
+        // Entity declarations carrying markup are dropped
+        let (clean, removed) = sanitize(r#"<!DOCTYPE svg [<!ENTITY x "<script>alert(1)</script>">]><svg>&x;</svg>"#);
+        assert_eq!(removed, 1);
+        assert!(!clean.contains("script"));
+        // A DOCTYPE without an internal subset declares nothing and is kept
+        let svg = r#"<!DOCTYPE svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd"><svg/>"#;
+        assert_eq!(sanitize(svg), (svg.to_string(), 0));
+    }
+
+    #[test]
+    fn test_sanitize_drops_entity_subset_with_char_refs() {
+        // Character references expand to markup when the entity is declared
+        let evil = r#"<!DOCTYPE svg [<!ENTITY x "&#60;script&#62;alert(1)&#60;/script&#62;">]><svg xmlns="http://www.w3.org/2000/svg">&x;</svg>"#;
+        let (clean, removed) = sanitize(evil);
+        assert_eq!(removed, 1);
+        assert_eq!(clean, r#"<svg xmlns="http://www.w3.org/2000/svg">&x;</svg>"#);
+        // Without the declaration the reference no longer parses, so it can't render
+        assert!(svg_to_png(&clean).is_err());
+    }
+
+    #[test]
+    fn test_sanitize_strips_active_urls_and_embeds() {
+        let evil = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="10" height="10"><a href="javascript:alert(1)"><rect width="5" height="5"/></a><a xlink:href=" &#106;ava&#x09;script:alert(1)">x</a><a href="#top">ok</a><set attributeName="href" to="JAVASCRIPT:alert(1)"/><foreignObject width="10" height="10"><foreignObject></foreignObject><iframe xmlns="http://www.w3.org/1999/xhtml" src="x"/></foreignObject><rect width="10" height="10" fill="red"/></svg>"##;
+        let (clean, removed) = sanitize(evil);
+        assert_eq!(
+            clean,
+            r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="10" height="10"><a><rect width="5" height="5"/></a><a>x</a><a href="#top">ok</a><set attributeName="href"/><rect width="10" height="10" fill="red"/></svg>"##
+        );
+        assert_eq!(removed, 4);
+        assert!(svg_to_png(&clean).is_ok());
+    }
+
+    #[test]
+    fn test_map_colors_finds_attributes_and_css() {
+        let upper = |c: &str| crate::colors::parse(c).ok().map(|_| c.to_uppercase());
+        let (out, replaced) = map_colors(VERBOSE, upper);
+        assert_eq!(replaced, 4);
+        assert!(out.contains(r#"stop-color="RED""#));
+        assert!(out.contains(r#"<stop offset="1" stop-color="BLUE" />"#));
+        assert!(out.contains(r#"fill="url(#g)""#));
+        assert!(out.contains(r#"<tspan fill="WHITE">B</tspan>"#));
+        assert!(out.contains("rect { stroke: BLACK; }"));
+        assert_eq!(out.len(), VERBOSE.len(), "only the values change");
+
+        let svg = r#"<svg><rect style="fill:#f00; stroke-width:2; stroke: green !important" stroke="none"/></svg>"#;
+        let (out, replaced) = map_colors(svg, upper);
+        assert_eq!(replaced, 2);
+        assert!(out.contains(r#"style="fill:#F00; stroke-width:2; stroke: GREEN !important" stroke="none""#), "{out}");
+    }
+
+    #[test]
+    fn test_map_colors_remaps_palette() {
+        use crate::colors::Remap;
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="30" height="10"><rect width="10" height="10" fill="#d62728"/><rect x="10" width="10" height="10" fill="#2ca02c"/><rect x="20" width="10" height="10" fill="#80808080" stroke="none"/></svg>"##;
+        let (out, replaced) = map_colors(svg, |c| Remap::Deuteranopia.apply_str(c).ok());
+        assert_eq!(replaced, 3);
+        assert!(!out.contains("#d62728") && !out.contains("#2ca02c"), "{out}");
+        // Grays keep their value and alpha
+        assert!(out.contains(r##"fill="#80808080" stroke="none""##), "{out}");
+        assert!(crate::render::svg_to_png(&out).is_ok());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::board::{sanitize_filename, validate_board_name};
+use std::path::Path;
+
+/// Largest template file accepted, same as the whiteboard code limit.
+const MAX_TEMPLATE_BYTES: u64 = 1_000_000;
+
+/// Load `{name}.py` from the template directory.
+///
+/// Names are restricted to filename-safe characters, and the resolved path
+/// must stay inside `dir` even through symlinks.
+pub fn load(dir: &Path, name: &str) -> Result<String, String> {
+    validate_board_name(name).map_err(|e| e.replace("Board name", "Template name"))?;
+    if sanitize_filename(name) != name {
+        return Err(format!(
+            "Invalid template name {name:?}: only letters, digits, '.', '_' and '-' are allowed"
+        ));
+    }
+    let root = dir
+        .canonicalize()
+        .map_err(|e| format!("Template directory unavailable: {e}"))?;
+    let path = root
+        .join(format!("{name}.py"))
+        .canonicalize()
+        .map_err(|_| format!("Template not found: {name}"))?;
+    if !path.starts_with(&root) {
+        return Err(format!("Template not found: {name}"));
+    }
+    let len = std::fs::metadata(&path).map_err(|e| e.to_string())?.len();
+    if len > MAX_TEMPLATE_BYTES {
+        return Err(format!("Template {name} too large ({len} bytes, max {MAX_TEMPLATE_BYTES})"));
+    }
+    std::fs::read_to_string(&path).map_err(|e| format!("Failed to read template {name}: {e}"))
+}
+
+/// Names of the `.py` templates in `dir`, sorted.
+pub fn list(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            let name = file_name.strip_suffix(".py")?;
+            (sanitize_filename(name) == name && !name.starts_with('.')).then(|| name.to_string())
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// Replace `{{var}}` placeholders (inner whitespace allowed) with parameters.
+///
+/// Strings are inserted verbatim, so templates quote them themselves
+/// (`title = "{{title}}"`). Other values are inserted as Python literals:
+/// numbers as-is, booleans as `True`/`False`, null as `None`, and lists and
+/// objects as list/dict literals with quoted strings. A placeholder
+/// with no matching parameter is an error; unused parameters are ignored.
+pub fn substitute(
+    template: &str,
+    params: &serde_json::Map<String, serde_json::Value>,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let key = rest[start + 2..start + 2 + len].trim();
+        let value = params
+            .get(key)
+            .ok_or_else(|| format!("Template parameter {key:?} was not provided"))?;
+        out.push_str(&python_literal(value));
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn python_literal(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => nested_literal(other),
+    }
+}
+
+/// Python literal for a value inside a container, where strings need quotes.
+/// JSON string escapes are all valid in Python string literals.
+fn nested_literal(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Bool(true) => "True".into(),
+        serde_json::Value::Bool(false) => "False".into(),
+        serde_json::Value::Null => "None".into(),
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::String(s) => serde_json::Value::from(s.as_str()).to_string(),
+        serde_json::Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(nested_literal).collect();
+            format!("[{}]", items.join(", "))
+        }
+        serde_json::Value::Object(map) => {
+            let entries: Vec<String> = map
+                .iter()
+                .map(|(k, v)| format!("{}: {}", serde_json::Value::from(k.as_str()), nested_literal(v)))
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_substitute() {
+        let p = params(json!({"title": "Sales", "n": 3, "show": true, "pts": [[1, 2], [3, null]], "cfg": {"c": "a\"b"}}));
+        let out = substitute("t = '{{title}}'\nn = {{ n }}\nshow = {{show}}\npts = {{pts}}\ncfg = {{cfg}}", &p).unwrap();
+        assert_eq!(
+            out,
+            "t = 'Sales'\nn = 3\nshow = True\npts = [[1, 2], [3, None]]\ncfg = {\"c\": \"a\\\"b\"}"
+        );
+        assert!(substitute("x = {{missing}}", &p).unwrap_err().contains("missing"));
+        // Unterminated placeholders and plain braces pass through
+        assert_eq!(substitute("d = {'a': 1} {{", &p).unwrap(), "d = {'a': 1} {{");
+    }
+
+    #[test]
+    fn test_load_rejects_traversal() {
+        let dir = std::env::temp_dir().join(format!("scry-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("bars.py"), "svg('<svg/>')").unwrap();
+
+        assert_eq!(load(&dir, "bars").unwrap(), "svg('<svg/>')");
+        assert_eq!(list(&dir), vec!["bars"]);
+        for bad in ["../bars", "..", "a/b", ".hidden", "bars py", ""] {
+            assert!(load(&dir, bad).is_err(), "{bad:?} should be rejected");
+        }
+        assert!(load(&dir, "nope").unwrap_err().contains("not found"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
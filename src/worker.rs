@@ -0,0 +1,410 @@
+//! Python worker processes.
+//!
+//! The embedded interpreter has one GIL, so boards sharing it run one at a
+//! time. With `--python-workers N`, board namespaces live in N child
+//! processes (`scry-mcp python-worker`) instead, assigned round-robin as
+//! boards are created, and runs on different workers proceed in parallel.
+//!
+//! Each worker reads the [`SandboxConfig`] from its first stdin line, then
+//! answers one JSON [`Request`] per line with one JSON reply per line. A
+//! worker that dies is respawned on the next call; namespaces it held come
+//! back empty.
+
+use crate::error::ScryError;
+use crate::presets::{self, Preset};
+use crate::python::{self, ExecResult, Namespace, SandboxConfig, VarsReport};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::Mutex;
+
+/// Subcommand the server re-executes itself with to become a worker.
+pub const WORKER_SUBCOMMAND: &str = "python-worker";
+
+/// One call into a worker. `ns` ids come from the parent and are never reused.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Request {
+    Create {
+        ns: u64,
+        width: u32,
+        height: u32,
+        vars: Option<Map<String, Value>>,
+    },
+    Run {
+        ns: u64,
+        code: String,
+        width: u32,
+        height: u32,
+        seed: Option<u64>,
+        /// Style preset name, see [`presets::get`].
+        style: Option<String>,
+    },
+    Vars {
+        ns: u64,
+        clear: Vec<String>,
+    },
+    Export {
+        ns: u64,
+        board: String,
+    },
+    Release {
+        ns: u64,
+    },
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+enum Reply {
+    Done,
+    Ran(ExecResult),
+    Vars(VarsReport),
+    Exported(Map<String, Value>),
+}
+
+/// Entry point of `scry-mcp python-worker`. Serves requests until stdin closes.
+pub fn serve() -> anyhow::Result<()> {
+    use std::io::{BufRead, Write};
+
+    let mut lines = std::io::stdin().lock().lines();
+    let Some(first) = lines.next() else {
+        return Ok(());
+    };
+    let config: SandboxConfig = serde_json::from_str(&first?)?;
+    let mut namespaces = HashMap::new();
+    let mut stdout = std::io::stdout().lock();
+    for line in lines {
+        let request: Request = serde_json::from_str(&line?)?;
+        let reply = Python::attach(|py| handle(py, &config, &mut namespaces, request));
+        serde_json::to_writer(&mut stdout, &reply)?;
+        stdout.write_all(b"\n")?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+/// Apply one request to this worker's namespaces.
+fn handle(
+    py: Python<'_>,
+    config: &SandboxConfig,
+    namespaces: &mut HashMap<u64, Py<PyDict>>,
+    request: Request,
+) -> Result<Reply, ScryError> {
+    match request {
+        Request::Create { ns, width, height, vars } => {
+            let namespace = python::create_namespace(py, width, height, config)?;
+            if let Some(vars) = vars {
+                python::restore_namespace(py, &namespace, &vars, config)?;
+            }
+            namespaces.insert(ns, namespace);
+            Ok(Reply::Done)
+        }
+        Request::Run { ns, code, width, height, seed, style } => {
+            let namespace = match namespaces.entry(ns) {
+                std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+                std::collections::hash_map::Entry::Vacant(e) => {
+                    // Created on a worker that has since been respawned
+                    tracing::warn!("Namespace {ns} is not on this worker; starting it empty");
+                    e.insert(python::create_namespace(py, width, height, config)?)
+                }
+            };
+            if let Some(style) = style {
+                let preset = presets::get(&style).map_err(ScryError::Internal)?;
+                python::apply_style(py, namespace, preset)?;
+            }
+            python::execute_python(py, namespace, &code, width, height, seed, config).map(Reply::Ran)
+        }
+        Request::Vars { ns, clear } => match namespaces.get(&ns) {
+            Some(namespace) => Ok(Reply::Vars(python::inspect_vars(py, namespace, &clear)?)),
+            None => Ok(Reply::Vars(VarsReport {
+                vars: Map::new(),
+                skipped: Vec::new(),
+                cleared: Vec::new(),
+                not_found: clear,
+            })),
+        },
+        Request::Export { ns, board } => match namespaces.get(&ns) {
+            Some(namespace) => Ok(Reply::Exported(python::namespace_for_persistence(py, &board, namespace)?)),
+            None => Ok(Reply::Exported(Map::new())),
+        },
+        Request::Release { ns } => {
+            namespaces.remove(&ns);
+            Ok(Reply::Done)
+        }
+    }
+}
+
+/// Fixed set of worker processes, started with [`WorkerPool::start`].
+pub struct WorkerPool {
+    workers: Vec<Arc<Worker>>,
+    next_worker: AtomicUsize,
+    next_ns: AtomicU64,
+}
+
+impl WorkerPool {
+    /// A pool of `config.python_workers` workers, or None for in-process execution.
+    pub fn new(config: &SandboxConfig) -> Option<Self> {
+        if config.python_workers == 0 {
+            return None;
+        }
+        // Fall back to PATH lookup if the running binary can't be located
+        let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("scry-mcp"));
+        let count = config.python_workers;
+        let config = serde_json::to_string(config).expect("SandboxConfig serializes");
+        let workers = (0..count)
+            .map(|index| {
+                Arc::new(Worker {
+                    index,
+                    exe: exe.clone(),
+                    config: config.clone(),
+                    conn: Mutex::new(None),
+                })
+            })
+            .collect();
+        Some(WorkerPool {
+            workers,
+            next_worker: AtomicUsize::new(0),
+            next_ns: AtomicU64::new(1),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Spawn every worker and wait for it to answer, so a broken setup fails
+    /// at startup rather than on the first run.
+    pub async fn start(&self) -> Result<(), ScryError> {
+        for worker in &self.workers {
+            let mut conn = worker.conn.lock().await;
+            let mut started = worker.spawn().await?;
+            // Releasing an id that was never created is a no-op round trip
+            match started.round_trip(&Request::Release { ns: 0 }).await {
+                Ok(reply) => reply?,
+                Err(e) => {
+                    return Err(ScryError::Internal(format!(
+                        "Python worker {} did not start: {e}",
+                        worker.index
+                    )));
+                }
+            };
+            *conn = Some(started);
+        }
+        Ok(())
+    }
+
+    /// Create a namespace on the next worker, restoring `vars` into it.
+    pub async fn create(
+        &self,
+        width: u32,
+        height: u32,
+        vars: Option<Map<String, Value>>,
+    ) -> Result<Namespace, ScryError> {
+        let worker = &self.workers[self.next_worker.fetch_add(1, Ordering::Relaxed) % self.workers.len()];
+        let ns = self.next_ns.fetch_add(1, Ordering::Relaxed);
+        // Held across the call so a failed or cancelled create is released
+        let handle = RemoteNamespace {
+            worker: Arc::clone(worker),
+            id: ns,
+        };
+        worker.call(Request::Create { ns, width, height, vars }).await?;
+        Ok(Namespace::Remote(Arc::new(handle)))
+    }
+}
+
+struct Worker {
+    index: usize,
+    exe: PathBuf,
+    /// Serialized [`SandboxConfig`], sent as the first line to each process.
+    config: String,
+    /// None until spawned, and again after the process fails.
+    conn: Mutex<Option<Connection>>,
+}
+
+struct Connection {
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Worker {
+    async fn spawn(&self) -> Result<Connection, ScryError> {
+        let spawn_err = |e: std::io::Error| {
+            ScryError::Internal(format!("Failed to start Python worker {}: {e}", self.index))
+        };
+        let mut child = tokio::process::Command::new(&self.exe)
+            .arg(WORKER_SUBCOMMAND)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(spawn_err)?;
+        let mut stdin = child.stdin.take().expect("piped stdin");
+        let stdout = BufReader::new(child.stdout.take().expect("piped stdout"));
+        stdin.write_all(format!("{}\n", self.config).as_bytes()).await.map_err(spawn_err)?;
+        tracing::debug!("Python worker {} started (pid {:?})", self.index, child.id());
+        Ok(Connection {
+            _child: child,
+            stdin,
+            stdout,
+        })
+    }
+
+    /// Send one request and wait for its reply. Runs as its own task so a
+    /// cancelled caller can't leave a reply unread on the pipe.
+    async fn call(self: &Arc<Self>, request: Request) -> Result<Reply, ScryError> {
+        let worker = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut conn = worker.conn.lock().await;
+            if matches!(request, Request::Release { .. }) && conn.is_none() {
+                return Ok(Reply::Done); // nothing left to release
+            }
+            if conn.is_none() {
+                *conn = Some(worker.spawn().await?);
+            }
+            let result = conn.as_mut().expect("connected").round_trip(&request).await;
+            result.unwrap_or_else(|e| {
+                // Dropping the connection kills the process; the next call respawns it
+                *conn = None;
+                tracing::warn!("Python worker {} failed: {e}", worker.index);
+                Err(ScryError::Internal(format!(
+                    "Python worker {} failed ({e}); boards on it lost their variables",
+                    worker.index
+                )))
+            })
+        })
+        .await
+        .map_err(|e| ScryError::Internal(format!("Task join error: {e}")))?
+    }
+}
+
+impl Connection {
+    async fn round_trip(&mut self, request: &Request) -> std::io::Result<Result<Reply, ScryError>> {
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        self.stdin.write_all(line.as_bytes()).await?;
+        self.stdin.flush().await?;
+        let mut reply = String::new();
+        if self.stdout.read_line(&mut reply).await? == 0 {
+            return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "worker exited"));
+        }
+        Ok(serde_json::from_str(&reply)?)
+    }
+}
+
+/// A namespace held by a worker, released there when the last handle drops.
+pub struct RemoteNamespace {
+    worker: Arc<Worker>,
+    id: u64,
+}
+
+impl RemoteNamespace {
+    pub async fn run(
+        &self,
+        code: String,
+        width: u32,
+        height: u32,
+        seed: Option<u64>,
+        style: Option<&'static Preset>,
+    ) -> Result<ExecResult, ScryError> {
+        let request = Request::Run {
+            ns: self.id,
+            code,
+            width,
+            height,
+            seed,
+            style: style.map(|p| p.name.to_string()),
+        };
+        match self.worker.call(request).await? {
+            Reply::Ran(result) => Ok(result),
+            _ => Err(unexpected_reply()),
+        }
+    }
+
+    pub async fn inspect_vars(&self, clear: Vec<String>) -> Result<VarsReport, ScryError> {
+        match self.worker.call(Request::Vars { ns: self.id, clear }).await? {
+            Reply::Vars(report) => Ok(report),
+            _ => Err(unexpected_reply()),
+        }
+    }
+
+    pub async fn export(&self, board: String) -> Result<Map<String, Value>, ScryError> {
+        match self.worker.call(Request::Export { ns: self.id, board }).await? {
+            Reply::Exported(vars) => Ok(vars),
+            _ => Err(unexpected_reply()),
+        }
+    }
+}
+
+impl Drop for RemoteNamespace {
+    fn drop(&mut self) {
+        // Outside a runtime the process is exiting and takes the workers with it
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let worker = Arc::clone(&self.worker);
+            let ns = self.id;
+            runtime.spawn(async move {
+                let _ = worker.call(Request::Release { ns }).await;
+            });
+        }
+    }
+}
+
+fn unexpected_reply() -> ScryError {
+    ScryError::Internal("Python worker sent an unexpected reply".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_namespace_lifecycle() {
+        let config = SandboxConfig::default();
+        let mut namespaces = HashMap::new();
+        Python::attach(|py| {
+            let mut call = |request| handle(py, &config, &mut namespaces, request);
+            let vars: Map<String, Value> = serde_json::from_str(r#"{"n": 2}"#).unwrap();
+            assert!(matches!(call(Request::Create { ns: 1, width: 10, height: 10, vars: Some(vars) }), Ok(Reply::Done)));
+
+            let run = |ns, code: &str| Request::Run {
+                ns,
+                code: code.into(),
+                width: 10,
+                height: 10,
+                seed: None,
+                style: Some("dark".into()),
+            };
+            let Ok(Reply::Ran(result)) = call(run(1, "n += 1\nprint(n, STYLE_BG != '')")) else {
+                panic!("run failed");
+            };
+            assert_eq!(result.stdout, "3 True\n");
+            let Err(ScryError::Python(msg)) = call(run(1, "1/0")) else {
+                panic!("errors come back as ScryError");
+            };
+            assert!(msg.contains("ZeroDivisionError"), "{msg}");
+
+            let Ok(Reply::Exported(vars)) = call(Request::Export { ns: 1, board: "b".into() }) else {
+                panic!("export failed");
+            };
+            assert_eq!(vars["n"], 3);
+
+            // Unknown ids (a respawned worker) start empty instead of failing
+            let Ok(Reply::Ran(result)) = call(run(7, "print('n' in dir())")) else {
+                panic!("run failed");
+            };
+            assert_eq!(result.stdout, "False\n");
+
+            assert!(matches!(call(Request::Release { ns: 1 }), Ok(Reply::Done)));
+            let Ok(Reply::Vars(report)) = call(Request::Vars { ns: 1, clear: vec!["n".into()] }) else {
+                panic!("vars failed");
+            };
+            assert!(report.vars.is_empty());
+            assert_eq!(report.not_found, ["n"]);
+        });
+    }
+}
@@ -0,0 +1,142 @@
+//! End-to-end checks of `--python-workers`, driving the real binary over MCP
+//! stdio (workers are re-executions of the binary, so unit tests can't
+//! exercise them).
+//!
+//! The ignored benchmark compares concurrent-board latency with and without
+//! workers:
+//!
+//!     cargo test --release --test python_workers -- --ignored --nocapture
+
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::{Duration, Instant};
+
+struct Server {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Server {
+    fn start(args: &[&str]) -> Server {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_scry-mcp"))
+            .args(args)
+            .arg("--quiet")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("spawn scry-mcp");
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut server = Server {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+        };
+        server.request_all(vec![(
+            "initialize",
+            json!({"protocolVersion": "2025-03-26", "capabilities": {}, "clientInfo": {"name": "test", "version": "1"}}),
+        )]);
+        server.send(json!({"jsonrpc": "2.0", "method": "notifications/initialized"}));
+        server
+    }
+
+    fn send(&mut self, message: Value) {
+        writeln!(self.stdin, "{message}").unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    /// Send every request at once, then return each result with the time it
+    /// took to arrive, in request order.
+    fn request_all(&mut self, requests: Vec<(&str, Value)>) -> Vec<(Value, Duration)> {
+        let start = Instant::now();
+        let first_id = self.next_id;
+        for (method, params) in &requests {
+            let message = json!({"jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params});
+            self.next_id += 1;
+            self.send(message);
+        }
+        let mut results = vec![None; requests.len()];
+        while results.iter().any(Option::is_none) {
+            let mut line = String::new();
+            assert!(self.stdout.read_line(&mut line).unwrap() > 0, "server exited");
+            let message: Value = serde_json::from_str(&line).unwrap();
+            let Some(id) = message["id"].as_u64() else {
+                continue; // notification
+            };
+            results[(id - first_id) as usize] = Some((message["result"].clone(), start.elapsed()));
+        }
+        results.into_iter().map(Option::unwrap).collect()
+    }
+
+    fn whiteboard_all(&mut self, runs: &[(&str, &str)]) -> Vec<(Value, Duration)> {
+        let requests = runs
+            .iter()
+            .map(|(board, code)| ("tools/call", json!({"name": "whiteboard", "arguments": {"name": board, "code": code}})))
+            .collect();
+        self.request_all(requests)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn text(result: &Value) -> &str {
+    result["content"][0]["text"].as_str().unwrap_or_default()
+}
+
+#[test]
+fn test_workers_keep_board_state() {
+    let mut server = Server::start(&["--python-workers", "2"]);
+    let results = server.whiteboard_all(&[("a", "x = 1"), ("b", "x = 10"), ("c", "y = 'c'")]);
+    assert!(results.iter().all(|(r, _)| r["isError"] == false), "{results:?}");
+
+    // Each board keeps its own namespace, wherever it landed
+    let results = server.whiteboard_all(&[("a", "x += 1\nprint(x)"), ("b", "x += 1\nprint(x)"), ("c", "print('x' in dir(), y)")]);
+    let stdout: Vec<&str> = results.iter().map(|(r, _)| text(r).rsplit("--- stdout ---\n").next().unwrap()).collect();
+    assert_eq!(stdout, ["2\n", "11\n", "False c\n"]);
+
+    // Errors come back as tool errors, and the sandbox still applies
+    let results = server.whiteboard_all(&[("a", "open('/etc/passwd')")]);
+    assert_eq!(results[0].0["isError"], true);
+    assert!(text(&results[0].0).contains("NameError"), "{}", text(&results[0].0));
+
+    let results = server.request_all(vec![
+        ("tools/call", json!({"name": "whiteboard_vars", "arguments": {"name": "b"}})),
+        ("tools/call", json!({"name": "scry_ping", "arguments": {}})),
+    ]);
+    assert!(text(&results[0].0).contains("\"x\": 11"), "{}", text(&results[0].0));
+    assert!(text(&results[1].0).starts_with("ok"), "{}", text(&results[1].0));
+}
+
+/// Eight boards each run the same CPU-bound snippet at once.
+#[test]
+#[ignore]
+fn bench_concurrent_boards() {
+    const BOARDS: usize = 8;
+    let names: Vec<String> = (0..BOARDS).map(|i| format!("board-{i}")).collect();
+    let work = "total = sum(i * i for i in range(2_000_000))";
+    for args in [&[][..], &["--python-workers", "4"][..], &["--python-workers", "8"][..]] {
+        let mut server = Server::start(args);
+        // Create every namespace first so only execution is timed
+        server.whiteboard_all(&names.iter().map(|n| (n.as_str(), "pass")).collect::<Vec<_>>());
+
+        let runs: Vec<(&str, &str)> = names.iter().map(|n| (n.as_str(), work)).collect();
+        let mut latencies: Vec<Duration> = server.whiteboard_all(&runs).into_iter().map(|(_, t)| t).collect();
+        latencies.sort();
+        let label = if args.is_empty() { "in-process".to_string() } else { format!("{} workers", args[1]) };
+        println!(
+            "{label:>10}: {BOARDS} boards, fastest {:?}, median {:?}, slowest {:?}",
+            latencies[0],
+            latencies[BOARDS / 2],
+            latencies[BOARDS - 1]
+        );
+    }
+}
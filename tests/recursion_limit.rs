@@ -0,0 +1,59 @@
+//! `--recursion-limit` at its maximum must end deep recursion with
+//! `RecursionError`, not a native stack overflow. Driven through the `render`
+//! subcommand because the stack size comes from the binary's runtime.
+
+use std::process::Command;
+
+/// Highest value the flag accepts (`python::MAX_RECURSION_LIMIT`).
+const MAX_RECURSION_LIMIT: u32 = 20_000;
+
+fn render(code: &str, recursion_limit: u32) -> std::process::Output {
+    let dir = std::env::temp_dir().join(format!("scry-recursion-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join(format!("deep-{recursion_limit}.py"));
+    std::fs::write(&script, code).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_scry-mcp"))
+        .arg("--quiet")
+        .args(["--recursion-limit", &recursion_limit.to_string()])
+        .arg("render")
+        .arg("--code")
+        .arg(&script)
+        .arg("--out")
+        .arg(dir.join(format!("deep-{recursion_limit}.png")))
+        .output()
+        .expect("run scry-mcp render");
+    let _ = std::fs::remove_dir_all(&dir);
+    output
+}
+
+#[test]
+fn test_deep_recursion_at_max_limit() {
+    // repr() of a nested list recurses in C; a plain function recurses in Python
+    let code = r#"x = []
+for _ in range(100_000):
+    x = [x]
+try:
+    repr(x)
+except RecursionError:
+    print("repr: RecursionError")
+
+def f(n):
+    return f(n + 1)
+try:
+    f(0)
+except RecursionError:
+    print("call: RecursionError")
+svg('<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>')
+"#;
+    let output = render(code, MAX_RECURSION_LIMIT);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "exit {:?}: {}", output.status, String::from_utf8_lossy(&output.stderr));
+    assert_eq!(stdout, "repr: RecursionError\ncall: RecursionError\n");
+}
+
+#[test]
+fn test_recursion_limit_above_max_is_refused() {
+    let output = render("", MAX_RECURSION_LIMIT + 1);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--recursion-limit"));
+}